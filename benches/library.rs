@@ -0,0 +1,184 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Times what drawing the library page asks of storage, the book list and a
+//! thumbnail handle per card, on a scratch database:
+//!
+//! ```sh
+//! cargo bench --bench library -- --books 5000 --frames 50 --writer
+//! ```
+//!
+//! `--books` sets the books in the library, 1000 by default, `--frames` the
+//! loads timed after the first, 100 by default, and `--writer` stores
+//! chapters from another thread meanwhile
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use cosmic::widget::image::Handle;
+
+// The app is a binary only, so the bench builds the storage code itself
+#[allow(dead_code)]
+#[path = "../src/core/mod.rs"]
+mod core;
+
+use crate::core::cover::{Cover, CoverCache, CoverSize};
+use crate::core::data::DataManager;
+use crate::core::Book;
+
+fn main() -> Result<(), String> {
+    let mut books = 1000;
+    let mut frames = 100;
+    let mut writer = false;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+        match arg.as_str() {
+            "--books" => books = parse_number(&value()?)?,
+            "--frames" => frames = parse_number(&value()?)?.max(1),
+            "--writer" => writer = true,
+            // Passed by `cargo bench`
+            "--bench" => (),
+            arg => return Err(format!("unexpected argument {arg:?}")),
+        }
+    }
+
+    let dir = tempfile::Builder::new()
+        .prefix(concat!(env!("CARGO_PKG_NAME"), "-bench-"))
+        .tempdir()
+        .map_err(|e| e.to_string())?;
+    run_bench(dir.path(), books, frames, writer)
+}
+
+fn run_bench(
+    dir: &std::path::Path,
+    books: usize,
+    frames: usize,
+    writer: bool,
+) -> Result<(), String> {
+    let open = |dir: &std::path::Path| {
+        let data = DataManager::new();
+        match data.init(dir.to_path_buf()).into_iter().next() {
+            Some(e) => Err(e.to_string()),
+            None => Ok(data),
+        }
+    };
+
+    let seed = open(dir)?;
+    let cover = Cover::process(&sample_cover()?).map_err(|e| e.to_string())?;
+    let started = Instant::now();
+    for i in 0..books {
+        let book = Book {
+            source: "bench".into(),
+            url: format!("https://example.com/book/{i}"),
+            name: format!("Book {i}"),
+            in_library: true,
+            ..Default::default()
+        };
+        seed.set_book(&book).map_err(|e| e.to_string())?;
+        seed.set_cover(&book, &cover).map_err(|e| e.to_string())?;
+    }
+    eprintln!("stored {books} books in {:.2?}", started.elapsed());
+    drop(seed);
+
+    // Fresh caches, like the app just started
+    let data = open(dir)?;
+
+    // A second manager stands in for a download task with its own connections
+    let stop = Arc::new(AtomicBool::new(false));
+    let writes = writer.then(|| {
+        let dir = dir.to_path_buf();
+        let stop = Arc::clone(&stop);
+        std::thread::spawn(move || -> Result<usize, String> {
+            let data = open(&dir)?;
+            let content = "Lorem ipsum dolor sit amet. ".repeat(400);
+            let mut count = 0;
+            while !stop.load(Ordering::Relaxed) {
+                let url = format!("https://example.com/chapter/{count}");
+                data.set_chapter_content(&url, &content)
+                    .map_err(|e| e.to_string())?;
+                count += 1;
+            }
+            Ok(count)
+        })
+    });
+
+    // What the library asks of storage when it loads, then the cover lookups
+    // a frame makes
+    let mut covers = CoverCache::default();
+    let mut frame = |data: &DataManager| -> Result<Duration, String> {
+        let started = Instant::now();
+        let mut books = data.get_library_books().map_err(|e| e.to_string())?;
+        books.sort_by_key(|book| book.name.to_lowercase());
+        for book in &books {
+            if covers.get(&book.url, CoverSize::Thumbnail).is_some() {
+                continue;
+            }
+            if let Some(cover) = data.get_cover(&book.url).map_err(|e| e.to_string())? {
+                let bytes = cover.thumbnail;
+                let len = bytes.len();
+                covers.insert(
+                    &book.url,
+                    CoverSize::Thumbnail,
+                    Handle::from_memory(bytes),
+                    len,
+                );
+            }
+        }
+        for book in &books {
+            _ = covers.peek(&book.url, CoverSize::Thumbnail);
+        }
+        Ok(started.elapsed())
+    };
+
+    // The first load reads every cover from the database, later ones hit the cache
+    let cold = frame(&data);
+    let warm = cold.clone().and_then(|_| {
+        (0..frames)
+            .map(|_| frame(&data))
+            .collect::<Result<Vec<_>, _>>()
+    });
+
+    stop.store(true, Ordering::Relaxed);
+    let writes = writes
+        .map(|handle| handle.join().map_err(|_| "writer panicked".to_string())?)
+        .transpose()?;
+    let (cold, warm) = (cold?, warm?);
+
+    let total: Duration = warm.iter().sum();
+    println!("first load: {cold:.2?}");
+    println!(
+        "{frames} loads: mean {:.2?}, min {:.2?}, max {:.2?}",
+        total / frames as u32,
+        warm.iter().min().copied().unwrap_or_default(),
+        warm.iter().max().copied().unwrap_or_default()
+    );
+    let stats = covers.stats();
+    println!(
+        "cover cache: {} handles, {} bytes, {} hits, {} misses",
+        stats.entries, stats.bytes, stats.hits, stats.misses
+    );
+    if let Some(count) = writes {
+        println!("chapters stored meanwhile: {count}");
+    }
+    Ok(())
+}
+
+/// A cover-sized gradient, encoded as PNG like a downloaded cover would be
+fn sample_cover() -> Result<Vec<u8>, String> {
+    let image = image::RgbImage::from_fn(600, 900, |x, y| {
+        image::Rgb([(x % 256) as u8, (y % 256) as u8, ((x + y) % 256) as u8])
+    });
+    let mut bytes = std::io::Cursor::new(vec![]);
+    image::DynamicImage::ImageRgb8(image)
+        .write_to(&mut bytes, image::ImageOutputFormat::Png)
+        .map_err(|e| e.to_string())?;
+    Ok(bytes.into_inner())
+}
+
+fn parse_number(value: &str) -> Result<usize, String> {
+    value
+        .parse()
+        .map_err(|_| format!("expected a number, got {value:?}"))
+}
@@ -6,4 +6,238 @@ welcome = Welcome to COSMIC! ✨
 settings = Settings
 settings-context-title = Settings
 book-context-title = Book
-site = Site
\ No newline at end of file
+site = Site
+retry = Retry
+loading = Loading…
+chapters = Chapters
+chapters-empty = No chapters yet
+chapters-filtered-empty = No chapters match the filter
+book-details = Details
+book-chapter-stats = { $count ->
+    [one] One chapter
+   *[other] { $count } chapters
+} · { $read } read · { $downloaded } downloaded
+continue-reading = Continue reading
+download-all = Download all
+mark-read = Mark as read
+mark-unread = Mark as unread
+library-add = Add to library
+library-remove = Remove from library
+library-unread = { $count } unread
+library-selected = { $count } selected
+library-search = Search by name, or author:, tag:, status:, -tag: ...
+select-all = Select all
+select-none = Select none
+batch-remove = Remove
+batch-move = Move to…
+batch-download-unread = Download unread
+batch-refresh = Refresh
+batch-export = Export
+category-none = No category
+category-new = New category
+synopsis = Synopsis
+show-more = Show more
+show-less = Show less
+go = Go
+explore = Explore
+library = Library
+history = History
+history-empty = Books you read show up here
+history-chapter-gone = The chapter you were on is no longer in the book
+book = Book
+shortcut-unbound = Unbound
+
+settings-display = Display
+settings-theme = Theme
+settings-library = Library
+settings-sort-by = Sort by
+settings-library-view = View
+settings-unread-only = Unread only
+settings-downloaded-only = Downloaded only
+settings-completed-only = Completed only
+settings-source = Source
+settings-tag = Tag
+settings-category = Category
+settings-reader = Reader
+settings-layout = Layout
+settings-font = Font
+settings-font-size = Font size
+settings-line-height = Line height
+settings-paragraph-spacing = Paragraph spacing
+settings-column-width = Column width
+settings-margins = Margins
+settings-read-aloud = Read aloud
+settings-speech-engine = Speech engine
+settings-voice = Voice
+settings-tts-rate = Rate ({ $rate })
+settings-tts-pitch = Pitch ({ $pitch })
+settings-audio-format = Audio export format
+settings-sources = Sources
+settings-downloads = Downloads
+settings-parallel-downloads = Parallel downloads
+settings-prefetch-chapters = Chapters to download ahead
+settings-update-interval = Check for updates
+settings-shortcuts = Keyboard shortcuts
+settings-reset-shortcuts = Reset Shortcuts
+settings-network = Network
+settings-cache-page-ttl = Keep pages for
+settings-cache-image-ttl = Keep covers for
+settings-cache-size = Cache size limit
+settings-network-cache-used = { $size } MB cached
+settings-clear-network-cache = Clear Network Cache
+settings-diagnostics = Diagnostics
+settings-log-level = Log level
+settings-cover-cache = Cover cache
+settings-cover-cache-stats = { $count } covers, { $size } of { $budget } MB, { $hits } hits, { $misses } misses
+settings-export-logs = Export Logs
+settings-clear-storage = Clear Storage
+
+app-theme-system = Match system
+app-theme-dark = Dark
+app-theme-light = Light
+reader-theme-system = Match app
+reader-theme-light = Light
+reader-theme-sepia = Sepia
+reader-theme-dark = Dark
+reader-theme-black = OLED black
+reading-mode-scroll = Scroll
+reading-mode-paged = Pages
+sort-date-added = Date added
+sort-title = Title
+sort-last-read = Last read
+sort-last-updated = Last updated
+sort-unread = Unread chapters
+sort-source = Source
+library-view-covers = Covers
+library-view-compact = Compact
+library-view-list = List
+chapter-filter-all = All chapters
+chapter-filter-unread = Unread
+chapter-filter-downloaded = Downloaded
+chapter-filter-bookmarked = Bookmarked
+update-interval-never = Never
+update-interval-hours = { $hours ->
+    [one] Every hour
+   *[other] Every { $hours } hours
+}
+update-interval-daily = Daily
+duration-minutes = { $count ->
+    [one] 1 minute
+   *[other] { $count } minutes
+}
+duration-hours = { $count ->
+    [one] 1 hour
+   *[other] { $count } hours
+}
+duration-days = { $count ->
+    [one] 1 day
+   *[other] { $count } days
+}
+duration-weeks = { $count ->
+    [one] 1 week
+   *[other] { $count } weeks
+}
+duration-months = { $count ->
+    [one] 1 month
+   *[other] { $count } months
+}
+duration-years = { $count ->
+    [one] 1 year
+   *[other] { $count } years
+}
+size-megabytes = { $count } MB
+
+menu-next-chapter = Next chapter
+menu-previous-chapter = Previous chapter
+menu-page-down = Next page
+menu-page-up = Previous page
+menu-toggle-library = Add to or remove from library
+menu-search = Search
+menu-refresh = Refresh
+menu-next-nav-page = Next page in sidebar
+menu-previous-nav-page = Previous page in sidebar
+menu-back = Back
+menu-forward = Forward
+menu-fullscreen = Fullscreen
+menu-font-size-increase = Increase font size
+menu-font-size-decrease = Decrease font size
+menu-read-aloud = Read aloud
+voice-default = Default
+export-audio = Export Audio
+export-audio-from = From
+export-audio-to = To
+audio-exports = Audio exports
+audio-export-progress = { $name }: { $done } of { $total } chapters
+cancel = Cancel
+export-book = Export
+annotations = Bookmarks and highlights
+annotations-empty = Right-click a paragraph while reading to highlight it
+annotations-export = Export to Markdown
+annotation-note = Add a note
+annotation-passage = Words to highlight
+highlight = Highlight
+bookmark = Bookmark
+annotation-remove-highlight = Remove highlight
+annotation-remove-bookmark = Remove bookmark
+
+notifications = Notifications
+notifications-context-title = Notifications
+notifications-empty = Nothing to report
+notifications-clear = Clear
+notifications-age-seconds = { $count }s ago
+notifications-age-minutes = { $count }m ago
+notifications-age-hours = { $count }h ago
+notification-storage-cleared = Cleared storage
+notification-network-cache-cleared = Cleared network cache
+notification-search-results = Found { $count } results for “{ $term }”
+notification-library-added = Added { $name } to the library
+notification-library-removed = Removed { $name } from the library
+notification-logs-exported = Exported logs to { $path }
+notification-invalid-shortcut = “{ $shortcut }” isn't a valid shortcut, try something like Ctrl+Shift+N
+notification-shortcut-taken = “{ $shortcut }” is already the shortcut for { $action }, change that one first
+notification-audio-queued = Queued audio export of { $name }
+notification-audio-exported = Exported audio to { $path }
+notification-audio-cancelled = Cancelled audio export of { $name }
+notification-audio-no-chapters = No chapters in that range
+notification-annotation-missing = That chapter is no longer in the book
+notification-annotations-empty = Nothing to export yet
+notification-annotations-exported = Exported notes to { $path }
+notification-export-empty = No chapters of this book are stored yet, read or download some first
+notification-book-exported = Exported { $count ->
+    [one] one chapter
+   *[other] { $count } chapters
+} to { $path }
+notification-no-sources = No sources are enabled, turn one on in the settings
+notification-downloading = Downloading { $count ->
+    [one] one chapter
+   *[other] { $count } chapters
+} of { $name }
+notification-downloaded-all = Every chapter of { $name } is already downloaded
+notification-batch-removed = Removed { $count ->
+    [one] one book
+   *[other] { $count } books
+} from the library
+notification-batch-moved = Moved { $count ->
+    [one] one book
+   *[other] { $count } books
+} to { $category }
+notification-batch-read = Marked { $count ->
+    [one] one book
+   *[other] { $count } books
+} as read
+notification-batch-downloading = Downloading { $count ->
+    [one] one chapter
+   *[other] { $count } chapters
+} of { $books ->
+    [one] one book
+   *[other] { $books } books
+}
+
+error-network = Couldn't reach the site: { $reason }
+error-parse = Couldn't read the page, the site layout may have changed (missing { $selector })
+error-storage = Couldn't access local storage: { $reason }
+error-not-found = Not found: { $what }
+error-rate-limited = The site is limiting requests, try again in a moment
+error-unsupported-source = No source supports { $url }
+error-speech = Couldn't read aloud: { $reason }
+error-image = Couldn't read the cover image: { $reason }
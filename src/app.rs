@@ -1,19 +1,29 @@
 pub mod context;
+pub mod jobs;
+pub mod key_binds;
+pub mod notifications;
 pub mod pages;
+pub mod storage;
 pub mod utils;
 
 use std::any::Any;
 use std::collections::HashMap;
 
+use crate::config::{self, Config, CONFIG_VERSION};
+use crate::core::cover::{CoverCache, CoverSize};
 use crate::core::source::{self, *};
-use crate::core::{self, Book, Chapter};
+use crate::core::{self, Annotation, AnnotationKind, Book, Chapter};
 use crate::fl;
 use cosmic::app::{message, Command, Core};
 use cosmic::iced::alignment::{Horizontal, Vertical};
-use cosmic::iced::{Alignment, Length};
+use cosmic::iced::keyboard::{self, Key, Modifiers};
+use cosmic::iced::widget::scrollable::Viewport;
+use cosmic::iced::{event, window, Alignment, Event, Length, Subscription};
 use cosmic::widget::{self, *};
-use cosmic::{cosmic_theme, theme, ApplicationExt, Apply, Element};
+use cosmic::{cosmic_config, cosmic_theme, theme, ApplicationExt, Apply, Element};
+use menu::action::MenuAction as _;
 use segmented_button::Selectable;
+use serde::{Deserialize, Serialize};
 
 pub const REPOSITORY: &str = "https://github.com/Gibson431/web-reader";
 
@@ -29,19 +39,80 @@ pub struct App {
     key_binds: HashMap<menu::KeyBind, MenuAction>,
     /// A model that contains all of the pages assigned to the nav bar panel.
     nav: nav_bar::Model,
-    data_manager: core::data::DataManager,
-    pages: HashMap<Page, Box<dyn crate::app::pages::page::Page>>,
+    storage: storage::Storage,
+    /// The nav bar pages, kept for the whole session
+    pages: HashMap<Page, Box<dyn pages::page::Page>>,
+    /// Pages pushed over the active nav page, the last one is shown
+    stack: Vec<Box<dyn pages::page::Page>>,
+    /// Pages gone back from, the last one comes back first
+    forward: Vec<Box<dyn pages::page::Page>>,
 
     // Explore page
-    explore_input: String,
-    explore_results: Vec<String>,
+    /// Search results, replaced by the stored copy once storage has seen them
+    explore_results: Vec<Book>,
 
     // Library page
-    library_input: String,
-    library_results: Vec<String>,
+    /// Matches for the library search, kept between keystrokes
+    library_search: Option<pages::library::SearchResults>,
+    /// Library books as last loaded from storage
+    library: Vec<Book>,
+    /// What the library is sorted and filtered by, keyed by book id
+    library_stats: HashMap<String, core::LibraryStats>,
+    /// Choices of the library's source and tag filters, the first of each
+    /// shows every book
+    library_sources: Vec<String>,
+    library_tags: Vec<String>,
+    library_categories: Vec<String>,
 
     // Book page
-    book: Option<Book>,
+    /// The book in the context drawer or on the book page with its chapters
+    /// and annotations, `None` until storage answers
+    book: Option<context::book_context::BookDetails>,
+    /// Cover handles for the books on screen, loaded from storage on a miss
+    covers: CoverCache,
+
+    fullscreen: bool,
+    /// Modifier keys held, for shift-clicking a range of books
+    modifiers: Modifiers,
+    /// Speech engines to read aloud with
+    tts: core::tts::Backends,
+    /// Bumped whenever reading aloud stops or jumps, so late replies from the engine are dropped
+    tts_session: u64,
+    /// Cuts off what the current session is saying, replaced when it stops
+    tts_cancel: core::tts::Cancel,
+    /// Queued audio exports
+    jobs: jobs::Jobs,
+    /// Chapter range typed into the book drawer's audio export, 1-based
+    audio_from: String,
+    audio_to: String,
+    /// Position in [`core::export::all`] of the format picked in the book drawer
+    export_format: usize,
+    /// Voice picker entries for the configured speech engine, the first
+    /// stands for the engine default
+    tts_voices: Vec<String>,
+    /// Note typed into the reader's annotation bar
+    annotation_note: String,
+    /// Words of the selected paragraph to highlight, the whole paragraph
+    /// until cut down
+    annotation_passage: String,
+    /// Shortcuts being typed in the settings, applied on submit
+    key_bind_inputs: HashMap<MenuAction, String>,
+    /// Bytes in the network cache as last measured, see
+    /// [`App::measure_network_cache`]
+    network_cache_size: u64,
+
+    /// Toasts and the log of everything background tasks reported
+    notifications: notifications::Notifications,
+    /// Writes settings back to disk, missing if cosmic-config couldn't be opened
+    config_handler: Option<cosmic_config::Config>,
+    config: Config,
+}
+
+/// Passed from `main` so the config is loaded before the first frame
+#[derive(Clone, Debug)]
+pub struct Flags {
+    pub config_handler: Option<cosmic_config::Config>,
+    pub config: Config,
 }
 
 /// This is the enum that contains all the possible variants that your application will need to transmit messages.
@@ -51,69 +122,186 @@ pub struct App {
 pub enum Message {
     /// Logs a message
     Log(LogMessage),
+    /// Hides the toast with the id, it stays in the notification log
+    DismissNotification(u64),
+    /// Empties the notification log
+    ClearNotifications,
     /// Initialises the db and cache
     InitializeStorage,
+    /// Storage finished initialising, with anything that failed on the way
+    StorageReady(Vec<core::Error>),
     /// Clears the internal cache and hard database
     ClearStorage,
+    StorageCleared,
+    /// Clears the on-disk HTTP response cache, leaving the database alone
+    ClearNetworkCache,
+    NetworkCacheCleared,
+    /// Bytes in the network cache, for the settings drawer
+    NetworkCacheSize(u64),
+    /// Changes from the settings drawer
+    Settings(context::settings_context::SettingsMessage),
+    /// The config changed on disk
+    Config(Config),
+    /// Refreshes the metadata of every library book
+    UpdateLibrary,
+    /// Bundles the log files into the downloads folder for bug reports
+    ExportLogs,
     /// Launches a website in default browser
     LaunchUrl(String),
     /// Toggles the context page
     ToggleContextPage(ContextPage),
 
-    ReadChapter(Chapter),
+    /// Opens the reader at a chapter of the book, by position in its chapter list
+    ReadChapter(Book, usize),
+    /// The book's chapters and saved place, read from storage
+    OpenReader(pages::reading::Opening),
+    /// Moves the reader to another chapter of the open book
+    OpenChapter(usize),
+    /// Downloaded text of a chapter, paragraphs separated by newlines
+    ChapterContent(Chapter, String),
+    /// Stored text of a chapter being opened, `None` downloads it
+    StoredChapter(Chapter, Option<String>),
+    /// Chapters downloaded ahead of the reader with their text, including
+    /// any found past the end of the stored chapter list
+    Prefetched(String, Vec<(Chapter, String)>),
+    /// Turns a page, or scrolls by a screen in scroll mode
+    TurnPage(Direction),
+    /// Opens the neighbouring chapter
+    TurnChapter(Direction),
+    /// Scroll position of the reader in scroll mode
+    ReaderScrolled(Viewport),
+    /// Starts or stops reading the chapter aloud
+    ReadAloud,
+    /// The engine finished a paragraph of a read-aloud session
+    Spoken(u64, usize, core::Result<()>),
+    /// Queues the chosen chapter range of the book for audio export
+    ExportAudio(Book),
+    AudioFromChanged(String),
+    AudioToChanged(String),
+    /// Writes the book's stored chapters in the picked export format
+    ExportBook(Book),
+    ExportFormatChanged(usize),
+    /// Background job updates
+    Job(jobs::JobMessage),
+    /// Asks the configured speech engine for its voices
+    LoadVoices,
+    TtsVoices(Vec<String>),
+    /// Opens or closes the reader's typography popover
+    ToggleReaderStyle,
+    /// Picks a paragraph of the open chapter to annotate, `None` puts it down
+    SelectParagraph(Option<usize>),
+    AnnotationNoteChanged(String),
+    AnnotationPassageChanged(String),
+    /// Bookmarks or highlights the selected paragraph, or the passage of it
+    /// left in the annotation bar, with the typed note
+    Annotate(AnnotationKind),
+    /// The annotation as stored, with its id
+    Annotated(Annotation),
+    /// Stored annotations of the open chapter, by chapter url
+    AnnotationsLoaded(String, Vec<Annotation>),
+    /// Opens the reader at a bookmark or highlight
+    OpenAnnotation(Annotation),
+    DeleteAnnotation(i64),
+    /// Writes the book's highlights and notes to a Markdown file
+    ExportAnnotations(Book),
+
+    /// Opens the book page
+    OpenBook(Book),
+    /// Downloads the chapters of the book that aren't stored
+    DownloadAll(Book),
+    /// Marks every chapter of the book as read, or unread with `false`
+    MarkRead(Book, bool),
+    /// Downloads the chapters of each book by url
+    DownloadChapters(Vec<(String, Vec<Chapter>)>),
 
-    /// Navigate pages
-    // PageMessage(crate::app::pages::Message),
+    /// A message for one of the pages, see [`pages::page::Page::update`]
+    PageMessage(pages::Message),
+    /// Goes back to the page under the shown one, or forward again
+    Navigate(Direction),
 
     /// Triggers the search from all activated sources
     ExploreSearch(String),
-    /// Callback for changing the explore text input field
-    ExploreInputChanged(String),
     /// Result for scraping all activated sources for the search term
-    ExploreResult(Vec<String>),
+    ExploreResult(String, Vec<Book>),
+    /// Search results as stored, flagged when new so their covers get downloaded
+    ExploreStored(Vec<(Book, bool)>),
 
-    // LibraryMessage(crate::app::pages::library::Message),
+    /// Reloads the library books from storage
     LibraryLoad,
+    LibraryLoaded(Vec<Book>, HashMap<String, core::LibraryStats>),
     /// Toggles to in_library flag for cache and db for book
     LibraryToggle(Book),
-    /// Searches the library db (unused)
-    LibrarySearch(String),
-    /// Callback for changing the library text input field
-    /// Is used for the library view's fuzzy search
-    LibraryInputChanged(String),
-    /// The result for the library search
-    LibraryResult(Vec<String>),
+    LibraryToggled(Book),
 
     /// Adds book to cache, db, and triggers thumbnail scrape
     AddBook(Book),
+    /// The book as stored, updated wherever it is shown
+    BookStored(Book),
+    /// Books changed together, updated wherever shown with one notification
+    BooksStored(Vec<Book>, String),
+    /// The book drawer's book with its chapters and annotations
+    BookLoaded(context::book_context::BookDetails),
+    /// Covers at a size for the books by url, `None` for books without one
+    CoversLoaded(CoverSize, Vec<(String, Option<bytes::Bytes>)>),
     /// Result of thumbnail scrape, adds iamge to image cache
-    AddThumbnail(Book, bytes::Bytes),
+    AddThumbnail(Book, core::cover::Cover),
     /// Adds chapter to cache and db
     AddChapter(Chapter),
 
-    /// Rescrapes book from url
+    /// Rescrapes book from url. Refreshes revalidate cached pages, see
+    /// [`core::http::revalidate`]
     RefreshBook(String),
-    /// Rescrapes chapter
+    /// Rescrapes the chapter list of a book
+    RefreshChapters(String),
+    /// Stores the scraped chapter list of a book
+    SetChapters(String, Vec<Chapter>),
+    /// Downloads the text of a chapter
     RefreshChapter(Chapter),
     /// Rescrapes tumbnail from book
     RefreshThumbnail(Book),
 
+    /// A key press nothing else handled, matched against the key bindings
+    Key(Modifiers, Key),
+    Modifiers(Modifiers),
+    /// Adds or removes the book being read or previewed from the library
+    ToggleCurrentBook,
+    /// Refreshes whatever is on screen
+    Refresh,
+    /// Moves to the explore page and focuses its search
+    OpenSearch,
+    /// Activates the neighbouring nav page
+    SwitchNavPage(Direction),
+    ToggleFullscreen,
+
     /// Null op
     Ignore,
 }
 
+/// Forward or back, through pages, chapters or the nav bar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Next,
+    Previous,
+}
+
 #[derive(Debug, Clone)]
 pub enum LogMessage {
     Log(String),
-    Error(String),
+    Success(String),
+    Warning(String),
+    Error(core::Error),
+    /// An error whose toast offers to send the message again
+    Retry(core::Error, Box<Message>),
 }
 
 /// Identifies a page in the application.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Page {
     Explore,
     Library,
     History,
-    Chapter(core::Chapter),
+    Book,
+    Reader,
 }
 
 /// Identifies a context page to display in the context drawer.
@@ -122,6 +310,7 @@ pub enum ContextPage {
     #[default]
     About,
     Settings,
+    Notifications,
     BookContext(Book),
 }
 
@@ -130,25 +319,107 @@ impl ContextPage {
         match self {
             Self::About => fl!("about-context-title"),
             Self::Settings => fl!("settings-context-title"),
+            Self::Notifications => fl!("notifications-context-title"),
             Self::BookContext(_) => fl!("book-context-title"),
             // Self::BookContext(book) => book.name.clone(),
         }
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
 pub enum MenuAction {
     About,
     Settings,
+    Notifications,
+    NextChapter,
+    PreviousChapter,
+    PageDown,
+    PageUp,
+    ToggleLibrary,
+    OpenSearch,
+    Refresh,
+    NextNavPage,
+    PreviousNavPage,
+    Back,
+    Forward,
+    ToggleFullscreen,
+    FontSizeIncrease,
+    FontSizeDecrease,
+    ReadAloud,
+}
+
+impl MenuAction {
+    /// Every action that can be bound to a key, in the order the settings list them
+    pub const ALL: [MenuAction; 18] = [
+        MenuAction::About,
+        MenuAction::Settings,
+        MenuAction::Notifications,
+        MenuAction::NextChapter,
+        MenuAction::PreviousChapter,
+        MenuAction::PageDown,
+        MenuAction::PageUp,
+        MenuAction::ToggleLibrary,
+        MenuAction::OpenSearch,
+        MenuAction::Refresh,
+        MenuAction::NextNavPage,
+        MenuAction::PreviousNavPage,
+        MenuAction::Back,
+        MenuAction::Forward,
+        MenuAction::ToggleFullscreen,
+        MenuAction::FontSizeIncrease,
+        MenuAction::FontSizeDecrease,
+        MenuAction::ReadAloud,
+    ];
+
+    pub fn label(&self) -> String {
+        match self {
+            MenuAction::About => fl!("about"),
+            MenuAction::Settings => fl!("settings"),
+            MenuAction::Notifications => fl!("notifications"),
+            MenuAction::NextChapter => fl!("menu-next-chapter"),
+            MenuAction::PreviousChapter => fl!("menu-previous-chapter"),
+            MenuAction::PageDown => fl!("menu-page-down"),
+            MenuAction::PageUp => fl!("menu-page-up"),
+            MenuAction::ToggleLibrary => fl!("menu-toggle-library"),
+            MenuAction::OpenSearch => fl!("menu-search"),
+            MenuAction::Refresh => fl!("menu-refresh"),
+            MenuAction::NextNavPage => fl!("menu-next-nav-page"),
+            MenuAction::PreviousNavPage => fl!("menu-previous-nav-page"),
+            MenuAction::Back => fl!("menu-back"),
+            MenuAction::Forward => fl!("menu-forward"),
+            MenuAction::ToggleFullscreen => fl!("menu-fullscreen"),
+            MenuAction::FontSizeIncrease => fl!("menu-font-size-increase"),
+            MenuAction::FontSizeDecrease => fl!("menu-font-size-decrease"),
+            MenuAction::ReadAloud => fl!("menu-read-aloud"),
+        }
+    }
 }
 
 impl menu::action::MenuAction for MenuAction {
     type Message = Message;
 
     fn message(&self) -> Self::Message {
+        use context::settings_context::SettingsMessage;
+
         match self {
             MenuAction::About => Message::ToggleContextPage(ContextPage::About),
             MenuAction::Settings => Message::ToggleContextPage(ContextPage::Settings),
+            MenuAction::Notifications => Message::ToggleContextPage(ContextPage::Notifications),
+            MenuAction::NextChapter => Message::TurnChapter(Direction::Next),
+            MenuAction::PreviousChapter => Message::TurnChapter(Direction::Previous),
+            MenuAction::PageDown => Message::TurnPage(Direction::Next),
+            MenuAction::PageUp => Message::TurnPage(Direction::Previous),
+            MenuAction::ToggleLibrary => Message::ToggleCurrentBook,
+            MenuAction::OpenSearch => Message::OpenSearch,
+            MenuAction::Refresh => Message::Refresh,
+            MenuAction::NextNavPage => Message::SwitchNavPage(Direction::Next),
+            MenuAction::PreviousNavPage => Message::SwitchNavPage(Direction::Previous),
+            MenuAction::Back => Message::Navigate(Direction::Previous),
+            MenuAction::Forward => Message::Navigate(Direction::Next),
+            MenuAction::ToggleFullscreen => Message::ToggleFullscreen,
+            MenuAction::FontSizeIncrease => Message::Settings(SettingsMessage::FontSizeBy(1)),
+            MenuAction::FontSizeDecrease => Message::Settings(SettingsMessage::FontSizeBy(-1)),
+            MenuAction::ReadAloud => Message::ReadAloud,
         }
     }
 }
@@ -164,7 +435,7 @@ impl menu::action::MenuAction for MenuAction {
 impl cosmic::Application for App {
     type Executor = cosmic::executor::Default;
 
-    type Flags = ();
+    type Flags = Flags;
 
     type Message = Message;
 
@@ -190,40 +461,49 @@ impl cosmic::Application for App {
     /// - `core` is used to passed on for you by libcosmic to use in the core of your own application.
     /// - `flags` is used to pass in any data that your application needs to use before it starts.
     /// - `Command` type is used to send messages to your application. `Command::none()` can be used to send no messages to your application.
-    fn init(core: Core, _flags: Self::Flags) -> (Self, Command<Self::Message>) {
+    fn init(core: Core, flags: Self::Flags) -> (Self, Command<Self::Message>) {
         let mut nav = nav_bar::Model::default();
 
         nav.insert()
-            .text("Explore")
+            .text(fl!("explore"))
             .data::<Page>(Page::Explore)
             .icon(icon::from_name("applications-science-symbolic"));
         // .activate();
 
         nav.insert()
-            .text("Library")
+            .text(fl!("library"))
             .data::<Page>(Page::Library)
             .icon(icon::from_name("applications-system-symbolic"));
 
         nav.insert()
-            .text("History")
+            .text(fl!("history"))
             .data::<Page>(Page::History)
             .icon(icon::from_name("applications-games-symbolic"));
 
+        let mut pages: HashMap<Page, Box<dyn pages::page::Page>> = HashMap::new();
+        pages.insert(Page::Explore, Box::<pages::explore::ExplorePage>::default());
+        pages.insert(Page::Library, Box::<pages::library::LibraryPage>::default());
+        pages.insert(Page::History, Box::<pages::history::HistoryPage>::default());
+
         let mut app = App {
             core,
             context_page: ContextPage::default(),
-            key_binds: HashMap::new(),
+            key_binds: key_binds::key_binds(&flags.config.key_binds),
             nav,
-            data_manager: core::data::DataManager::new(),
+            pages,
+            config_handler: flags.config_handler,
+            config: flags.config,
             ..Default::default()
         };
 
         let command = Command::batch([
             app.update_titles(),
+            app.apply_config(),
             Command::perform(
                 async move { message::app(Message::InitializeStorage) },
                 |x| x,
             ),
+            Command::perform(async move { message::app(Message::LoadVoices) }, |x| x),
         ]);
 
         (app, command)
@@ -231,18 +511,118 @@ impl cosmic::Application for App {
 
     /// Elements to pack at the start of the header bar.
     fn header_start(&self) -> Vec<Element<Self::Message>> {
-        let menu_bar = menu::bar(vec![menu::Tree::with_children(
-            menu::root(fl!("view")),
-            menu::items(
-                &self.key_binds,
-                vec![
-                    menu::Item::Button(fl!("about"), MenuAction::About),
-                    menu::Item::Button(fl!("settings"), MenuAction::Settings),
-                ],
+        let item = |action: MenuAction| menu::Item::Button(action.label(), action);
+
+        let menu_bar = menu::bar(vec![
+            menu::Tree::with_children(
+                menu::root(fl!("view")),
+                menu::items(
+                    &self.key_binds,
+                    vec![
+                        item(MenuAction::ToggleFullscreen),
+                        item(MenuAction::FontSizeIncrease),
+                        item(MenuAction::FontSizeDecrease),
+                        menu::Item::Divider,
+                        item(MenuAction::About),
+                        item(MenuAction::Settings),
+                        item(MenuAction::Notifications),
+                    ],
+                ),
+            ),
+            menu::Tree::with_children(
+                menu::root(fl!("go")),
+                menu::items(
+                    &self.key_binds,
+                    vec![
+                        item(MenuAction::OpenSearch),
+                        item(MenuAction::NextNavPage),
+                        item(MenuAction::PreviousNavPage),
+                        menu::Item::Divider,
+                        item(MenuAction::Back),
+                        item(MenuAction::Forward),
+                        menu::Item::Divider,
+                        item(MenuAction::NextChapter),
+                        item(MenuAction::PreviousChapter),
+                        item(MenuAction::PageDown),
+                        item(MenuAction::PageUp),
+                    ],
+                ),
             ),
-        )]);
+            menu::Tree::with_children(
+                menu::root(fl!("book")),
+                menu::items(
+                    &self.key_binds,
+                    vec![
+                        item(MenuAction::ToggleLibrary),
+                        item(MenuAction::Refresh),
+                        item(MenuAction::ReadAloud),
+                    ],
+                ),
+            ),
+        ]);
 
-        vec![menu_bar.into()]
+        let back = widget::button::icon(icon::from_name("go-previous-symbolic")).on_press_maybe(
+            (!self.stack.is_empty()).then_some(Message::Navigate(Direction::Previous)),
+        );
+        let forward = widget::button::icon(icon::from_name("go-next-symbolic")).on_press_maybe(
+            (!self.forward.is_empty()).then_some(Message::Navigate(Direction::Next)),
+        );
+
+        vec![back.into(), forward.into(), menu_bar.into()]
+    }
+
+    /// Elements to pack at the end of the header bar.
+    fn header_end(&self) -> Vec<Element<Self::Message>> {
+        let notifications =
+            widget::button::icon(icon::from_name("preferences-system-notifications-symbolic"))
+                .on_press(Message::ToggleContextPage(ContextPage::Notifications));
+
+        vec![notifications.into()]
+    }
+
+    /// Watches the config for outside changes and drives the library update timer.
+    fn subscription(&self) -> Subscription<Self::Message> {
+        struct ConfigSubscription;
+
+        let mut subscriptions = vec![cosmic_config::config_subscription(
+            std::any::TypeId::of::<ConfigSubscription>(),
+            Self::APP_ID.into(),
+            CONFIG_VERSION,
+        )
+        .map(|update| {
+            for e in update.errors {
+                tracing::error!("failed to watch config: {e}");
+            }
+            Message::Config(update.config)
+        })];
+
+        if self.config.update_interval > 0 {
+            let interval =
+                std::time::Duration::from_secs(u64::from(self.config.update_interval) * 60 * 60);
+            subscriptions.push(cosmic::iced::time::every(interval).map(|_| Message::UpdateLibrary));
+        }
+
+        subscriptions.push(event::listen_with(|event, status| match event {
+            Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+                Some(Message::Modifiers(modifiers))
+            }
+            Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. })
+                if status == event::Status::Ignored =>
+            {
+                Some(Message::Key(modifiers, key))
+            }
+            _ => None,
+        }));
+
+        subscriptions.push(self.jobs.subscription(&self.storage));
+
+        if self.is_reading() && self.config.reading_mode == config::ReadingMode::Paged {
+            subscriptions.push(cosmic::iced::event::listen_with(
+                pages::reading::page_turn_events,
+            ));
+        }
+
+        Subscription::batch(subscriptions)
     }
 
     /// This is the main view of your application, it is the root of your widget tree.
@@ -252,12 +632,9 @@ impl cosmic::Application for App {
     ///
     /// To get a better sense of which widgets are available, check out the `widget` module.
     fn view(&self) -> Element<Self::Message> {
-        dbg!(self.nav.active());
-        let page_view = widget::responsive(|size| match self.nav.active_data::<Page>() {
-            Some(Page::Explore) => self.view_explore(size),
-            Some(Page::Library) => self.view_library(size),
-            Some(Page::History) => self.view_history(size),
-            _ => widget::text::title1(fl!("welcome"))
+        let page_view = widget::responsive(|size| match self.page() {
+            Some(page) => page.view(self, size),
+            None => widget::text::title1(fl!("welcome"))
                 .apply(widget::container)
                 .width(Length::Fill)
                 .height(Length::Fill)
@@ -265,7 +642,15 @@ impl cosmic::Application for App {
                 .align_y(Vertical::Center)
                 .into(),
         });
-        page_view.into()
+
+        if self.notifications.toasts().next().is_none() {
+            return page_view.into();
+        }
+
+        widget::column()
+            .push(self.view_toasts())
+            .push(page_view)
+            .into()
     }
 
     /// Application messages are handled here. The application state can be modified based on
@@ -276,26 +661,135 @@ impl cosmic::Application for App {
             Message::InitializeStorage => {
                 let storage_path = dirs::data_local_dir().unwrap().join(App::APP_ID);
 
-                let errors = self.data_manager.init(storage_path);
-                if !errors.is_empty() {
-                    let commands: Vec<cosmic::Command<message::Message<Message>>> = errors
-                        .iter()
-                        .map(|e| self.log_error(format!("{:?}", e)))
-                        .collect();
-                    return Command::batch(commands);
+                if let Err(e) = core::http::CACHE.init(storage_path.join("http-cache")) {
+                    return self.log_error(e);
                 }
+
+                return self.storage.perform(
+                    move |data| Ok(data.init(storage_path)),
+                    Message::StorageReady,
+                );
+            }
+            Message::StorageReady(errors) => {
+                let mut commands: Vec<cosmic::Command<message::Message<Message>>> =
+                    errors.into_iter().map(|e| self.log_error(e)).collect();
+                commands.push(self.update(Message::LibraryLoad));
+                return Command::batch(commands);
             }
             Message::ClearStorage => {
-                if let Err(e) = self.data_manager.clear_all() {
-                    return self.log_error(format!("{:?}", e));
+                return self
+                    .storage
+                    .perform(|data| data.clear_all(), |()| Message::StorageCleared);
+            }
+            Message::StorageCleared => {
+                self.library.clear();
+                self.explore_results.clear();
+                self.book = None;
+                self.stop_read_aloud();
+                self.stack.clear();
+                self.forward.clear();
+                self.covers.clear();
+                return Command::batch([
+                    self.notify(LogMessage::Success(fl!("notification-storage-cleared"))),
+                    self.update(Message::LibraryLoad),
+                ]);
+            }
+            Message::ClearNetworkCache => {
+                return Command::perform(
+                    async move {
+                        let res = tokio::task::spawn_blocking(|| core::http::CACHE.clear()).await;
+                        match res {
+                            Ok(Ok(())) => message::app(Message::NetworkCacheCleared),
+                            Ok(Err(e)) => message::app(Message::Log(LogMessage::Error(e))),
+                            Err(e) => message::app(Message::Log(LogMessage::Error(
+                                core::Error::Storage(e.to_string()),
+                            ))),
+                        }
+                    },
+                    |x| x,
+                );
+            }
+            Message::NetworkCacheCleared => {
+                self.network_cache_size = 0;
+                return self.notify(LogMessage::Success(fl!(
+                    "notification-network-cache-cleared"
+                )));
+            }
+            Message::NetworkCacheSize(size) => {
+                self.network_cache_size = size;
+            }
+            Message::Settings(settings_message) => {
+                return self.update_settings(settings_message);
+            }
+            Message::Config(config) => {
+                if config != self.config {
+                    self.config = config;
+                    return self.apply_config();
                 }
-                dbg!("Cleared storage");
+            }
+            Message::UpdateLibrary => {
+                let commands = self.library.iter().map(|book| {
+                    let url = book.url.clone();
+                    Command::perform(
+                        async move { message::app(Message::RefreshBook(url)) },
+                        |x| x,
+                    )
+                });
+                return Command::batch(commands);
+            }
+            Message::ExportLogs => {
+                let dest = dirs::download_dir()
+                    .or_else(dirs::home_dir)
+                    .unwrap_or_else(std::env::temp_dir);
+                return Command::perform(
+                    async move {
+                        let res =
+                            tokio::task::spawn_blocking(move || core::logging::export(&dest)).await;
+                        match res {
+                            Ok(Ok(path)) => message::app(Message::Log(LogMessage::Success(fl!(
+                                "notification-logs-exported",
+                                path = path.display().to_string()
+                            )))),
+                            Ok(Err(e)) => message::app(Message::Log(LogMessage::Error(e))),
+                            Err(e) => message::app(Message::Log(LogMessage::Error(
+                                core::Error::Storage(e.to_string()),
+                            ))),
+                        }
+                    },
+                    |x| x,
+                );
             }
             Message::Log(log) => {
-                match log {
-                    LogMessage::Log(msg) => dbg!(&msg),
-                    LogMessage::Error(msg) => dbg!(&msg),
+                use notifications::Level;
+
+                let (level, text, action) = match log {
+                    LogMessage::Log(msg) => (Level::Info, msg, None),
+                    LogMessage::Success(msg) => (Level::Success, msg, None),
+                    LogMessage::Warning(msg) => (Level::Warning, msg, None),
+                    LogMessage::Error(e) => (Level::Error, e.localized(), None),
+                    LogMessage::Retry(e, retry) => (Level::Error, e.localized(), Some(*retry)),
                 };
+                match level {
+                    Level::Info | Level::Success => tracing::info!("{text}"),
+                    Level::Warning => tracing::warn!("{text}"),
+                    Level::Error => tracing::error!("{text}"),
+                }
+
+                let id = self.notifications.push(level, text, action);
+                let timeout = level.timeout();
+                return Command::perform(
+                    async move {
+                        tokio::time::sleep(timeout).await;
+                        message::app(Message::DismissNotification(id))
+                    },
+                    |x| x,
+                );
+            }
+            Message::DismissNotification(id) => {
+                self.notifications.dismiss(id);
+            }
+            Message::ClearNotifications => {
+                self.notifications.clear();
             }
             Message::LaunchUrl(url) => {
                 let _result = open::that_detached(url);
@@ -312,111 +806,282 @@ impl cosmic::Application for App {
 
                 // Set the title of the context drawer.
                 self.set_context_title(context_page.title());
+
+                if !self.core.window.show_context {
+                    return Command::none();
+                }
+                match context_page {
+                    ContextPage::BookContext(book) => return self.load_book(book),
+                    ContextPage::Settings => return self.measure_network_cache(),
+                    _ => (),
+                }
             }
-            Message::ExploreInputChanged(input) => {
-                self.explore_input = input;
+            Message::PageMessage(message) => {
+                let id = message.page();
+                return self.with_page(id, |page, app| page.update(app, message));
             }
-            Message::LibraryInputChanged(input) => {
-                self.library_input = input;
+            Message::Navigate(direction) => {
+                return self.navigate(direction);
             }
             Message::ExploreSearch(term) => {
-                let mut commands = vec![];
-                // Royal Road search
-                commands.push(Command::perform(
-                    async move {
-                        let source = source::RoyalRoadSource::new();
-                        dbg!(format!("searching for: {}", &term));
-                        let res = source.search(term).await;
-                        match res {
-                            Ok(content) => {
-                                cosmic::app::message::app(Message::ExploreResult(content))
+                self.explore_results.clear();
+
+                let commands = source::all()
+                    .into_iter()
+                    .filter(|source| self.config.enabled_sources.contains(&source.as_str()))
+                    .map(|source| {
+                        let term = term.clone();
+                        Command::perform(
+                            async move {
+                                tracing::info!("searching {} for: {}", source.as_str(), &term);
+                                let res = source.search(term.clone()).await;
+                                match res {
+                                    Ok(content) => cosmic::app::message::app(
+                                        Message::ExploreResult(term, content),
+                                    ),
+                                    Err(e) => {
+                                        cosmic::app::message::app(Message::Log(LogMessage::Retry(
+                                            e,
+                                            Box::new(Message::ExploreSearch(term)),
+                                        )))
+                                    }
+                                }
+                            },
+                            |x| x,
+                        )
+                    })
+                    .collect::<Vec<_>>();
+
+                if commands.is_empty() {
+                    return self.notify(LogMessage::Warning(fl!("notification-no-sources")));
+                }
+                return Command::batch(commands);
+            }
+            Message::ExploreResult(term, res) => {
+                // Each enabled source answers separately
+                for book in &res {
+                    if !self.explore_results.iter().any(|b| b.id() == book.id()) {
+                        self.explore_results.push(book.clone());
+                    }
+                }
+                let notify = self.notify(match res.len() {
+                    0 => LogMessage::Warning(fl!(
+                        "notification-search-results",
+                        count = 0,
+                        term = term.clone()
+                    )),
+                    count => LogMessage::Log(fl!(
+                        "notification-search-results",
+                        count = count,
+                        term = term.clone()
+                    )),
+                });
+                let store = self.storage.perform(
+                    move |data| {
+                        let mut stored = vec![];
+                        for book in res {
+                            match data.get_book(&book.url)? {
+                                Some(b) => stored.push((b, false)),
+                                // Store the partial book, the full scrape happens when it is opened
+                                None => {
+                                    data.set_book(&book)?;
+                                    stored.push((book, true));
+                                }
                             }
-                            Err(e) => cosmic::app::message::app(Message::Log(LogMessage::Error(
-                                e.to_string(),
-                            ))),
                         }
+                        Ok(stored)
                     },
-                    |x| x,
-                ));
-
-                return Command::batch(commands);
+                    Message::ExploreStored,
+                );
+                return Command::batch([notify, store]);
             }
-            Message::ExploreResult(res) => {
-                self.explore_results = res;
-                let mut commands = vec![];
-                for url in self.explore_results.clone() {
-                    match self.data_manager.get_book(&url) {
-                        // Skip book
-                        Ok(Some(_)) => continue,
-                        // Create command to scrape book info
-                        Ok(_) => commands.push(Command::perform(
-                            async move { message::app(Message::RefreshBook(url)) },
-                            |x| x,
-                        )),
-                        // Log error
-                        Err(e) => commands.push(self.log_error(format!("{:?}", e))),
-                    };
+            Message::ExploreStored(books) => {
+                let urls = books.iter().map(|(b, _)| b.url.clone()).collect();
+                let mut commands = vec![self.load_covers(urls, CoverSize::Thumbnail)];
+                for (book, new) in books {
+                    commands.push(self.book_stored(&book));
+                    if new {
+                        commands.push(self.update(Message::RefreshThumbnail(book)));
+                    }
                 }
                 return Command::batch(commands);
             }
-            Message::LibrarySearch(_) => todo!("library search"),
-            Message::LibraryResult(_) => todo!("library result"),
-
-            Message::AddBook(book) => {
-                if let Err(e) = self.data_manager.set_book(&book) {
-                    return self.log_error(format!("{:?}", e));
-                };
 
-                return Command::perform(
-                    async move { message::app(Message::RefreshThumbnail(book)) },
-                    |x| x,
+            Message::AddBook(mut book) => {
+                let thumbnail = self.update(Message::RefreshThumbnail(book.clone()));
+                let store = self.storage.perform(
+                    move |data| {
+                        // Scrapes don't know about the library, keep what is stored
+                        if let Some(stored) = data.get_book(&book.url)? {
+                            book.in_library = stored.in_library;
+                            book.category = stored.category;
+                        }
+                        data.set_book(&book)?;
+                        Ok(book)
+                    },
+                    Message::BookStored,
                 );
+                return Command::batch([store, thumbnail]);
+            }
+            Message::BookStored(book) => {
+                return self.book_stored(&book);
             }
-            Message::AddThumbnail(book, bytes) => {
-                self.data_manager.set_image_as_bytes_to_cache(&book, bytes);
+            Message::BooksStored(books, done) => {
+                let mut commands = books
+                    .iter()
+                    .map(|book| self.book_stored(book))
+                    .collect::<Vec<_>>();
+                commands.push(self.notify(LogMessage::Success(done)));
+                return Command::batch(commands);
+            }
+            Message::BookLoaded(details) => {
+                return self.book_loaded(details);
+            }
+            Message::OpenBook(book) => {
+                return self.open_book(book);
+            }
+            Message::DownloadAll(book) => {
+                return self.download_all(book);
+            }
+            Message::MarkRead(book, read) => {
+                return self.mark_read(book, read);
+            }
+            Message::DownloadChapters(books) => {
+                return self.download_chapters(books);
+            }
+            Message::CoversLoaded(size, covers) => {
+                self.covers_loaded(size, covers);
+            }
+            Message::AddThumbnail(book, cover) => {
+                // The grid thumbnail goes straight into the cache since the
+                // card showing it is usually on screen
+                self.covers.remove(&book.url);
+                self.covers.insert(
+                    &book.url,
+                    CoverSize::Thumbnail,
+                    widget::image::Handle::from_memory(cover.thumbnail.clone()),
+                    cover.thumbnail.len(),
+                );
+                // Stored for every book so evicted covers can be reloaded
+                return self
+                    .storage
+                    .write(move |data| data.set_cover(&book, &cover));
             }
             Message::AddChapter(chapter) => todo!(),
             Message::LibraryToggle(mut book) => {
                 book.in_library = !book.in_library;
-
-                if let Err(e) = self.data_manager.set_book(&book) {
-                    return self.log_error(format!("{:?}", e));
-                };
-
-                if let Ok(Some(bytes)) = self.data_manager.get_image_as_bytes(&book) {
-                    if book.in_library {
-                        if let Err(e) = self.data_manager.set_image_as_bytes(&book, bytes) {
-                            return self.log_error(format!("{:?}", e));
-                        }
-                    }
-                }
+                return self.storage.perform(
+                    move |data| {
+                        data.set_book(&book)?;
+                        Ok(book)
+                    },
+                    Message::LibraryToggled,
+                );
+            }
+            Message::LibraryToggled(book) => {
+                let notify = self.notify(LogMessage::Success(if book.in_library {
+                    fl!("notification-library-added", name = book.name.clone())
+                } else {
+                    fl!("notification-library-removed", name = book.name.clone())
+                }));
+                return Command::batch([notify, self.book_stored(&book)]);
+            }
+            Message::LibraryLoad => {
+                return self.storage.perform(
+                    |data| Ok((data.get_library_books()?, data.get_library_stats()?)),
+                    |(books, stats)| Message::LibraryLoaded(books, stats),
+                );
+            }
+            Message::LibraryLoaded(books, stats) => {
+                let urls = books.iter().map(|b| b.url.clone()).collect();
+                self.library = books;
+                self.library_stats = stats;
+                self.update_library_filters();
+                return self.load_covers(urls, CoverSize::Thumbnail);
             }
-            Message::LibraryLoad => todo!(),
             Message::Ignore => (),
             Message::RefreshBook(book_url) => {
+                let chapters = book_url.clone();
+                let book = Command::perform(
+                    async move {
+                        let book = match source::for_url(&book_url) {
+                            Ok(source) => {
+                                core::http::revalidate(source.scrape_book(book_url.clone())).await
+                            }
+                            Err(e) => Err(e),
+                        };
+                        match book {
+                            Ok(b) => message::app(Message::AddBook(b)),
+                            Err(e) => message::app(Message::Log(LogMessage::Retry(
+                                e,
+                                Box::new(Message::RefreshBook(book_url)),
+                            ))),
+                        }
+                    },
+                    |x| x,
+                );
+                return Command::batch([
+                    book,
+                    Command::perform(
+                        async move { message::app(Message::RefreshChapters(chapters)) },
+                        |x| x,
+                    ),
+                ]);
+            }
+            Message::RefreshChapters(book_url) => {
                 return Command::perform(
                     async move {
-                        // message::app(Message::RefreshBook(url))
-                        if book_url.contains(&RoyalRoadSource::new().as_str()) {
-                            let source = RoyalRoadSource::new();
-                            let book = source.scrape_book(book_url.clone()).await;
-                            match book {
-                                Ok(b) => message::app(Message::AddBook(b)),
-                                Err(e) => message::app(Message::Log(LogMessage::Error(format!(
-                                    "{:?}",
-                                    e
-                                )))),
+                        let chapters = match source::for_url(&book_url) {
+                            Ok(source) => {
+                                let chapters = source.scrape_chapter_list(book_url.clone());
+                                core::http::revalidate(chapters).await
                             }
-                        } else if false {
-                            message::app(Message::Ignore)
-                        } else {
-                            message::app(Message::Ignore)
+                            Err(e) => Err(e),
+                        };
+                        match chapters {
+                            Ok(c) => message::app(Message::SetChapters(book_url, c)),
+                            Err(e) => message::app(Message::Log(LogMessage::Retry(
+                                e,
+                                Box::new(Message::RefreshChapters(book_url)),
+                            ))),
+                        }
+                    },
+                    |x| x,
+                );
+            }
+            Message::SetChapters(book_url, chapters) => {
+                let book_id = source::book_id(&book_url);
+                for reader in self.readers_mut().filter(|r| r.book.id() == book_id) {
+                    reader.chapters = chapters.clone();
+                }
+                for details in self.book_details_mut().filter(|d| d.book.id() == book_id) {
+                    details.chapters = chapters.clone();
+                }
+                return self
+                    .storage
+                    .write(move |data| data.set_chapters(&book_url, &chapters));
+            }
+            Message::RefreshChapter(chapter) => {
+                return Command::perform(
+                    async move {
+                        let content = match chapter.url.as_deref().map(source::for_url) {
+                            Some(Ok(source)) => {
+                                core::http::revalidate(source.download_chapter(&chapter)).await
+                            }
+                            Some(Err(e)) => Err(e),
+                            None => Err(core::Error::NotFound("chapter url".into())),
+                        };
+                        match content {
+                            Ok(c) => message::app(Message::ChapterContent(chapter, c)),
+                            Err(e) => message::app(Message::Log(LogMessage::Retry(
+                                e,
+                                Box::new(Message::RefreshChapter(chapter)),
+                            ))),
                         }
                     },
                     |x| x,
                 );
             }
-            Message::RefreshChapter(chapter) => todo!(),
             Message::RefreshThumbnail(book) => {
                 let image_url = match book.image.clone() {
                     Some(url) => url,
@@ -426,17 +1091,440 @@ impl cosmic::Application for App {
                 return Command::perform(
                     async move {
                         match App::download_book_cover(image_url).await {
-                            Ok(bytes) => message::app(Message::AddThumbnail(book.clone(), bytes)),
+                            Ok(cover) => message::app(Message::AddThumbnail(book.clone(), cover)),
+                            // Already logged, the book keeps the placeholder
+                            Err(core::Error::Image(_)) => message::app(Message::Ignore),
+                            Err(e) => message::app(Message::Log(LogMessage::Error(e))),
+                        }
+                    },
+                    |x| x,
+                );
+            }
+            Message::ReadChapter(book, index) => {
+                return self.storage.perform(
+                    move |data| {
+                        let chapters = data.get_chapters(&book.url)?;
+                        // Pick up on the saved page when reopening the chapter it was left at
+                        let page = match data.get_progress(&book.url).ok().flatten() {
+                            Some(progress)
+                                if chapters.get(index).and_then(|c| c.url.as_ref())
+                                    == Some(&progress.chapter_url) =>
+                            {
+                                progress.page
+                            }
+                            _ => 0,
+                        };
+                        Ok(pages::reading::Opening {
+                            book,
+                            chapters,
+                            index,
+                            page,
+                            jump: None,
+                        })
+                    },
+                    Message::OpenReader,
+                );
+            }
+            Message::OpenReader(opening) => {
+                let reader = pages::reading::Reader {
+                    book: opening.book,
+                    chapters: opening.chapters,
+                    ..Default::default()
+                };
+                self.core.window.show_context = false;
+                let push = self.push_page(Box::new(pages::reading::ReaderPage { reader }));
+                let command = self.open_chapter(opening.index, opening.page);
+                // Set after opening, which clears it, and taken once the text arrives
+                if let Some(reader) = self.reader_mut() {
+                    reader.jump = opening.jump;
+                }
+                return Command::batch([push, command]);
+            }
+            Message::OpenChapter(index) => {
+                return self.open_chapter(index, 0);
+            }
+            Message::TurnPage(turn) => {
+                if self.is_reading() {
+                    return self.turn_page(turn);
+                }
+            }
+            Message::TurnChapter(direction) => {
+                if !self.is_reading() {
+                    return Command::none();
+                }
+                let index = self.reader().and_then(|reader| match direction {
+                    Direction::Next => reader.next(),
+                    Direction::Previous => reader.previous(),
+                });
+                if let Some(index) = index {
+                    return self.open_chapter(index, 0);
+                }
+            }
+            Message::ReaderScrolled(viewport) => {
+                if let Some(reader) = self.reader_mut() {
+                    reader.viewport = Some(viewport);
+                }
+            }
+            Message::Modifiers(modifiers) => {
+                self.modifiers = modifiers;
+            }
+            Message::Key(modifiers, key) => {
+                let action = self
+                    .key_binds
+                    .iter()
+                    .find(|(key_bind, _)| key_bind.matches(modifiers, &key))
+                    .map(|(_, action)| *action);
+                if let Some(action) = action {
+                    return self.update(action.message());
+                }
+            }
+            Message::ToggleCurrentBook => {
+                if let Some(book) = self.current_book().cloned() {
+                    return self.update(Message::LibraryToggle(book));
+                }
+            }
+            Message::Refresh => {
+                // The book previewed in the drawer over a nav page comes first
+                if self.stack.is_empty() {
+                    if let Some(url) = self.current_book().map(|b| b.url.clone()) {
+                        return self.update(Message::RefreshBook(url));
+                    }
+                }
+                if let Some(id) = self.page().map(|p| p.id()) {
+                    return self.with_page(id, |page, app| page.refresh(app));
+                }
+            }
+            Message::OpenSearch => {
+                let explore = self
+                    .nav
+                    .iter()
+                    .find(|id| matches!(self.nav.data::<Page>(*id), Some(Page::Explore)));
+                if let Some(id) = explore {
+                    return Command::batch([
+                        self.on_nav_select(id),
+                        widget::text_input::focus(pages::explore::SEARCH_INPUT.clone()),
+                    ]);
+                }
+            }
+            Message::SwitchNavPage(direction) => {
+                let ids = self.nav.iter().collect::<Vec<nav_bar::Id>>();
+                if ids.is_empty() {
+                    return Command::none();
+                }
+                let active = ids.iter().position(|id| *id == self.nav.active());
+                let index = match (active, direction) {
+                    (Some(i), Direction::Next) => (i + 1) % ids.len(),
+                    (Some(i), Direction::Previous) => (i + ids.len() - 1) % ids.len(),
+                    (None, Direction::Next) => 0,
+                    (None, Direction::Previous) => ids.len() - 1,
+                };
+                return self.on_nav_select(ids[index]);
+            }
+            Message::ToggleFullscreen => {
+                self.fullscreen = !self.fullscreen;
+                let mode = match self.fullscreen {
+                    true => window::Mode::Fullscreen,
+                    false => window::Mode::Windowed,
+                };
+                return window::change_mode(window::Id::MAIN, mode);
+            }
+            Message::ChapterContent(chapter, content) => {
+                let show = self.show_chapter(&chapter, &content);
+                let Some(url) = chapter.url else {
+                    return show;
+                };
+                for details in self.book_details_mut() {
+                    if details
+                        .chapters
+                        .iter()
+                        .any(|c| c.url.as_ref() == Some(&url))
+                    {
+                        details.downloaded.insert(url.clone());
+                    }
+                }
+                return Command::batch([
+                    show,
+                    self.storage
+                        .write(move |data| data.set_chapter_content(&url, &content)),
+                ]);
+            }
+            Message::StoredChapter(chapter, content) => {
+                return match content {
+                    Some(content) => self.show_chapter(&chapter, &content),
+                    // Only download if the reader is still on the chapter
+                    None if self
+                        .reader()
+                        .and_then(|r| r.chapter())
+                        .is_some_and(|c| c.url == chapter.url) =>
+                    {
+                        self.update(Message::RefreshChapter(chapter))
+                    }
+                    None => Command::none(),
+                };
+            }
+            Message::Prefetched(book_url, chapters) => {
+                return self.prefetched(book_url, chapters);
+            }
+            Message::ReadAloud => {
+                if self.is_reading() {
+                    return self.toggle_read_aloud();
+                }
+            }
+            Message::Spoken(session, paragraph, res) => {
+                return self.spoken(session, paragraph, res);
+            }
+            Message::LoadVoices => {
+                let backend = self.tts.for_name(&self.config.tts_backend);
+                return Command::perform(
+                    async move {
+                        match backend.voices().await {
+                            Ok(voices) => message::app(Message::TtsVoices(voices)),
+                            // Not having the engine installed only matters once it is used
                             Err(e) => {
-                                message::app(Message::Log(LogMessage::Error(format!("{:?}", e))))
+                                tracing::warn!("failed to list voices: {e}");
+                                message::app(Message::TtsVoices(vec![]))
+                            }
+                        }
+                    },
+                    |x| x,
+                );
+            }
+            Message::AudioFromChanged(input) => {
+                self.audio_from = input;
+            }
+            Message::AudioToChanged(input) => {
+                self.audio_to = input;
+            }
+            Message::ExportAudio(book) => {
+                let Some(chapters) = self
+                    .book_details()
+                    .find(|d| d.book.id() == book.id())
+                    .map(|d| d.chapters.clone())
+                else {
+                    return Command::none();
+                };
+                let from = self.audio_from.trim().parse::<usize>().unwrap_or(1).max(1);
+                let to = self
+                    .audio_to
+                    .trim()
+                    .parse::<usize>()
+                    .unwrap_or(chapters.len())
+                    .min(chapters.len());
+                if from > to {
+                    return self.notify(LogMessage::Warning(fl!("notification-audio-no-chapters")));
+                }
+
+                let export = core::audio::AudioExport {
+                    book,
+                    chapters: chapters[from - 1..to].to_vec(),
+                    format: self.config.audio_format,
+                    backend: self.config.tts_backend.clone(),
+                    voice: self.config.voice(),
+                    dest: dirs::audio_dir()
+                        .or_else(dirs::download_dir)
+                        .or_else(dirs::home_dir)
+                        .unwrap_or_else(std::env::temp_dir),
+                };
+                return self.update(Message::Job(jobs::JobMessage::Queue(export)));
+            }
+            Message::ExportFormatChanged(index) => {
+                self.export_format = index;
+            }
+            Message::ExportBook(book) => {
+                let Some(exporter) = core::export::all().into_iter().nth(self.export_format) else {
+                    return Command::none();
+                };
+
+                let dest = dirs::document_dir()
+                    .or_else(dirs::download_dir)
+                    .or_else(dirs::home_dir)
+                    .unwrap_or_else(std::env::temp_dir);
+                let storage = self.storage.clone();
+                return Command::perform(
+                    async move {
+                        let export = storage
+                            .run(move |data| {
+                                Ok(core::export::ExportBook {
+                                    chapters: data.get_stored_chapters(&book.url)?,
+                                    cover: data
+                                        .get_cover(&book.url)
+                                        .ok()
+                                        .flatten()
+                                        .map(|c| c.detail),
+                                    book,
+                                })
+                            })
+                            .await;
+                        let export = match export {
+                            Ok(export) if export.chapters.is_empty() => {
+                                return message::app(Message::Log(LogMessage::Warning(fl!(
+                                    "notification-export-empty"
+                                ))));
                             }
+                            Ok(export) => export,
+                            Err(e) => return message::app(Message::Log(LogMessage::Error(e))),
+                        };
+
+                        let count = export.chapters.len();
+                        let res =
+                            tokio::task::spawn_blocking(move || exporter.export(&export, &dest))
+                                .await;
+                        match res {
+                            Ok(Ok(path)) => message::app(Message::Log(LogMessage::Success(fl!(
+                                "notification-book-exported",
+                                count = count,
+                                path = path.display().to_string()
+                            )))),
+                            Ok(Err(e)) => message::app(Message::Log(LogMessage::Error(e))),
+                            Err(e) => message::app(Message::Log(LogMessage::Error(
+                                core::Error::Storage(e.to_string()),
+                            ))),
                         }
                     },
                     |x| x,
                 );
             }
-            Message::ReadChapter(_) => {
-                self.nav.deactivate();
+            Message::Job(job_message) => {
+                use jobs::JobMessage;
+
+                match job_message {
+                    JobMessage::Queue(export) => {
+                        let name = export.book.name.clone();
+                        self.jobs.push(export);
+                        return self.notify(LogMessage::Log(fl!(
+                            "notification-audio-queued",
+                            name = name
+                        )));
+                    }
+                    JobMessage::Progress(id, done, total) => {
+                        self.jobs.progress(id, done, total);
+                    }
+                    JobMessage::Finished(id, res) => {
+                        let Some(job) = self.jobs.remove(id) else {
+                            return Command::none();
+                        };
+                        return self.notify(match res {
+                            Ok(path) => LogMessage::Success(fl!(
+                                "notification-audio-exported",
+                                path = path.display().to_string()
+                            )),
+                            Err(e) => LogMessage::Retry(
+                                e,
+                                Box::new(Message::Job(JobMessage::Queue(job.export))),
+                            ),
+                        });
+                    }
+                    JobMessage::Cancel(id) => {
+                        if let Some(job) = self.jobs.remove(id) {
+                            return self.notify(LogMessage::Warning(fl!(
+                                "notification-audio-cancelled",
+                                name = job.export.book.name
+                            )));
+                        }
+                    }
+                }
+            }
+            Message::TtsVoices(voices) => {
+                self.tts_voices = std::iter::once(fl!("voice-default"))
+                    .chain(voices)
+                    .collect();
+            }
+            Message::ToggleReaderStyle => {
+                if let Some(reader) = self.reader_mut() {
+                    reader.show_style = !reader.show_style;
+                }
+            }
+            Message::SelectParagraph(paragraph) => {
+                let mut passage = String::new();
+                if let Some(reader) = self.reader_mut() {
+                    reader.selected = paragraph;
+                    passage = paragraph
+                        .and_then(|i| reader.content.as_ref()?.get(i).cloned())
+                        .unwrap_or_default();
+                }
+                self.annotation_passage = passage;
+            }
+            Message::AnnotationNoteChanged(note) => {
+                self.annotation_note = note;
+            }
+            Message::AnnotationPassageChanged(passage) => {
+                self.annotation_passage = passage;
+            }
+            Message::Annotate(kind) => {
+                if self.is_reading() {
+                    return self.annotate(kind);
+                }
+            }
+            Message::Annotated(annotation) => {
+                self.annotated(annotation);
+            }
+            Message::AnnotationsLoaded(chapter_url, annotations) => {
+                return self.annotations_loaded(chapter_url, annotations);
+            }
+            Message::OpenAnnotation(annotation) => {
+                return self.storage.perform(
+                    move |data| {
+                        let chapters = data.get_chapters(&annotation.book_url)?;
+                        let Some(index) = chapters
+                            .iter()
+                            .position(|c| c.url.as_ref() == Some(&annotation.chapter_url))
+                        else {
+                            return Ok(None);
+                        };
+                        let Some(book) = data.get_book(&annotation.book_url)? else {
+                            return Ok(None);
+                        };
+                        Ok(Some(pages::reading::Opening {
+                            book,
+                            chapters,
+                            index,
+                            page: 0,
+                            jump: Some(annotation.id),
+                        }))
+                    },
+                    |opening| match opening {
+                        Some(opening) => Message::OpenReader(opening),
+                        None => Message::Log(LogMessage::Warning(fl!(
+                            "notification-annotation-missing"
+                        ))),
+                    },
+                );
+            }
+            Message::DeleteAnnotation(id) => {
+                if let Some(reader) = self.reader_mut() {
+                    reader.annotations.retain(|a| a.id != id);
+                }
+                for details in self.book_details_mut() {
+                    details.annotations.retain(|a| a.id != id);
+                }
+                return self.storage.write(move |data| data.delete_annotation(id));
+            }
+            Message::ExportAnnotations(book) => {
+                let annotations = self
+                    .book_details()
+                    .find(|d| d.book.id() == book.id())
+                    .map(|d| d.annotations.clone())
+                    .unwrap_or_default();
+                if annotations.is_empty() {
+                    return self.notify(LogMessage::Warning(fl!("notification-annotations-empty")));
+                }
+
+                let dest = dirs::document_dir()
+                    .or_else(dirs::download_dir)
+                    .or_else(dirs::home_dir)
+                    .unwrap_or_else(std::env::temp_dir);
+                return Command::perform(
+                    async move {
+                        match core::annotation::export(&book, &annotations, &dest).await {
+                            Ok(path) => message::app(Message::Log(LogMessage::Success(fl!(
+                                "notification-annotations-exported",
+                                path = path.display().to_string()
+                            )))),
+                            Err(e) => message::app(Message::Log(LogMessage::Error(e))),
+                        }
+                    },
+                    |x| x,
+                );
             }
         }
         Command::none()
@@ -451,6 +1539,7 @@ impl cosmic::Application for App {
         Some(match &self.context_page {
             ContextPage::About => self.about_context(),
             ContextPage::Settings => self.settings_context(),
+            ContextPage::Notifications => self.notifications_context(),
             ContextPage::BookContext(book) => self.book_context(book.clone()),
         })
     }
@@ -459,22 +1548,227 @@ impl cosmic::Application for App {
     fn on_nav_select(&mut self, id: nav_bar::Id) -> Command<Self::Message> {
         // Activate the page in the model.
         self.nav.activate(id);
-        // self.nav.close();
+        // Forward brings back the pages that were over the last one
+        if !self.stack.is_empty() {
+            self.forward.clear();
+            while let Some(page) = self.stack.pop() {
+                self.forward.push(page);
+            }
+        }
 
-        self.update_titles()
+        self.page_shown()
     }
 }
 
 impl App {
+    /// Shows the chapter at `index` of the open book from `page` and downloads its text
+    fn open_chapter(&mut self, index: usize, page: usize) -> Command<Message> {
+        let Some(reader) = self.reader_mut() else {
+            return Command::none();
+        };
+        let Some(chapter) = reader.chapters.get(index).cloned() else {
+            return Command::none();
+        };
+        // Moving on to the next chapter finishes the one before
+        let finished = reader
+            .chapter()
+            .filter(|_| index == reader.index + 1 && reader.content.is_some())
+            .and_then(|c| c.url.clone());
+        let book_id = reader.book.id();
+        reader.index = index;
+        reader.page = page;
+        reader.content = None;
+        reader.viewport = None;
+        reader.annotations.clear();
+        reader.selected = None;
+        reader.anchor = None;
+        reader.jump = None;
+
+        // Reading aloud follows into the new chapter from its start
+        if reader.speaking.is_some() {
+            self.stop_read_aloud();
+            if let Some(reader) = self.reader_mut() {
+                reader.speaking = Some(0);
+            }
+        }
+
+        let name = chapter.name.clone().unwrap_or_default();
+        self.set_header_title(name);
+
+        // Chapters read or prefetched before open without waiting on the network
+        let load = self.storage.perform(
+            move |data| {
+                let content = chapter
+                    .url
+                    .as_deref()
+                    .and_then(|url| data.get_chapter_content(url).ok().flatten());
+                Ok((chapter, content))
+            },
+            |(chapter, content)| Message::StoredChapter(chapter, content),
+        );
+
+        let read = match finished {
+            Some(url) => {
+                for details in self.book_details_mut().filter(|d| d.book.id() == book_id) {
+                    details.read.insert(url.clone());
+                }
+                self.storage.write(move |data| data.set_read(&[url], true))
+            }
+            None => Command::none(),
+        };
+
+        Command::batch([
+            self.save_progress(),
+            pages::reading::scroll_to_top(),
+            load,
+            self.prefetch(),
+            read,
+        ])
+    }
+
+    /// The page shown: the last one pushed, else the active nav page
+    pub fn page(&self) -> Option<&dyn pages::page::Page> {
+        match self.stack.last() {
+            Some(page) => Some(page.as_ref()),
+            None => self
+                .nav
+                .active_data::<Page>()
+                .and_then(|id| self.pages.get(id))
+                .map(|page| page.as_ref()),
+        }
+    }
+
+    /// Runs `f` on the page with the id, the topmost one if pushed more than
+    /// once. The page is taken out meanwhile so it can change the app too
+    fn with_page(
+        &mut self,
+        id: Page,
+        f: impl FnOnce(&mut Box<dyn pages::page::Page>, &mut App) -> Command<Message>,
+    ) -> Command<Message> {
+        if let Some(i) = self.stack.iter().rposition(|p| p.id() == id) {
+            let mut page = self.stack.remove(i);
+            let command = f(&mut page, self);
+            self.stack.insert(i.min(self.stack.len()), page);
+            return command;
+        }
+        let Some(mut page) = self.pages.remove(&id) else {
+            return Command::none();
+        };
+        let command = f(&mut page, self);
+        self.pages.insert(id, page);
+        command
+    }
+
+    /// Shows the page over the current one, replacing it if it is the same
+    /// kind of page
+    pub fn push_page(&mut self, page: Box<dyn pages::page::Page>) -> Command<Message> {
+        self.forward.clear();
+        if self.stack.last().is_some_and(|p| p.id() == page.id()) {
+            self.stack.pop();
+        }
+        self.stack.push(page);
+        self.update_titles()
+    }
+
+    /// Goes back to the page under the shown one, or forward to the last one
+    /// gone back from
+    fn navigate(&mut self, direction: Direction) -> Command<Message> {
+        let (from, to) = match direction {
+            Direction::Previous => (&mut self.stack, &mut self.forward),
+            Direction::Next => (&mut self.forward, &mut self.stack),
+        };
+        let Some(page) = from.pop() else {
+            return Command::none();
+        };
+        to.push(page);
+        self.page_shown()
+    }
+
+    /// Updates the titles for the page shown after navigating, and reloads
+    /// the library or history if it is one of them
+    fn page_shown(&mut self) -> Command<Message> {
+        // Reading since the library or history last showed changes what they list
+        let reload = match self.page().map(|p| p.id()) {
+            Some(Page::Library) => self.update(Message::LibraryLoad),
+            Some(Page::History) => self.with_page(Page::History, |page, app| page.refresh(app)),
+            _ => Command::none(),
+        };
+        Command::batch([self.update_titles(), reload])
+    }
+
+    /// The book of the page shown, or else the one previewed in the context
+    /// drawer
+    fn current_book(&self) -> Option<&Book> {
+        if let Some(book) = self.page().and_then(|p| p.book(self)) {
+            return Some(book);
+        }
+        match &self.context_page {
+            ContextPage::BookContext(book) if self.core.window.show_context => Some(book),
+            _ => None,
+        }
+    }
+
+    /// Updates every copy of a stored book the views show, loading its
+    /// cover if it just joined the library
+    fn book_stored(&mut self, book: &Book) -> Command<Message> {
+        // Matched by id, a renamed book comes back under its new url
+        let id = book.id();
+        for shown in self.explore_results.iter_mut().filter(|b| b.id() == id) {
+            *shown = book.clone();
+        }
+        for details in self.book_details_mut().filter(|d| d.book.id() == id) {
+            details.book = book.clone();
+        }
+        for reader in self.readers_mut().filter(|r| r.book.id() == id) {
+            reader.book = book.clone();
+        }
+        if let ContextPage::BookContext(shown) = &mut self.context_page {
+            if shown.id() == id {
+                *shown = book.clone();
+            }
+        }
+
+        let command = match self.library.iter().position(|b| b.id() == id) {
+            Some(i) if book.in_library => {
+                self.library[i] = book.clone();
+                Command::none()
+            }
+            Some(i) => {
+                self.library.remove(i);
+                self.library_stats.remove(&id);
+                Command::none()
+            }
+            None if book.in_library => {
+                self.library.push(book.clone());
+                // Counted when the library page shows next
+                let added = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_secs() as i64)
+                    .unwrap_or(0);
+                self.library_stats.insert(
+                    id,
+                    core::LibraryStats {
+                        added,
+                        ..Default::default()
+                    },
+                );
+                self.load_covers(vec![book.url.clone()], CoverSize::Thumbnail)
+            }
+            None => return Command::none(),
+        };
+        self.update_library_filters();
+        command
+    }
+
     /// Updates the header and window titles.
     pub fn update_titles(&mut self) -> Command<Message> {
         let mut window_title = fl!("app-title");
         let mut header_title = String::new();
 
-        if let Some(page) = self.nav.text(self.nav.active()) {
+        if let Some(page) = self.page().map(|p| p.title(self)) {
             window_title.push_str(" — ");
-            window_title.push_str(page);
-            header_title.push_str(page);
+            window_title.push_str(&page);
+            header_title.push_str(&page);
         }
 
         self.set_header_title(header_title);
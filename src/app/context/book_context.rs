@@ -1,8 +1,10 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use crate::app::{App, Message};
+use crate::app::pages::book::BookPage;
+use crate::app::{App, ContextPage, Message};
+use crate::core::cover::CoverSize;
 use crate::core::source::{self, *};
-use crate::core::{self, Book, Chapter};
+use crate::core::{self, Annotation, AnnotationKind, Book, Chapter, Progress};
 use crate::fl;
 use cosmic::app::{message, Command, Core};
 use cosmic::iced::alignment::{Horizontal, Vertical};
@@ -10,23 +12,120 @@ use cosmic::iced::{Alignment, Length};
 use cosmic::widget::{self, *};
 use cosmic::{cosmic_theme, theme, ApplicationExt, Apply, Element};
 
+/// What the book drawer and pages show, read from storage when they open
+#[derive(Debug, Clone, Default)]
+pub struct BookDetails {
+    pub book: Book,
+    pub chapters: Vec<Chapter>,
+    pub annotations: Vec<Annotation>,
+    pub progress: Option<Progress>,
+    /// Urls of the chapters read
+    pub read: HashSet<String>,
+    /// Urls of the chapters with text stored
+    pub downloaded: HashSet<String>,
+}
+
 impl App {
+    /// The details the drawer and the open book pages show
+    pub fn book_details(&self) -> impl Iterator<Item = &BookDetails> {
+        let pages = self
+            .stack
+            .iter()
+            .chain(self.forward.iter())
+            .filter_map(|p| p.downcast_ref::<BookPage>())
+            .filter_map(|p| p.details.as_ref());
+        self.book.iter().chain(pages)
+    }
+
+    /// Every copy of the details shown, to keep them in step with storage
+    pub fn book_details_mut(&mut self) -> impl Iterator<Item = &mut BookDetails> {
+        let pages = self
+            .stack
+            .iter_mut()
+            .chain(self.forward.iter_mut())
+            .filter_map(|p| p.downcast_mut::<BookPage>())
+            .filter_map(|p| p.details.as_mut());
+        self.book.iter_mut().chain(pages)
+    }
+
+    /// Reads the book with its chapters, annotations and reading state
+    pub fn load_book(&mut self, book: Book) -> Command<Message> {
+        if self.book.as_ref().is_some_and(|d| d.book.id() != book.id()) {
+            self.book = None;
+        }
+        self.storage.perform(
+            move |data| {
+                Ok(BookDetails {
+                    chapters: data.get_chapters(&book.url)?,
+                    annotations: data.get_annotations(&book.url)?,
+                    progress: data.get_progress(&book.url)?,
+                    read: data.get_read_chapters(&book.url)?,
+                    downloaded: data.get_downloaded_chapters(&book.url)?,
+                    book: data.get_book(&book.url)?.unwrap_or(book),
+                })
+            },
+            Message::BookLoaded,
+        )
+    }
+
+    pub fn book_loaded(&mut self, details: BookDetails) -> Command<Message> {
+        // The drawer and page moved on to another book meanwhile
+        let id = details.book.id();
+        let in_drawer = matches!(&self.context_page, ContextPage::BookContext(b) if b.id() == id);
+        let mut in_page = false;
+        for page in self
+            .stack
+            .iter_mut()
+            .chain(self.forward.iter_mut())
+            .filter_map(|p| p.downcast_mut::<BookPage>())
+            .filter(|p| p.book.id() == id)
+        {
+            page.details = Some(details.clone());
+            in_page = true;
+        }
+        if !in_drawer && !in_page {
+            return Command::none();
+        }
+
+        let url = details.book.url.clone();
+        // Books from search results only carry the listing, scrape the rest on open
+        let refresh = if details.book.partial {
+            Some(Message::RefreshBook(url.clone()))
+        } else if details.chapters.is_empty() {
+            Some(Message::RefreshChapters(url.clone()))
+        } else {
+            None
+        };
+        if in_drawer {
+            self.context_page = ContextPage::BookContext(details.book.clone());
+            self.book = Some(details);
+        }
+
+        let cover = self.load_covers(vec![url], CoverSize::Detail);
+        // The page title follows the stored name
+        let titles = match in_page {
+            true => self.update_titles(),
+            false => Command::none(),
+        };
+        match refresh {
+            Some(next) => Command::batch([
+                cover,
+                titles,
+                Command::perform(async move { message::app(next) }, |x| x),
+            ]),
+            None => Command::batch([cover, titles]),
+        }
+    }
+
     // The book context page
-    pub fn book_context(&self, mut book: Book) -> Element<Message> {
+    pub fn book_context(&self, book: Book) -> Element<Message> {
         let spacing = theme::active().cosmic().spacing;
 
-        match self.data_manager.get_book_from_storage(book.url.clone()) {
-            Ok(b) => {
-                if let Some(b) = b {
-                    book = b;
-                }
-            }
-            Err(e) => {
-                dbg!(e);
-            }
-        }
+        // Shows the listing until storage answers
+        let details = self.book.as_ref().filter(|d| d.book.id() == book.id());
+        let book = details.map(|d| d.book.clone()).unwrap_or(book);
 
-        let image = widget::image(self.data_manager.get_image_handle(&book))
+        let image = widget::image(self.cover(&book.url, CoverSize::Detail))
             .content_fit(cosmic::iced::ContentFit::Contain)
             .border_radius([spacing.space_xxs as f32; 4])
             .apply(container)
@@ -38,6 +137,7 @@ impl App {
             .push(
                 widget::column()
                     .push(widget::text(book.name.clone()))
+                    .push_maybe(book.author.clone().map(widget::text::caption))
                     .push(widget::text(book.source.clone()).style(cosmic::theme::Text::Default))
                     .spacing(spacing.space_xxs),
             )
@@ -68,6 +168,11 @@ impl App {
                     .on_press(Message::RefreshBook(book.url.clone()))
                     .padding(spacing.space_xxs),
             )
+            .push(
+                widget::button(widget::text(fl!("book-details")))
+                    .on_press(Message::OpenBook(book.clone()))
+                    .padding(spacing.space_xxs),
+            )
             .width(Length::Fill)
             .align_items(Alignment::Center)
             .apply(container)
@@ -78,17 +183,58 @@ impl App {
             .on_press(Message::LaunchUrl(book.url.clone()))
             .padding(0);
 
-        let mut chapters: Vec<Element<Message>> = vec![];
-        for i in 0..15 {
-            chapters.push(
-                widget::button::button(widget::text(format!("Chapter {}", i + 1)))
-                    .on_press(Message::ReadChapter(Chapter::new(None, None, None)))
-                    .into(),
-            );
-        }
+        let chapters = details.map(|d| d.chapters.clone()).unwrap_or_default();
+        let chapters_empty = chapters.is_empty();
+        let chapters: Vec<Element<Message>> = match chapters_empty {
+            true => vec![widget::text(fl!("chapters-empty")).into()],
+            false => chapters
+                .into_iter()
+                .enumerate()
+                .map(|(i, chapter)| {
+                    let name = chapter.name.unwrap_or_else(|| format!("Chapter {}", i + 1));
+                    widget::button::button(widget::text(name))
+                        .on_press(Message::ReadChapter(book.clone(), i))
+                        .width(Length::Fill)
+                        .into()
+                })
+                .collect(),
+        };
+
+        let audio_row = widget::row()
+            .push(
+                widget::text_input(fl!("export-audio-from"), &self.audio_from)
+                    .on_input(Message::AudioFromChanged)
+                    .width(Length::Fixed(64.0)),
+            )
+            .push(
+                widget::text_input(fl!("export-audio-to"), &self.audio_to)
+                    .on_input(Message::AudioToChanged)
+                    .width(Length::Fixed(64.0)),
+            )
+            .push(
+                widget::button(widget::text(fl!("export-audio")))
+                    .on_press_maybe((!chapters_empty).then(|| Message::ExportAudio(book.clone())))
+                    .padding(spacing.space_xxs),
+            )
+            .spacing(spacing.space_xxs)
+            .align_items(Alignment::Center);
+
+        let export_row = widget::row()
+            .push(widget::dropdown(
+                core::export::labels(),
+                Some(self.export_format),
+                Message::ExportFormatChanged,
+            ))
+            .push(
+                widget::button(widget::text(fl!("export-book")))
+                    .on_press_maybe((!chapters_empty).then(|| Message::ExportBook(book.clone())))
+                    .padding(spacing.space_xxs),
+            )
+            .spacing(spacing.space_xxs)
+            .align_items(Alignment::Center);
 
         let chapter_view = widget::column()
-            .push(widget::text("Chapters"))
+            .push(widget::text(fl!("chapters")))
             .push(widget::divider::horizontal::default())
             .push(
                 widget::container(
@@ -105,6 +251,84 @@ impl App {
             .style(cosmic::theme::Container::Card)
             .padding(spacing.space_xs);
 
+        let annotations = details.map(|d| d.annotations.clone()).unwrap_or_default();
+        let annotations_empty = annotations.is_empty();
+        let annotations: Vec<Element<Message>> = match annotations_empty {
+            true => vec![widget::text(fl!("annotations-empty")).into()],
+            false => annotations
+                .into_iter()
+                .map(|annotation| {
+                    let excerpt = match annotation.text.chars().count() > 140 {
+                        true => {
+                            format!("{}…", annotation.text.chars().take(140).collect::<String>())
+                        }
+                        false => annotation.text.clone(),
+                    };
+                    let icon_name = match annotation.kind {
+                        AnnotationKind::Bookmark => "bookmark-new-symbolic",
+                        AnnotationKind::Highlight => "edit-select-all-symbolic",
+                    };
+
+                    let details = widget::column()
+                        .push(
+                            widget::row()
+                                .push(widget::icon::from_name(icon_name).size(16).icon())
+                                .push(widget::text::caption(
+                                    annotation.chapter_name.clone().unwrap_or_default(),
+                                ))
+                                .spacing(spacing.space_xxs)
+                                .align_items(Alignment::Center),
+                        )
+                        .push(widget::text(excerpt))
+                        .push_maybe(
+                            (!annotation.note.is_empty())
+                                .then(|| widget::text::caption(annotation.note.clone())),
+                        )
+                        .spacing(spacing.space_xxxs)
+                        .width(Length::Fill);
+
+                    let id = annotation.id;
+                    widget::row()
+                        .push(
+                            widget::button::button(details)
+                                .on_press(Message::OpenAnnotation(annotation))
+                                .width(Length::Fill),
+                        )
+                        .push(
+                            widget::button::icon(widget::icon::from_name("edit-delete-symbolic"))
+                                .on_press(Message::DeleteAnnotation(id)),
+                        )
+                        .align_items(Alignment::Center)
+                        .into()
+                })
+                .collect(),
+        };
+
+        let annotation_view = widget::column()
+            .push(widget::text(fl!("annotations")))
+            .push(widget::divider::horizontal::default())
+            .push(
+                widget::container(
+                    widget::column::with_children(annotations)
+                        .width(Length::Fill)
+                        .apply(scrollable),
+                )
+                .max_height(200)
+                .width(Length::Fill),
+            )
+            .push(
+                widget::button(widget::text(fl!("annotations-export")))
+                    .on_press_maybe(
+                        (!annotations_empty).then(|| Message::ExportAnnotations(book.clone())),
+                    )
+                    .padding(spacing.space_xxs),
+            )
+            .align_items(Alignment::Start)
+            .spacing(spacing.space_xxs)
+            .apply(container)
+            .style(cosmic::theme::Container::Card)
+            .padding(spacing.space_xs);
+
         widget::column()
             // .push(icon)
             // .push(title)
@@ -113,6 +337,9 @@ impl App {
             .push(interaction_row)
             .push(widget::divider::horizontal::default())
             .push(chapter_view)
+            .push(audio_row)
+            .push(export_row)
+            .push(annotation_view)
             .push(link)
             .align_items(Alignment::Center)
             .spacing(spacing.space_xs)
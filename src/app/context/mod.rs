@@ -1,3 +1,4 @@
 pub mod about_context;
 pub mod book_context;
+pub mod notifications_context;
 pub mod settings_context;
@@ -0,0 +1,110 @@
+use std::time::SystemTime;
+
+use crate::app::jobs::JobMessage;
+use crate::app::{App, Message};
+use crate::fl;
+use cosmic::iced::{Alignment, Length};
+use cosmic::widget::{self, *};
+use cosmic::{theme, Apply, Element};
+
+impl App {
+    /// The notification log, newest first
+    pub fn notifications_context(&self) -> Element<Message> {
+        let spacing = theme::active().cosmic().spacing;
+
+        let jobs = self.jobs.iter().map(|job| {
+            let progress = match job.total {
+                0 => 0.0,
+                total => job.done as f32 / total as f32,
+            };
+            widget::row()
+                .push(
+                    widget::column()
+                        .push(widget::text(fl!(
+                            "audio-export-progress",
+                            name = job.export.book.name.clone(),
+                            done = job.done,
+                            total = job.total
+                        )))
+                        .push(widget::progress_bar(0.0..=1.0, progress))
+                        .spacing(spacing.space_xxs)
+                        .width(Length::Fill),
+                )
+                .push(
+                    widget::button::text(fl!("cancel"))
+                        .on_press(Message::Job(JobMessage::Cancel(job.id))),
+                )
+                .align_items(Alignment::Center)
+                .spacing(spacing.space_xxs)
+                .apply(container)
+                .padding(spacing.space_xxs)
+                .style(cosmic::theme::Container::Card)
+                .into()
+        });
+        let jobs = match self.jobs.is_empty() {
+            true => None,
+            false => Some(
+                widget::column()
+                    .push(widget::text::heading(fl!("audio-exports")))
+                    .push(widget::column::with_children(jobs.collect()).spacing(spacing.space_xxs))
+                    .spacing(spacing.space_xxs)
+                    .width(Length::Fill),
+            ),
+        };
+
+        if self.notifications.is_empty() {
+            return widget::column()
+                .push_maybe(jobs)
+                .push(
+                    widget::text(fl!("notifications-empty"))
+                        .apply(container)
+                        .width(Length::Fill)
+                        .center_x(),
+                )
+                .spacing(spacing.space_xs)
+                .into();
+        }
+
+        let entries = self.notifications.log().map(|n| {
+            let elapsed = SystemTime::now()
+                .duration_since(n.time)
+                .unwrap_or_default()
+                .as_secs();
+            let age = match elapsed {
+                0..=59 => fl!("notifications-age-seconds", count = elapsed),
+                60..=3599 => fl!("notifications-age-minutes", count = elapsed / 60),
+                _ => fl!("notifications-age-hours", count = elapsed / 3600),
+            };
+
+            let mut row = widget::row()
+                .push(icon::from_name(n.level.icon_name()).size(16).icon())
+                .push(
+                    widget::column()
+                        .push(widget::text(n.text.clone()))
+                        .push(widget::text::caption(age))
+                        .width(Length::Fill),
+                );
+            if let Some(action) = &n.action {
+                row =
+                    row.push(widget::button::text(fl!("retry")).on_press(action.as_ref().clone()));
+            }
+            row.align_items(Alignment::Center)
+                .spacing(spacing.space_xxs)
+                .apply(container)
+                .padding(spacing.space_xxs)
+                .style(cosmic::theme::Container::Card)
+                .into()
+        });
+
+        widget::column()
+            .push_maybe(jobs)
+            .push(
+                widget::button::text(fl!("notifications-clear"))
+                    .on_press(Message::ClearNotifications),
+            )
+            .push(widget::column::with_children(entries.collect()).spacing(spacing.space_xxs))
+            .align_items(Alignment::End)
+            .spacing(spacing.space_xs)
+            .into()
+    }
+}
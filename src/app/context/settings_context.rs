@@ -1,28 +1,551 @@
 use std::collections::HashMap;
+use std::time::Duration;
 
-use crate::app::{App, Message, REPOSITORY};
+use crate::app::{key_binds, App, LogMessage, MenuAction, Message, REPOSITORY};
+use crate::config::{self, AppTheme, Config, LibrarySort, LibraryView, ReaderTheme, ReadingMode};
+use crate::core::audio::AudioFormat;
 use crate::core::source::{self, *};
 use crate::core::{self, Book, Chapter};
 use crate::fl;
 use cosmic::app::{message, Command, Core};
+use cosmic::cosmic_config::CosmicConfigEntry;
 use cosmic::iced::alignment::{Horizontal, Vertical};
 use cosmic::iced::{Alignment, Length};
 use cosmic::widget::{self, *};
 use cosmic::{cosmic_theme, theme, ApplicationExt, Apply, Element};
+use once_cell::sync::Lazy;
+
+/// Network cache lifetime presets offered in the settings drawer
+pub const CACHE_TTLS: [Duration; 4] = [
+    Duration::from_secs(15 * 60),
+    Duration::from_secs(60 * 60),
+    Duration::from_secs(6 * 60 * 60),
+    Duration::from_secs(24 * 60 * 60),
+];
+
+/// Names of [`CACHE_TTLS`] in the same order
+pub fn cache_ttl_labels() -> &'static [String] {
+    static LABELS: Lazy<Vec<String>> = Lazy::new(|| {
+        vec![
+            fl!("duration-minutes", count = 15),
+            fl!("duration-hours", count = 1),
+            fl!("duration-hours", count = 6),
+            fl!("duration-days", count = 1),
+        ]
+    });
+    &LABELS
+}
+
+/// Cover cache lifetime presets offered in the settings drawer
+pub const CACHE_IMAGE_TTLS: [Duration; 4] = [
+    Duration::from_secs(24 * 60 * 60),
+    Duration::from_secs(7 * 24 * 60 * 60),
+    Duration::from_secs(30 * 24 * 60 * 60),
+    Duration::from_secs(365 * 24 * 60 * 60),
+];
+
+/// Names of [`CACHE_IMAGE_TTLS`] in the same order
+pub fn cache_image_ttl_labels() -> &'static [String] {
+    static LABELS: Lazy<Vec<String>> = Lazy::new(|| {
+        vec![
+            fl!("duration-days", count = 1),
+            fl!("duration-weeks", count = 1),
+            fl!("duration-months", count = 1),
+            fl!("duration-years", count = 1),
+        ]
+    });
+    &LABELS
+}
+
+/// Network cache size presets offered in the settings drawer
+pub const CACHE_SIZES: [u64; 4] = [
+    50 * 1024 * 1024,
+    100 * 1024 * 1024,
+    250 * 1024 * 1024,
+    500 * 1024 * 1024,
+];
+
+/// Names of [`CACHE_SIZES`] in the same order
+pub fn cache_size_labels() -> &'static [String] {
+    static LABELS: Lazy<Vec<String>> = Lazy::new(|| {
+        CACHE_SIZES
+            .iter()
+            .map(|bytes| fl!("size-megabytes", count = bytes / (1024 * 1024)))
+            .collect()
+    });
+    &LABELS
+}
+
+#[derive(Debug, Clone)]
+pub enum SettingsMessage {
+    Theme(usize),
+    DefaultSort(usize),
+    LibraryView(usize),
+    FilterUnread(bool),
+    FilterDownloaded(bool),
+    FilterCompleted(bool),
+    /// Index into the library's source choices, 0 shows every source
+    FilterSource(usize),
+    /// Index into the library's tag choices, 0 shows every tag
+    FilterTag(usize),
+    /// Index into the library's category choices, 0 shows every category
+    FilterCategory(usize),
+    /// Index into [`config::reader_fonts`]
+    ReaderFont(usize),
+    FontSize(u16),
+    /// Grows or shrinks the font size from the shortcuts
+    FontSizeBy(i16),
+    LineHeight(usize),
+    ParagraphSpacing(u16),
+    ColumnWidth(u16),
+    Margin(u16),
+    ReaderTheme(usize),
+    ReadingMode(usize),
+    /// Enables or disables the source with the id
+    Source(String, bool),
+    DownloadConcurrency(u16),
+    PrefetchChapters(u16),
+    UpdateInterval(usize),
+    /// Selects one of the network cache lifetime presets
+    CacheTtl(usize),
+    /// Selects one of the cover cache lifetime presets
+    CacheImageTtl(usize),
+    /// Selects one of the network cache size presets
+    CacheSize(usize),
+    /// Selects one of [`core::logging::LEVELS`]
+    LogLevel(usize),
+    /// Index into [`core::tts::Backends::names`]
+    TtsBackend(usize),
+    /// Index into the voice picker, 0 is the engine default
+    TtsVoice(usize),
+    TtsRate(i16),
+    TtsPitch(i16),
+    AudioFormat(usize),
+    /// Text typed into a shortcut field
+    KeyBindInput(MenuAction, String),
+    /// Applies the typed shortcut of the action
+    KeyBind(MenuAction),
+    ResetKeyBinds,
+}
 
 impl App {
+    /// Applies a change from the settings drawer and persists it
+    pub fn update_settings(&mut self, message: SettingsMessage) -> Command<Message> {
+        let config = &mut self.config;
+        let mut voices_changed = false;
+        match message {
+            SettingsMessage::Theme(i) => {
+                config.app_theme = AppTheme::ALL.get(i).copied().unwrap_or_default()
+            }
+            SettingsMessage::DefaultSort(i) => {
+                config.default_sort = LibrarySort::ALL.get(i).copied().unwrap_or_default()
+            }
+            SettingsMessage::LibraryView(i) => {
+                config.library_view = LibraryView::ALL.get(i).copied().unwrap_or_default()
+            }
+            SettingsMessage::FilterUnread(on) => config.library_filter.unread = on,
+            SettingsMessage::FilterDownloaded(on) => config.library_filter.downloaded = on,
+            SettingsMessage::FilterCompleted(on) => config.library_filter.completed = on,
+            SettingsMessage::FilterSource(i) => {
+                config.library_filter.source = match i {
+                    0 => None,
+                    i => self.library_sources.get(i).cloned(),
+                }
+            }
+            SettingsMessage::FilterTag(i) => {
+                config.library_filter.tag = match i {
+                    0 => None,
+                    i => self.library_tags.get(i).cloned(),
+                }
+            }
+            SettingsMessage::FilterCategory(i) => {
+                config.library_filter.category = match i {
+                    0 => None,
+                    i => self.library_categories.get(i).cloned(),
+                }
+            }
+            SettingsMessage::ReaderFont(i) => {
+                if let Some(font) = config::reader_fonts().get(i) {
+                    config.reader_font = font.to_string();
+                }
+            }
+            SettingsMessage::FontSize(size) => {
+                config.font_size = size.clamp(Config::FONT_SIZE_MIN, Config::FONT_SIZE_MAX)
+            }
+            SettingsMessage::FontSizeBy(step) => {
+                config.font_size = config
+                    .font_size
+                    .saturating_add_signed(step)
+                    .clamp(Config::FONT_SIZE_MIN, Config::FONT_SIZE_MAX)
+            }
+            SettingsMessage::LineHeight(i) => {
+                if let Some(line_height) = config::LINE_HEIGHTS.get(i) {
+                    config.line_height = *line_height;
+                }
+            }
+            SettingsMessage::ParagraphSpacing(spacing) => {
+                config.paragraph_spacing = spacing.min(Config::PARAGRAPH_SPACING_MAX)
+            }
+            SettingsMessage::ColumnWidth(width) => {
+                config.column_width =
+                    width.clamp(Config::COLUMN_WIDTH_MIN, Config::COLUMN_WIDTH_MAX)
+            }
+            SettingsMessage::Margin(margin) => config.margin = margin.min(Config::MARGIN_MAX),
+            SettingsMessage::ReaderTheme(i) => {
+                config.reader_theme = ReaderTheme::ALL.get(i).copied().unwrap_or_default()
+            }
+            SettingsMessage::ReadingMode(i) => {
+                config.reading_mode = ReadingMode::ALL.get(i).copied().unwrap_or_default()
+            }
+            SettingsMessage::Source(id, enabled) => {
+                config.enabled_sources.retain(|s| *s != id);
+                if enabled {
+                    config.enabled_sources.push(id);
+                }
+            }
+            SettingsMessage::DownloadConcurrency(n) => {
+                config.download_concurrency = n.clamp(1, Config::DOWNLOAD_CONCURRENCY_MAX)
+            }
+            SettingsMessage::PrefetchChapters(n) => {
+                config.prefetch_chapters = n.min(Config::PREFETCH_CHAPTERS_MAX)
+            }
+            SettingsMessage::UpdateInterval(i) => {
+                if let Some(hours) = config::UPDATE_INTERVALS.get(i) {
+                    config.update_interval = *hours;
+                }
+            }
+            SettingsMessage::CacheTtl(i) => {
+                if let Some(ttl) = CACHE_TTLS.get(i) {
+                    config.cache_ttl_secs = ttl.as_secs();
+                }
+            }
+            SettingsMessage::CacheImageTtl(i) => {
+                if let Some(ttl) = CACHE_IMAGE_TTLS.get(i) {
+                    config.cache_image_ttl_secs = ttl.as_secs();
+                }
+            }
+            SettingsMessage::CacheSize(i) => {
+                if let Some(max_bytes) = CACHE_SIZES.get(i) {
+                    config.cache_max_bytes = *max_bytes;
+                }
+                // A smaller limit evicts entries when applied
+                self.save_config();
+                return Command::batch([self.apply_config(), self.measure_network_cache()]);
+            }
+            SettingsMessage::LogLevel(i) => {
+                if let Some(level) = core::logging::LEVELS.get(i) {
+                    config.log_level = level.to_string();
+                }
+            }
+            SettingsMessage::TtsBackend(i) => {
+                if let Some(name) = self.tts.names().get(i) {
+                    if config.tts_backend != *name {
+                        // Cut off mid paragraph, reading aloud goes on with the new engine
+                        self.tts_cancel.cancel();
+                        self.tts_cancel = core::tts::Cancel::default();
+                        self.tts.for_name(&config.tts_backend).stop();
+                        // Voice names belong to one engine
+                        config.tts_backend = name.to_string();
+                        config.tts_voice.clear();
+                        voices_changed = true;
+                    }
+                }
+            }
+            SettingsMessage::TtsVoice(i) => {
+                config.tts_voice = match i {
+                    0 => String::new(),
+                    i => self.tts_voices.get(i).cloned().unwrap_or_default(),
+                }
+            }
+            SettingsMessage::TtsRate(rate) => config.tts_rate = rate.clamp(-100, 100),
+            SettingsMessage::TtsPitch(pitch) => config.tts_pitch = pitch.clamp(-100, 100),
+            SettingsMessage::AudioFormat(i) => {
+                config.audio_format = AudioFormat::ALL.get(i).copied().unwrap_or_default()
+            }
+            // Typing a shortcut only keeps the text until it is submitted
+            SettingsMessage::KeyBindInput(action, input) => {
+                self.key_bind_inputs.insert(action, input);
+                return Command::none();
+            }
+            SettingsMessage::KeyBind(action) => {
+                let Some(input) = self.key_bind_inputs.remove(&action) else {
+                    return Command::none();
+                };
+                let input = input.trim().to_string();
+                if !input.is_empty() && key_binds::parse(&input).is_none() {
+                    return self.notify(LogMessage::Warning(fl!(
+                        "notification-invalid-shortcut",
+                        shortcut = input
+                    )));
+                }
+                // Taking over another action's shortcut would leave it
+                // showing one that no longer works
+                let taken = key_binds::parse(&input)
+                    .and_then(|key_bind| key_binds::conflict(&config.key_binds, action, &key_bind));
+                if let Some(other) = taken {
+                    return self.notify(LogMessage::Warning(fl!(
+                        "notification-shortcut-taken",
+                        shortcut = input,
+                        action = other.label()
+                    )));
+                }
+                config.key_binds.insert(action, input);
+            }
+            SettingsMessage::ResetKeyBinds => {
+                config.key_binds.clear();
+                self.key_bind_inputs.clear();
+            }
+        }
+
+        self.save_config();
+        if voices_changed {
+            self.stop_read_aloud();
+            return Command::batch([
+                self.apply_config(),
+                Command::perform(async move { message::app(Message::LoadVoices) }, |x| x),
+            ]);
+        }
+        self.apply_config()
+    }
+
+    /// Sizes up the network cache off the UI thread, it stats every entry
+    pub fn measure_network_cache(&self) -> Command<Message> {
+        Command::perform(
+            async move {
+                let size = tokio::task::spawn_blocking(|| core::http::CACHE.size())
+                    .await
+                    .unwrap_or_default();
+                message::app(Message::NetworkCacheSize(size))
+            },
+            |x| x,
+        )
+    }
+
+    /// Pushes the config into the parts of the app that don't read it on every frame
+    pub fn apply_config(&mut self) -> Command<Message> {
+        self.key_binds = key_binds::key_binds(&self.config.key_binds);
+        core::http::CACHE.set_config(self.config.cache_config());
+        core::logging::set_level(&self.config.log_level);
+        cosmic::app::command::set_theme(self.config.app_theme.theme())
+    }
+
+    pub fn save_config(&self) {
+        let Some(handler) = &self.config_handler else {
+            return;
+        };
+        if let Err(e) = self.config.write_entry(handler) {
+            tracing::error!("failed to save config: {e}");
+        }
+    }
+
+    /// Order, layout and filters of the library, shared with the library's popover
+    pub fn library_options(&self) -> widget::Column<Message> {
+        let config = &self.config;
+        let filter = &config.library_filter;
+        let position = |choices: &[String], chosen: &Option<String>| {
+            chosen
+                .as_ref()
+                .and_then(|c| choices.iter().position(|x| x == c))
+                .or(Some(0))
+        };
+
+        settings_section(fl!("settings-library"))
+            .push(setting_row(
+                fl!("settings-sort-by"),
+                widget::dropdown(
+                    LibrarySort::labels(),
+                    LibrarySort::ALL
+                        .iter()
+                        .position(|s| *s == config.default_sort),
+                    |i| Message::Settings(SettingsMessage::DefaultSort(i)),
+                ),
+            ))
+            .push(setting_row(
+                fl!("settings-library-view"),
+                widget::dropdown(
+                    LibraryView::labels(),
+                    LibraryView::ALL
+                        .iter()
+                        .position(|v| *v == config.library_view),
+                    |i| Message::Settings(SettingsMessage::LibraryView(i)),
+                ),
+            ))
+            .push(setting_row(
+                fl!("settings-unread-only"),
+                widget::toggler(None, filter.unread, |on| {
+                    Message::Settings(SettingsMessage::FilterUnread(on))
+                }),
+            ))
+            .push(setting_row(
+                fl!("settings-downloaded-only"),
+                widget::toggler(None, filter.downloaded, |on| {
+                    Message::Settings(SettingsMessage::FilterDownloaded(on))
+                }),
+            ))
+            .push(setting_row(
+                fl!("settings-completed-only"),
+                widget::toggler(None, filter.completed, |on| {
+                    Message::Settings(SettingsMessage::FilterCompleted(on))
+                }),
+            ))
+            .push(setting_row(
+                fl!("settings-source"),
+                widget::dropdown(
+                    &self.library_sources,
+                    position(&self.library_sources, &filter.source),
+                    |i| Message::Settings(SettingsMessage::FilterSource(i)),
+                ),
+            ))
+            .push(setting_row(
+                fl!("settings-tag"),
+                widget::dropdown(
+                    &self.library_tags,
+                    position(&self.library_tags, &filter.tag),
+                    |i| Message::Settings(SettingsMessage::FilterTag(i)),
+                ),
+            ))
+            .push(setting_row(
+                fl!("settings-category"),
+                widget::dropdown(
+                    &self.library_categories,
+                    position(&self.library_categories, &filter.category),
+                    |i| Message::Settings(SettingsMessage::FilterCategory(i)),
+                ),
+            ))
+    }
+
+    /// Typography and colours of the reader, shared with the reader's popover
+    pub fn reader_options(&self) -> widget::Column<Message> {
+        let config = &self.config;
+
+        settings_section(fl!("settings-reader"))
+            .push(setting_row(
+                fl!("settings-layout"),
+                widget::dropdown(
+                    ReadingMode::labels(),
+                    ReadingMode::ALL
+                        .iter()
+                        .position(|m| *m == config.reading_mode),
+                    |i| Message::Settings(SettingsMessage::ReadingMode(i)),
+                ),
+            ))
+            .push(setting_row(
+                fl!("settings-theme"),
+                widget::dropdown(
+                    ReaderTheme::labels(),
+                    ReaderTheme::ALL
+                        .iter()
+                        .position(|t| *t == config.reader_theme),
+                    |i| Message::Settings(SettingsMessage::ReaderTheme(i)),
+                ),
+            ))
+            .push(setting_row(
+                fl!("settings-font"),
+                widget::dropdown(
+                    config::reader_fonts(),
+                    config::reader_fonts()
+                        .iter()
+                        .position(|f| *f == config.reader_font),
+                    |i| Message::Settings(SettingsMessage::ReaderFont(i)),
+                ),
+            ))
+            .push(setting_row(
+                fl!("settings-font-size"),
+                stepper(config.font_size, 1, |n| {
+                    Message::Settings(SettingsMessage::FontSize(n))
+                }),
+            ))
+            .push(setting_row(
+                fl!("settings-line-height"),
+                widget::dropdown(
+                    &config::LINE_HEIGHT_LABELS,
+                    config::LINE_HEIGHTS
+                        .iter()
+                        .position(|h| *h == config.line_height),
+                    |i| Message::Settings(SettingsMessage::LineHeight(i)),
+                ),
+            ))
+            .push(setting_row(
+                fl!("settings-paragraph-spacing"),
+                stepper(config.paragraph_spacing, 2, |n| {
+                    Message::Settings(SettingsMessage::ParagraphSpacing(n))
+                }),
+            ))
+            .push(setting_row(
+                fl!("settings-column-width"),
+                stepper(config.column_width, Config::COLUMN_WIDTH_STEP, |n| {
+                    Message::Settings(SettingsMessage::ColumnWidth(n))
+                }),
+            ))
+            .push(setting_row(
+                fl!("settings-margins"),
+                stepper(config.margin, Config::MARGIN_STEP, |n| {
+                    Message::Settings(SettingsMessage::Margin(n))
+                }),
+            ))
+    }
+
     /// The settings page for this app.
     pub fn settings_context(&self) -> Element<Message> {
         let cosmic_theme::Spacing { space_xxs, .. } = theme::active().cosmic().spacing;
+        let config = &self.config;
 
         let icon = widget::svg(widget::svg::Handle::from_memory(
             &include_bytes!("../../../res/icons/hicolor/48x48/apps/settings-svgrepo-com.svg")[..],
         ))
         .height(64);
 
-        let display_options = widget::column()
-            .push(widget::row().push(widget::text("Display")))
-            .align_items(Alignment::Center);
+        let display_options = settings_section(fl!("settings-display")).push(setting_row(
+            fl!("settings-theme"),
+            widget::dropdown(
+                AppTheme::labels(),
+                AppTheme::ALL.iter().position(|t| *t == config.app_theme),
+                |i| Message::Settings(SettingsMessage::Theme(i)),
+            ),
+        ));
+
+        let library_options = self.library_options();
+
+        let reader_options = self.reader_options();
+
+        let source_options = source::all().into_iter().fold(
+            settings_section(fl!("settings-sources")),
+            |section, source| {
+                let id = source.as_str();
+                let enabled = config.enabled_sources.contains(&id);
+                section.push(setting_row(
+                    id.clone(),
+                    widget::toggler(None, enabled, move |enabled| {
+                        Message::Settings(SettingsMessage::Source(id.clone(), enabled))
+                    }),
+                ))
+            },
+        );
+
+        let download_options = settings_section(fl!("settings-downloads"))
+            .push(setting_row(
+                fl!("settings-parallel-downloads"),
+                stepper(config.download_concurrency, 1, |n| {
+                    Message::Settings(SettingsMessage::DownloadConcurrency(n))
+                }),
+            ))
+            .push(setting_row(
+                fl!("settings-prefetch-chapters"),
+                stepper(config.prefetch_chapters, 1, |n| {
+                    Message::Settings(SettingsMessage::PrefetchChapters(n))
+                }),
+            ))
+            .push(setting_row(
+                fl!("settings-update-interval"),
+                widget::dropdown(
+                    config::update_interval_labels(),
+                    config::UPDATE_INTERVALS
+                        .iter()
+                        .position(|h| *h == config.update_interval),
+                    |i| Message::Settings(SettingsMessage::UpdateInterval(i)),
+                ),
+            ));
+
         let contact_info = widget::column()
             .push(
                 widget::button::link(REPOSITORY)
@@ -31,13 +554,167 @@ impl App {
             )
             .align_items(Alignment::Center);
 
-        let clear_storage_btn = widget::button("Clear Storage").on_press(Message::ClearStorage);
+        let network_options = settings_section(fl!("settings-network"))
+            .push(setting_row(
+                fl!("settings-cache-page-ttl"),
+                widget::dropdown(
+                    cache_ttl_labels(),
+                    CACHE_TTLS
+                        .iter()
+                        .position(|ttl| ttl.as_secs() == config.cache_ttl_secs),
+                    |i| Message::Settings(SettingsMessage::CacheTtl(i)),
+                ),
+            ))
+            .push(setting_row(
+                fl!("settings-cache-image-ttl"),
+                widget::dropdown(
+                    cache_image_ttl_labels(),
+                    CACHE_IMAGE_TTLS
+                        .iter()
+                        .position(|ttl| ttl.as_secs() == config.cache_image_ttl_secs),
+                    |i| Message::Settings(SettingsMessage::CacheImageTtl(i)),
+                ),
+            ))
+            .push(setting_row(
+                fl!("settings-cache-size"),
+                widget::dropdown(
+                    cache_size_labels(),
+                    CACHE_SIZES
+                        .iter()
+                        .position(|size| *size == config.cache_max_bytes),
+                    |i| Message::Settings(SettingsMessage::CacheSize(i)),
+                ),
+            ))
+            .push(setting_row(
+                fl!(
+                    "settings-network-cache-used",
+                    size = format!("{:.1}", self.network_cache_size as f64 / (1024.0 * 1024.0))
+                ),
+                widget::button(fl!("settings-clear-network-cache"))
+                    .on_press(Message::ClearNetworkCache),
+            ));
+
+        let diagnostics = settings_section(fl!("settings-diagnostics"))
+            .push(setting_row(
+                fl!("settings-log-level"),
+                widget::dropdown(
+                    &core::logging::LEVELS,
+                    core::logging::LEVELS
+                        .iter()
+                        .position(|level| *level == config.log_level),
+                    |i| Message::Settings(SettingsMessage::LogLevel(i)),
+                ),
+            ))
+            .push(setting_row(
+                fl!("settings-cover-cache"),
+                widget::text({
+                    let stats = self.covers.stats();
+                    fl!(
+                        "settings-cover-cache-stats",
+                        count = stats.entries,
+                        size = format!("{:.1}", stats.bytes as f64 / (1024.0 * 1024.0)),
+                        budget = format!("{:.0}", stats.budget as f64 / (1024.0 * 1024.0)),
+                        hits = stats.hits,
+                        misses = stats.misses
+                    )
+                }),
+            ))
+            .push(widget::button(fl!("settings-export-logs")).on_press(Message::ExportLogs));
+
+        let read_aloud_options = settings_section(fl!("settings-read-aloud"))
+            .push(setting_row(
+                fl!("settings-speech-engine"),
+                widget::dropdown(
+                    self.tts.names(),
+                    self.tts
+                        .names()
+                        .iter()
+                        .position(|name| *name == config.tts_backend),
+                    |i| Message::Settings(SettingsMessage::TtsBackend(i)),
+                ),
+            ))
+            .push(setting_row(
+                fl!("settings-voice"),
+                widget::dropdown(
+                    &self.tts_voices,
+                    match config.tts_voice.is_empty() {
+                        true => Some(0),
+                        false => self.tts_voices.iter().position(|v| *v == config.tts_voice),
+                    },
+                    |i| Message::Settings(SettingsMessage::TtsVoice(i)),
+                ),
+            ))
+            .push(setting_row(
+                fl!("settings-tts-rate", rate = config.tts_rate),
+                widget::slider(-100..=100, config.tts_rate, |rate| {
+                    Message::Settings(SettingsMessage::TtsRate(rate))
+                })
+                .width(Length::Fixed(160.0)),
+            ))
+            .push(setting_row(
+                fl!("settings-tts-pitch", pitch = config.tts_pitch),
+                widget::slider(-100..=100, config.tts_pitch, |pitch| {
+                    Message::Settings(SettingsMessage::TtsPitch(pitch))
+                })
+                .width(Length::Fixed(160.0)),
+            ))
+            .push(setting_row(
+                fl!("settings-audio-format"),
+                widget::dropdown(
+                    &AudioFormat::LABELS,
+                    AudioFormat::ALL
+                        .iter()
+                        .position(|f| *f == config.audio_format),
+                    |i| Message::Settings(SettingsMessage::AudioFormat(i)),
+                ),
+            ));
+
+        let shortcuts = MenuAction::ALL
+            .iter()
+            .fold(
+                settings_section(fl!("settings-shortcuts")),
+                |section, action| {
+                    let action = *action;
+                    section.push(setting_row(
+                        action.label(),
+                        widget::text_input(fl!("shortcut-unbound"), self.key_bind_text(action))
+                            .on_input(move |input| {
+                                Message::Settings(SettingsMessage::KeyBindInput(action, input))
+                            })
+                            .on_submit(Message::Settings(SettingsMessage::KeyBind(action)))
+                            .width(Length::Fixed(160.0)),
+                    ))
+                },
+            )
+            .push(
+                widget::button(fl!("settings-reset-shortcuts"))
+                    .on_press(Message::Settings(SettingsMessage::ResetKeyBinds)),
+            );
+
+        let clear_storage_btn =
+            widget::button(fl!("settings-clear-storage")).on_press(Message::ClearStorage);
 
         widget::column()
             .push(icon)
             .push(widget::divider::horizontal::default())
             .push(display_options)
             .push(widget::divider::horizontal::default())
+            .push(library_options)
+            .push(widget::divider::horizontal::default())
+            .push(reader_options)
+            .push(widget::divider::horizontal::default())
+            .push(read_aloud_options)
+            .push(widget::divider::horizontal::default())
+            .push(source_options)
+            .push(widget::divider::horizontal::default())
+            .push(download_options)
+            .push(widget::divider::horizontal::default())
+            .push(shortcuts)
+            .push(widget::divider::horizontal::default())
+            .push(network_options)
+            .push(widget::divider::horizontal::default())
+            .push(diagnostics)
+            .push(widget::divider::horizontal::default())
             .push(contact_info)
             .push(widget::divider::horizontal::default())
             .push(clear_storage_btn)
@@ -46,3 +723,67 @@ impl App {
             .into()
     }
 }
+
+/// A titled column the settings of one topic are pushed into
+fn settings_section<'a>(
+    title: impl Into<std::borrow::Cow<'a, str>>,
+) -> widget::Column<'a, Message> {
+    let cosmic_theme::Spacing { space_xxs, .. } = theme::active().cosmic().spacing;
+
+    widget::column()
+        .push(widget::row().push(widget::text(title)))
+        .spacing(space_xxs)
+        .align_items(Alignment::Center)
+}
+
+/// A label on the left and its control on the right
+fn setting_row<'a>(
+    label: impl Into<std::borrow::Cow<'a, str>>,
+    control: impl Into<Element<'a, Message>>,
+) -> Element<'a, Message> {
+    widget::row()
+        .push(widget::text(label))
+        .push(widget::horizontal_space(Length::Fill))
+        .push(control)
+        .align_items(Alignment::Center)
+        .into()
+}
+
+impl App {
+    /// What a shortcut field shows: the text being typed, the remapped
+    /// binding, or the defaults
+    fn key_bind_text(&self, action: MenuAction) -> String {
+        if let Some(input) = self
+            .key_bind_inputs
+            .get(&action)
+            .or(self.config.key_binds.get(&action))
+        {
+            return input.clone();
+        }
+        key_binds::defaults()
+            .into_iter()
+            .filter(|(a, _)| *a == action)
+            .map(|(_, binding)| binding)
+            .collect::<Vec<&str>>()
+            .join(", ")
+    }
+}
+
+/// Minus and plus buttons around a number
+fn stepper<'a>(value: u16, step: u16, on_change: impl Fn(u16) -> Message) -> Element<'a, Message> {
+    let cosmic_theme::Spacing { space_xxs, .. } = theme::active().cosmic().spacing;
+
+    widget::row()
+        .push(
+            widget::button::icon(icon::from_name("list-remove-symbolic"))
+                .on_press(on_change(value.saturating_sub(step))),
+        )
+        .push(widget::text(value.to_string()))
+        .push(
+            widget::button::icon(icon::from_name("list-add-symbolic"))
+                .on_press(on_change(value.saturating_add(step))),
+        )
+        .spacing(space_xxs)
+        .align_items(Alignment::Center)
+        .into()
+}
@@ -0,0 +1,103 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+use cosmic::iced::futures::SinkExt;
+use cosmic::iced::Subscription;
+
+use super::storage::Storage;
+use super::Message;
+use crate::core::{self, audio::AudioExport};
+
+#[derive(Debug, Clone)]
+pub enum JobMessage {
+    /// Adds an audio export to the back of the queue
+    Queue(AudioExport),
+    /// Chapters done out of the total for the running job
+    Progress(u64, usize, usize),
+    Finished(u64, core::Result<PathBuf>),
+    /// Drops a job, stopping it if it is running
+    Cancel(u64),
+}
+
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub id: u64,
+    pub export: AudioExport,
+    pub done: usize,
+    pub total: usize,
+}
+
+/// Audio exports run one at a time, in the order they were queued
+#[derive(Default)]
+pub struct Jobs {
+    next_id: u64,
+    queue: VecDeque<Job>,
+}
+
+impl Jobs {
+    pub fn push(&mut self, export: AudioExport) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let total = export.chapters.len();
+        self.queue.push_back(Job {
+            id,
+            export,
+            done: 0,
+            total,
+        });
+        id
+    }
+
+    pub fn progress(&mut self, id: u64, done: usize, total: usize) {
+        if let Some(job) = self.queue.iter_mut().find(|j| j.id == id) {
+            job.done = done;
+            job.total = total;
+        }
+    }
+
+    /// Takes the job out of the queue, which also ends its subscription
+    pub fn remove(&mut self, id: u64) -> Option<Job> {
+        let index = self.queue.iter().position(|j| j.id == id)?;
+        self.queue.remove(index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Job> {
+        self.queue.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Runs the job at the front of the queue, with chapter text from storage
+    pub fn subscription(&self, storage: &Storage) -> Subscription<Message> {
+        struct JobSubscription;
+
+        let Some(job) = self.queue.front() else {
+            return Subscription::none();
+        };
+        let (id, export, data) = (job.id, job.export.clone(), storage.data());
+
+        cosmic::iced::subscription::channel(
+            (std::any::TypeId::of::<JobSubscription>(), id),
+            16,
+            move |mut output| async move {
+                let res = export
+                    .run(Some(data), |done, total| {
+                        // A full channel only loses a progress tick
+                        _ = output.try_send(Message::Job(JobMessage::Progress(id, done, total)));
+                    })
+                    .await;
+                _ = output
+                    .send(Message::Job(JobMessage::Finished(id, res)))
+                    .await;
+
+                // Stays idle until the finished job is dropped from the queue
+                std::future::pending().await
+            },
+        )
+    }
+}
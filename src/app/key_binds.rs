@@ -0,0 +1,244 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::collections::HashMap;
+
+use cosmic::iced::keyboard::{key::Named, Key};
+use cosmic::widget::menu::key_bind::{KeyBind, Modifier};
+
+use super::MenuAction;
+
+/// Names accepted for keys that don't type a character
+const NAMED_KEYS: [(&str, Named); 24] = [
+    ("Left", Named::ArrowLeft),
+    ("Right", Named::ArrowRight),
+    ("Up", Named::ArrowUp),
+    ("Down", Named::ArrowDown),
+    ("PageUp", Named::PageUp),
+    ("PageDown", Named::PageDown),
+    ("Home", Named::Home),
+    ("End", Named::End),
+    ("Space", Named::Space),
+    ("Tab", Named::Tab),
+    ("Enter", Named::Enter),
+    ("Escape", Named::Escape),
+    ("F1", Named::F1),
+    ("F2", Named::F2),
+    ("F3", Named::F3),
+    ("F4", Named::F4),
+    ("F5", Named::F5),
+    ("F6", Named::F6),
+    ("F7", Named::F7),
+    ("F8", Named::F8),
+    ("F9", Named::F9),
+    ("F10", Named::F10),
+    ("F11", Named::F11),
+    ("F12", Named::F12),
+];
+
+const MODIFIERS: [(&str, Modifier); 4] = [
+    ("Super", Modifier::Super),
+    ("Ctrl", Modifier::Ctrl),
+    ("Alt", Modifier::Alt),
+    ("Shift", Modifier::Shift),
+];
+
+/// Shortcuts used when the settings don't remap an action
+pub fn defaults() -> Vec<(MenuAction, &'static str)> {
+    use MenuAction::*;
+    vec![
+        (Settings, "Ctrl+,"),
+        (NextChapter, "Ctrl+Right"),
+        (PreviousChapter, "Ctrl+Left"),
+        (PageDown, "Right"),
+        (PageDown, "PageDown"),
+        (PageDown, "Space"),
+        (PageUp, "Left"),
+        (PageUp, "PageUp"),
+        (ToggleLibrary, "Ctrl+D"),
+        (OpenSearch, "Ctrl+F"),
+        (Refresh, "F5"),
+        (NextNavPage, "Ctrl+Tab"),
+        (PreviousNavPage, "Ctrl+Shift+Tab"),
+        (Back, "Alt+Left"),
+        (Forward, "Alt+Right"),
+        (ToggleFullscreen, "F11"),
+        (FontSizeIncrease, "Ctrl+="),
+        (FontSizeDecrease, "Ctrl+-"),
+        (ReadAloud, "Ctrl+Shift+S"),
+    ]
+}
+
+/// The defaults with every remapped action replaced by its binding from the
+/// settings, an empty binding leaves the action without a shortcut
+pub fn key_binds(remapped: &HashMap<MenuAction, String>) -> HashMap<KeyBind, MenuAction> {
+    let mut key_binds = HashMap::new();
+
+    for (action, binding) in defaults() {
+        if remapped.contains_key(&action) {
+            continue;
+        }
+        if let Some(key_bind) = parse(binding) {
+            key_binds.insert(key_bind, action);
+        }
+    }
+    for (action, binding) in remapped {
+        match parse(binding) {
+            Some(key_bind) => {
+                key_binds.insert(key_bind, *action);
+            }
+            None if binding.is_empty() => (),
+            None => tracing::warn!("ignoring invalid shortcut {binding:?} for {action:?}"),
+        }
+    }
+
+    key_binds
+}
+
+/// The other action `key_bind` already triggers with the remapped
+/// shortcuts, which binding it to `action` would take over
+pub fn conflict(
+    remapped: &HashMap<MenuAction, String>,
+    action: MenuAction,
+    key_bind: &KeyBind,
+) -> Option<MenuAction> {
+    key_binds(remapped)
+        .get(key_bind)
+        .copied()
+        .filter(|other| *other != action)
+}
+
+/// Reads a binding like `Ctrl+Shift+Tab`, the key comes last. Modifiers
+/// can come in any order, equal bindings parse the same
+pub fn parse(binding: &str) -> Option<KeyBind> {
+    // `Ctrl++` binds the plus key
+    let (modifiers, key) = match binding.strip_suffix("++") {
+        Some(modifiers) => (modifiers, "+"),
+        None => binding.rsplit_once('+').unwrap_or(("", binding)),
+    };
+
+    let named = modifiers
+        .split('+')
+        .map(str::trim)
+        .filter(|m| !m.is_empty())
+        .map(|m| {
+            MODIFIERS
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(m))
+                .map(|(_, modifier)| *modifier)
+        })
+        .collect::<Option<Vec<Modifier>>>()?;
+    let modifiers = MODIFIERS
+        .iter()
+        .map(|(_, modifier)| *modifier)
+        .filter(|modifier| named.contains(modifier))
+        .collect();
+
+    let key = key.trim();
+    let key = match NAMED_KEYS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(key))
+    {
+        Some((_, named)) => Key::Named(*named),
+        None if key.chars().count() == 1 => Key::Character(key.to_lowercase().into()),
+        None => return None,
+    };
+
+    Some(KeyBind { modifiers, key })
+}
+
+/// Writes a binding the way [`parse`] reads it
+pub fn format(key_bind: &KeyBind) -> String {
+    let mut parts = MODIFIERS
+        .iter()
+        .filter(|(_, modifier)| key_bind.modifiers.contains(modifier))
+        .map(|(name, _)| name.to_string())
+        .collect::<Vec<String>>();
+
+    parts.push(match &key_bind.key {
+        Key::Named(named) => NAMED_KEYS
+            .iter()
+            .find(|(_, n)| n == named)
+            .map(|(name, _)| name.to_string())
+            .unwrap_or_else(|| format!("{named:?}")),
+        Key::Character(c) => c.to_uppercase(),
+        Key::Unidentified => String::new(),
+    });
+
+    parts.join("+")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bind(binding: &str) -> KeyBind {
+        parse(binding).unwrap_or_else(|| panic!("{binding:?} should parse"))
+    }
+
+    #[test]
+    fn bindings_round_trip() {
+        for binding in [
+            "F5",
+            "Space",
+            "Alt+Left",
+            "Ctrl+Shift+Tab",
+            "Super+Ctrl+Alt+Shift+PageDown",
+            "Ctrl+,",
+            "Ctrl+-",
+            "Ctrl+=",
+            "Ctrl++",
+            "Ctrl+Shift+S",
+        ] {
+            assert_eq!(format(&bind(binding)), binding);
+        }
+    }
+
+    #[test]
+    fn defaults_round_trip() {
+        for (action, binding) in defaults() {
+            assert_eq!(format(&bind(binding)), binding, "default for {action:?}");
+        }
+    }
+
+    #[test]
+    fn parse_ignores_case_spaces_and_modifier_order() {
+        assert_eq!(bind("shift + ctrl + a"), bind("Ctrl+Shift+A"));
+        assert_eq!(format(&bind("shift+ctrl+a")), "Ctrl+Shift+A");
+        assert_eq!(bind("pageup"), bind("PageUp"));
+    }
+
+    #[test]
+    fn parse_rejects_unknown_keys_and_modifiers() {
+        for binding in ["", "Ctrl+", "Ctrl+Foo", "Hyper+A", "AB"] {
+            assert_eq!(parse(binding), None, "{binding:?}");
+        }
+    }
+
+    #[test]
+    fn conflict_finds_the_action_holding_the_shortcut() {
+        let mut remapped = HashMap::new();
+        assert_eq!(
+            conflict(&remapped, MenuAction::OpenSearch, &bind("F5")),
+            Some(MenuAction::Refresh)
+        );
+        // Rebinding an action to its own shortcut is no conflict
+        assert_eq!(conflict(&remapped, MenuAction::Refresh, &bind("F5")), None);
+
+        remapped.insert(MenuAction::Refresh, "Ctrl+K".to_string());
+        assert_eq!(
+            conflict(&remapped, MenuAction::OpenSearch, &bind("F5")),
+            None
+        );
+        assert_eq!(
+            conflict(&remapped, MenuAction::OpenSearch, &bind("Ctrl+K")),
+            Some(MenuAction::Refresh)
+        );
+
+        // Unbinding frees every default of the action
+        remapped.insert(MenuAction::PageDown, String::new());
+        assert_eq!(
+            conflict(&remapped, MenuAction::Refresh, &bind("Space")),
+            None
+        );
+    }
+}
@@ -0,0 +1,102 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime};
+
+use super::Message;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl Level {
+    pub fn icon_name(&self) -> &'static str {
+        match self {
+            Level::Info => "dialog-information-symbolic",
+            Level::Success => "emblem-ok-symbolic",
+            Level::Warning => "dialog-warning-symbolic",
+            Level::Error => "dialog-error-symbolic",
+        }
+    }
+
+    /// How long the toast stays up, problems linger so they can be read
+    pub fn timeout(&self) -> Duration {
+        match self {
+            Level::Info | Level::Success => Duration::from_secs(4),
+            Level::Warning | Level::Error => Duration::from_secs(8),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub id: u64,
+    pub level: Level,
+    pub text: String,
+    pub time: SystemTime,
+    /// Message sent by the notification's action button, e.g. a retry
+    pub action: Option<Box<Message>>,
+}
+
+/// Transient toasts on top of a persistent log of everything posted
+#[derive(Default)]
+pub struct Notifications {
+    next_id: u64,
+    log: VecDeque<Notification>,
+    toasts: Vec<u64>,
+}
+
+impl Notifications {
+    const LOG_LIMIT: usize = 200;
+
+    /// Posts a notification and shows it as a toast, returns its id
+    pub fn push(&mut self, level: Level, text: String, action: Option<Message>) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.log.push_back(Notification {
+            id,
+            level,
+            text,
+            time: SystemTime::now(),
+            action: action.map(Box::new),
+        });
+        if self.log.len() > Self::LOG_LIMIT {
+            self.log.pop_front();
+        }
+        self.toasts.push(id);
+
+        id
+    }
+
+    /// Hides the toast, the notification stays in the log
+    pub fn dismiss(&mut self, id: u64) {
+        self.toasts.retain(|t| *t != id);
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Notification> {
+        self.log.iter().find(|n| n.id == id)
+    }
+
+    pub fn clear(&mut self) {
+        self.log.clear();
+        self.toasts.clear();
+    }
+
+    pub fn toasts(&self) -> impl Iterator<Item = &Notification> {
+        self.toasts.iter().filter_map(|id| self.get(*id))
+    }
+
+    /// The log, newest first
+    pub fn log(&self) -> impl Iterator<Item = &Notification> {
+        self.log.iter().rev()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.log.is_empty()
+    }
+}
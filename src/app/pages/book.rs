@@ -0,0 +1,432 @@
+use std::collections::HashSet;
+
+use crate::app::context::book_context::BookDetails;
+use crate::app::*;
+use crate::core::cover::CoverSize;
+use crate::core::{AnnotationKind, Book, Chapter};
+use crate::fl;
+use cosmic::app::{message, Command};
+use cosmic::iced::{Alignment, Length, Size};
+use cosmic::widget::*;
+use cosmic::{theme, Apply, Element};
+use once_cell::sync::Lazy;
+
+/// Synopses longer than this many characters are cut until expanded
+const SYNOPSIS_PREVIEW: usize = 400;
+
+/// Which chapters the book page lists
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ChapterFilter {
+    #[default]
+    All,
+    Unread,
+    Downloaded,
+    Bookmarked,
+}
+
+impl ChapterFilter {
+    pub const ALL: [ChapterFilter; 4] = [
+        ChapterFilter::All,
+        ChapterFilter::Unread,
+        ChapterFilter::Downloaded,
+        ChapterFilter::Bookmarked,
+    ];
+    pub fn labels() -> &'static [String] {
+        static LABELS: Lazy<Vec<String>> = Lazy::new(|| {
+            vec![
+                fl!("chapter-filter-all"),
+                fl!("chapter-filter-unread"),
+                fl!("chapter-filter-downloaded"),
+                fl!("chapter-filter-bookmarked"),
+            ]
+        });
+        &LABELS
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    /// Callback for the chapter filter dropdown
+    FilterChanged(usize),
+    /// Shows the whole synopsis, or the preview again
+    ToggleSynopsis,
+}
+
+impl From<Message> for crate::app::Message {
+    fn from(message: Message) -> Self {
+        pages::Message::Book(message).into()
+    }
+}
+
+/// A book with its chapters and reading state, pushed over the page it was
+/// opened from
+pub struct BookPage {
+    /// The listing it was opened with, shown until `details` are read
+    pub book: Book,
+    pub details: Option<BookDetails>,
+    filter: ChapterFilter,
+    synopsis_expanded: bool,
+}
+
+impl BookPage {
+    pub fn new(book: Book) -> Self {
+        Self {
+            book,
+            details: None,
+            filter: ChapterFilter::All,
+            synopsis_expanded: false,
+        }
+    }
+}
+
+impl pages::page::Page for BookPage {
+    fn id(&self) -> Page {
+        Page::Book
+    }
+
+    fn title(&self, app: &App) -> String {
+        self.book(app).map(|b| b.name.clone()).unwrap_or_default()
+    }
+
+    fn update(&mut self, _app: &mut App, message: pages::Message) -> Command<crate::app::Message> {
+        match message {
+            pages::Message::Book(Message::FilterChanged(index)) => {
+                self.filter = ChapterFilter::ALL[index];
+            }
+            pages::Message::Book(Message::ToggleSynopsis) => {
+                self.synopsis_expanded = !self.synopsis_expanded;
+            }
+            _ => {}
+        }
+        Command::none()
+    }
+
+    fn refresh(&mut self, _app: &mut App) -> Command<crate::app::Message> {
+        let url = self.book.url.clone();
+        Command::perform(
+            async move { message::app(crate::app::Message::RefreshBook(url)) },
+            |x| x,
+        )
+    }
+
+    /// The stored book once loaded, it may have been renamed since
+    fn book<'a>(&'a self, _app: &'a App) -> Option<&'a Book> {
+        self.details.as_ref().map(|d| &d.book).or(Some(&self.book))
+    }
+
+    fn view<'a>(&'a self, app: &'a App, _size: Size) -> Element<'a, crate::app::Message> {
+        let spacing = theme::active().cosmic().spacing;
+
+        // Shows the listing until storage answers
+        let details = self.details.as_ref();
+        let book = details.map(|d| &d.book).unwrap_or(&self.book);
+        let chapters = details.map(|d| d.chapters.as_slice()).unwrap_or_default();
+        let is_read = |c: &Chapter| {
+            c.url
+                .as_ref()
+                .is_some_and(|u| details.is_some_and(|d| d.read.contains(u)))
+        };
+        let is_downloaded = |c: &Chapter| {
+            c.url
+                .as_ref()
+                .is_some_and(|u| details.is_some_and(|d| d.downloaded.contains(u)))
+        };
+        let bookmarked = details
+            .map(|d| {
+                d.annotations
+                    .iter()
+                    .filter(|a| a.kind == AnnotationKind::Bookmark)
+                    .map(|a| &a.chapter_url)
+                    .collect::<HashSet<&String>>()
+            })
+            .unwrap_or_default();
+        let is_bookmarked = |c: &Chapter| c.url.as_ref().is_some_and(|u| bookmarked.contains(u));
+
+        let read_count = chapters.iter().filter(|c| is_read(c)).count();
+        let downloaded_count = chapters.iter().filter(|c| is_downloaded(c)).count();
+        let all_read = !chapters.is_empty() && read_count == chapters.len();
+        let has_chapters = !chapters.is_empty();
+
+        let cover = widget::image(app.cover(&book.url, CoverSize::Detail))
+            .content_fit(cosmic::iced::ContentFit::Contain)
+            .border_radius([spacing.space_xxs as f32; 4])
+            .width(Length::Fixed(240.0));
+
+        let actions = widget::row()
+            .push(
+                widget::button(widget::text(fl!("continue-reading")))
+                    .style(theme::Button::Suggested)
+                    .on_press_maybe(details.filter(|_| has_chapters).map(|d| {
+                        crate::app::Message::ReadChapter(book.clone(), continue_index(d))
+                    })),
+            )
+            .push(
+                widget::button(widget::text(fl!("download-all"))).on_press_maybe(
+                    (downloaded_count < chapters.len())
+                        .then(|| crate::app::Message::DownloadAll(book.clone())),
+                ),
+            )
+            .push(
+                widget::button(widget::text(match all_read {
+                    true => fl!("mark-unread"),
+                    false => fl!("mark-read"),
+                }))
+                .on_press_maybe(
+                    has_chapters.then(|| crate::app::Message::MarkRead(book.clone(), !all_read)),
+                ),
+            )
+            .push(
+                widget::button(widget::text(match book.in_library {
+                    true => fl!("library-remove"),
+                    false => fl!("library-add"),
+                }))
+                .on_press(crate::app::Message::LibraryToggle(book.clone())),
+            )
+            .push(
+                widget::button(widget::text(fl!("site")))
+                    .on_press(crate::app::Message::LaunchUrl(book.url.clone())),
+            )
+            .spacing(spacing.space_xxs);
+
+        let metadata = widget::column()
+            .push(widget::text::title3(book.name.clone()))
+            .push(widget::text::caption(book.source.clone()))
+            .push_maybe((!book.tags.is_empty()).then(|| widget::text(book.tags.join(" · "))))
+            .push(widget::text::caption(fl!(
+                "book-chapter-stats",
+                count = chapters.len(),
+                read = read_count,
+                downloaded = downloaded_count
+            )))
+            .push(actions)
+            .spacing(spacing.space_xs)
+            .width(Length::Fill);
+
+        let overview = widget::row()
+            .push(cover)
+            .push(metadata)
+            .spacing(spacing.space_m)
+            .align_items(Alignment::Start);
+
+        let synopsis = book.description.as_ref().map(|description| {
+            let long = description.chars().count() > SYNOPSIS_PREVIEW;
+            let text = match long && !self.synopsis_expanded {
+                true => format!(
+                    "{}…",
+                    description
+                        .chars()
+                        .take(SYNOPSIS_PREVIEW)
+                        .collect::<String>()
+                ),
+                false => description.clone(),
+            };
+            widget::column()
+                .push(widget::text::heading(fl!("synopsis")))
+                .push(widget::text(text))
+                .push_maybe(long.then(|| {
+                    widget::button::link(match self.synopsis_expanded {
+                        true => fl!("show-less"),
+                        false => fl!("show-more"),
+                    })
+                    .on_press(Message::ToggleSynopsis.into())
+                    .padding(0)
+                }))
+                .spacing(spacing.space_xxs)
+        });
+
+        let listed = chapters
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| match self.filter {
+                ChapterFilter::All => true,
+                ChapterFilter::Unread => !is_read(c),
+                ChapterFilter::Downloaded => is_downloaded(c),
+                ChapterFilter::Bookmarked => is_bookmarked(c),
+            })
+            .map(|(i, chapter)| {
+                let name = chapter
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| format!("Chapter {}", i + 1));
+                // Dates come as ISO 8601, the day is enough here
+                let released = chapter
+                    .released
+                    .as_deref()
+                    .map(|r| r.get(..10).unwrap_or(r).to_owned())
+                    .unwrap_or_default();
+                let state_icon = |shown: bool, name: &'static str| {
+                    shown.then(|| widget::icon::from_name(name).size(16).icon())
+                };
+
+                let row = widget::row()
+                    .push(widget::text(name).width(Length::Fill))
+                    .push(widget::text::caption(released))
+                    .push_maybe(state_icon(is_bookmarked(chapter), "bookmark-new-symbolic"))
+                    .push_maybe(state_icon(
+                        is_downloaded(chapter),
+                        "folder-download-symbolic",
+                    ))
+                    .push_maybe(state_icon(is_read(chapter), "object-select-symbolic"))
+                    .spacing(spacing.space_xs)
+                    .align_items(Alignment::Center);
+                widget::button::button(row)
+                    .on_press(crate::app::Message::ReadChapter(book.clone(), i))
+                    .width(Length::Fill)
+                    .into()
+            })
+            .collect::<Vec<Element<crate::app::Message>>>();
+
+        let chapter_list: Element<crate::app::Message> = match (details, listed.is_empty()) {
+            (None, _) => widget::text(fl!("loading")).into(),
+            (Some(_), true) if chapters.is_empty() => widget::text(fl!("chapters-empty")).into(),
+            (Some(_), true) => widget::text(fl!("chapters-filtered-empty")).into(),
+            (Some(_), false) => widget::column::with_children(listed)
+                .width(Length::Fill)
+                .into(),
+        };
+
+        let chapter_view = widget::column()
+            .push(
+                widget::row()
+                    .push(widget::text::heading(fl!("chapters")).width(Length::Fill))
+                    .push(widget::dropdown(
+                        ChapterFilter::labels(),
+                        ChapterFilter::ALL.iter().position(|f| *f == self.filter),
+                        |i| Message::FilterChanged(i).into(),
+                    ))
+                    .align_items(Alignment::Center),
+            )
+            .push(widget::divider::horizontal::default())
+            .push(chapter_list)
+            .spacing(spacing.space_xxs)
+            .apply(container)
+            .style(cosmic::theme::Container::Card)
+            .padding(spacing.space_xs);
+
+        widget::column()
+            .push(overview)
+            .push_maybe(synopsis)
+            .push(chapter_view)
+            .spacing(spacing.space_m)
+            .padding([spacing.space_xs, spacing.space_m])
+            .max_width(960)
+            .apply(container)
+            .center_x()
+            .width(Length::Fill)
+            .apply(scrollable)
+            .height(Length::Fill)
+            .into()
+    }
+}
+
+impl App {
+    /// Opens the book page over the current page, closing the drawer's preview
+    pub fn open_book(&mut self, book: Book) -> Command<crate::app::Message> {
+        self.core.window.show_context = false;
+        let load = self.load_book(book.clone());
+        let push = self.push_page(Box::new(BookPage::new(book)));
+        Command::batch([push, load])
+    }
+
+    /// Downloads every chapter of the book that isn't stored yet
+    pub fn download_all(&mut self, book: Book) -> Command<crate::app::Message> {
+        let id = book.id();
+        let Some(details) = self.book_details().find(|d| d.book.id() == id) else {
+            return Command::none();
+        };
+        let missing = details
+            .chapters
+            .iter()
+            .filter(|c| {
+                c.url
+                    .as_ref()
+                    .is_some_and(|u| !details.downloaded.contains(u))
+            })
+            .cloned()
+            .collect::<Vec<Chapter>>();
+        if missing.is_empty() {
+            return self.notify(LogMessage::Log(fl!(
+                "notification-downloaded-all",
+                name = book.name
+            )));
+        }
+
+        let notify = self.notify(LogMessage::Log(fl!(
+            "notification-downloading",
+            count = missing.len(),
+            name = book.name
+        )));
+        Command::batch([notify, Self::download(book.url, missing)])
+    }
+
+    /// Downloads the chapters of each book by url, notifying how many
+    pub fn download_chapters(
+        &mut self,
+        books: Vec<(String, Vec<Chapter>)>,
+    ) -> Command<crate::app::Message> {
+        let books = books
+            .into_iter()
+            .filter(|(_, chapters)| !chapters.is_empty())
+            .collect::<Vec<_>>();
+        let count = books
+            .iter()
+            .map(|(_, chapters)| chapters.len())
+            .sum::<usize>();
+        let notify = self.notify(LogMessage::Log(fl!(
+            "notification-batch-downloading",
+            count = count,
+            books = books.len()
+        )));
+        let downloads = books
+            .into_iter()
+            .map(|(book_url, chapters)| Self::download(book_url, chapters));
+        Command::batch(std::iter::once(notify).chain(downloads))
+    }
+
+    fn download(book_url: String, chapters: Vec<Chapter>) -> Command<crate::app::Message> {
+        Command::perform(
+            async move {
+                let chapters = App::download_ahead(&book_url, chapters, None, 0).await;
+                message::app(crate::app::Message::Prefetched(book_url, chapters))
+            },
+            |x| x,
+        )
+    }
+
+    /// Marks every chapter of the book as read, or as unread
+    pub fn mark_read(&mut self, book: Book, read: bool) -> Command<crate::app::Message> {
+        let id = book.id();
+        let Some(details) = self.book_details().find(|d| d.book.id() == id) else {
+            return Command::none();
+        };
+        let urls = details
+            .chapters
+            .iter()
+            .filter_map(|c| c.url.clone())
+            .collect::<Vec<String>>();
+        for details in self.book_details_mut().filter(|d| d.book.id() == id) {
+            match read {
+                true => details.read.extend(urls.iter().cloned()),
+                false => details.read.clear(),
+            }
+        }
+        self.storage.write(move |data| data.set_read(&urls, read))
+    }
+}
+
+/// Where Continue reading opens the book: the chapter it was left at,
+/// else the first one not read yet
+fn continue_index(details: &BookDetails) -> usize {
+    let is_read = |c: &Chapter| c.url.as_ref().is_some_and(|u| details.read.contains(u));
+    details
+        .progress
+        .as_ref()
+        .and_then(|p| {
+            details
+                .chapters
+                .iter()
+                .position(|c| c.url.as_ref() == Some(&p.chapter_url))
+        })
+        .or_else(|| details.chapters.iter().position(|c| !is_read(c)))
+        .unwrap_or(0)
+}
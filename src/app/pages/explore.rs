@@ -1,12 +1,63 @@
 use crate::app::*;
+use crate::fl;
 
+use cosmic::app::{message, Command};
 use cosmic::iced::alignment::{Horizontal, Vertical};
 use cosmic::iced::{Alignment, Length, Padding, Size};
 use cosmic::widget::*;
 use cosmic::{theme, Apply, Element};
+use once_cell::sync::Lazy;
 
-impl App {
-    pub fn view_explore(&self, size: Size) -> Element<Message> {
+/// Focused by the search shortcut
+pub static SEARCH_INPUT: Lazy<Id> = Lazy::new(Id::unique);
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    /// Callback for changing the search text input field
+    InputChanged(String),
+}
+
+impl From<Message> for crate::app::Message {
+    fn from(message: Message) -> Self {
+        pages::Message::Explore(message).into()
+    }
+}
+
+/// Searches the enabled sources, the results are kept by the app since
+/// storage updates them
+#[derive(Default)]
+pub struct ExplorePage {
+    input: String,
+}
+
+impl pages::page::Page for ExplorePage {
+    fn id(&self) -> Page {
+        Page::Explore
+    }
+
+    fn title(&self, _app: &App) -> String {
+        fl!("explore")
+    }
+
+    fn update(&mut self, _app: &mut App, message: pages::Message) -> Command<crate::app::Message> {
+        if let pages::Message::Explore(Message::InputChanged(input)) = message {
+            self.input = input;
+        }
+        Command::none()
+    }
+
+    fn refresh(&mut self, _app: &mut App) -> Command<crate::app::Message> {
+        if self.input.is_empty() {
+            return Command::none();
+        }
+        let term = self.input.clone();
+        Command::perform(
+            async move { message::app(crate::app::Message::ExploreSearch(term)) },
+            |x| x,
+        )
+    }
+
+    fn view<'a>(&'a self, app: &'a App, size: Size) -> Element<'a, crate::app::Message> {
         let spacing = theme::active().cosmic().spacing;
         let item_width = 180;
         let item_height = 400;
@@ -34,15 +85,16 @@ impl App {
             .align_items(Alignment::Center)
             .spacing(spacing.space_xs)
             .push(
-                cosmic::widget::search_input("Search for books...", &self.explore_input)
+                cosmic::widget::search_input("Search for books...", &self.input)
+                    .id(SEARCH_INPUT.clone())
                     .width(Length::Fill)
-                    .on_input(Message::ExploreInputChanged)
-                    .on_submit_maybe(Some(Message::ExploreSearch(self.explore_input.clone()))),
+                    .on_input(|input| Message::InputChanged(input).into())
+                    .on_submit_maybe(Some(crate::app::Message::ExploreSearch(self.input.clone()))),
             )
             .apply(container);
 
         let content;
-        if self.explore_results.is_empty() {
+        if app.explore_results.is_empty() {
             content = cosmic::widget::container("No results")
                 .width(Length::Fill)
                 .height(Length::Fill)
@@ -59,12 +111,8 @@ impl App {
 
             let card_size = Size::new(item_width as f32, item_height as f32);
             let mut col = 0;
-            for (_i, url) in self.explore_results.clone().into_iter().enumerate() {
-                let book = match self.data_manager.get_book(&url) {
-                    Ok(Some(b)) => b.clone(),
-                    _ => continue,
-                };
-                grid = grid.push(self.create_book_card(&book, card_size));
+            for book in &app.explore_results {
+                grid = grid.push(app.create_book_card(book, card_size));
                 col += 1;
                 if col >= cols {
                     col = 0;
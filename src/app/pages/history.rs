@@ -1,109 +1,117 @@
-use std::collections::HashMap;
-use std::path::Display;
-
 use crate::app::*;
-use crate::core::Book;
+use crate::core::cover::CoverSize;
+use crate::core::HistoryEntry;
 use crate::fl;
-use cosmic::app::{Command, Core};
+use cosmic::app::Command;
 use cosmic::iced::alignment::{Horizontal, Vertical};
-use cosmic::iced::window::Icon;
-use cosmic::iced::{Alignment, Length, Padding, Size};
+use cosmic::iced::{Alignment, Length, Size};
 use cosmic::widget::*;
-use cosmic::{cosmic_theme, theme, ApplicationExt, Apply, Element};
+use cosmic::{theme, Apply, Element};
+
+/// How many of the last read books the page lists
+const HISTORY_LIMIT: usize = 100;
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    /// The books read lately, from storage
+    Loaded(Vec<HistoryEntry>),
+}
+
+impl From<Message> for crate::app::Message {
+    fn from(message: Message) -> Self {
+        pages::Message::History(message).into()
+    }
+}
 
-impl App {
-    pub fn view_history(&self, size: Size) -> Element<Message> {
-        todo!("history tab view")
-        // let spacing = theme::active().cosmic().spacing;
-        // let item_width = 180;
-        // let item_height = 400;
-        // let (width, _height) = (
-        //     (size.width.floor() as usize)
-        //         .checked_sub(spacing.space_s as usize)
-        //         .unwrap_or(0)
-        //         .max(item_width),
-        //     (size.height.floor() as usize).max(item_height),
-        // );
+/// Recently read books, reloaded each time the page shows
+#[derive(Default)]
+pub struct HistoryPage {
+    entries: Vec<HistoryEntry>,
+}
+
+impl pages::page::Page for HistoryPage {
+    fn id(&self) -> Page {
+        Page::History
+    }
 
-        // let (cols, column_spacing) = {
-        //     let width_m1 = width.checked_sub(item_width).unwrap_or(0);
-        //     let cols_m1 = width_m1 / (item_width + spacing.space_xxs as usize);
-        //     let cols = cols_m1 + 1;
-        //     let spacing = width_m1
-        //         .checked_div(cols_m1)
-        //         .unwrap_or(0)
-        //         .checked_sub(item_width)
-        //         .unwrap_or(0);
-        //     (cols, spacing as u16)
-        // };
+    fn title(&self, _app: &App) -> String {
+        fl!("history")
+    }
 
-        // let search_bar = cosmic::widget::row()
-        //     .align_items(Alignment::Center)
-        //     .spacing(spacing.space_xs)
-        //     .push(
-        //         cosmic::widget::search_input("Search for books...", &self.explore_input)
-        //             .width(Length::Fill)
-        //             .on_input(Message::SearchInputChanged)
-        //             .on_submit_maybe(Some(Message::ExploreSearch(self.explore_input.clone()))),
-        //     )
-        //     .apply(container);
+    fn update(&mut self, app: &mut App, message: pages::Message) -> Command<crate::app::Message> {
+        if let pages::Message::History(Message::Loaded(entries)) = message {
+            let urls = entries.iter().map(|e| e.book.url.clone()).collect();
+            self.entries = entries;
+            return app.load_covers(urls, CoverSize::Thumbnail);
+        }
+        Command::none()
+    }
+
+    fn refresh(&mut self, app: &mut App) -> Command<crate::app::Message> {
+        app.storage.perform(
+            |data| data.get_history(HISTORY_LIMIT),
+            |entries| Message::Loaded(entries).into(),
+        )
+    }
 
-        // let mut grid = cosmic::widget::grid()
-        //     .width(Length::Fill)
-        //     .column_spacing(column_spacing)
-        //     .row_spacing(spacing.space_xs)
-        //     // .padding([0, spacing.space_m].into())
-        //     // .height(Length::Fill)
-        //     .insert_row();
+    fn view<'a>(&'a self, app: &'a App, _size: Size) -> Element<'a, crate::app::Message> {
+        let spacing = theme::active().cosmic().spacing;
 
-        // let card_size = Size::new(item_width as f32, item_height as f32);
-        // // let cols = 4;
-        // let mut col = 0;
-        // for (_i, url) in self.explore_results.clone().into_iter().enumerate() {
-        //     let book = match self.books.get(&url) {
-        //         Some(b) => b.clone(),
-        //         None => continue,
-        //     };
-        //     grid = grid.push(self.create_book_card(&book, card_size));
-        //     col += 1;
-        //     if col >= cols {
-        //         col = 0;
-        //         grid = grid.insert_row();
-        //     }
-        // }
+        if self.entries.is_empty() {
+            return widget::container(widget::text(fl!("history-empty")))
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .padding(spacing.space_xxs)
+                .align_x(Horizontal::Center)
+                .align_y(Vertical::Center)
+                .into();
+        }
 
-        // let grid = grid
-        //     .apply(container)
-        //     .center_x()
-        //     .height(Length::Fill)
-        //     .width(Length::Fill)
-        //     .padding(Padding {
-        //         top: 0.0,
-        //         bottom: 0.0,
-        //         left: spacing.space_xs as f32,
-        //         right: spacing.space_m as f32,
-        //     })
-        //     .apply(scrollable)
-        //     .height(Length::Fill)
-        //     .width(Length::Fill)
-        //     .apply(container);
+        let rows = self.entries.iter().map(|entry| {
+            let book = &entry.book;
+            let chapter = match (&entry.chapter_name, entry.chapter_index) {
+                (Some(name), _) => name.clone(),
+                (None, Some(index)) => format!("Chapter {}", index + 1),
+                (None, None) => fl!("history-chapter-gone"),
+            };
+            // Disabled once the chapter is gone, the rest of the row still
+            // opens the book
+            let continue_reading = widget::button(widget::text(fl!("continue-reading")))
+                .style(theme::Button::Suggested)
+                .on_press_maybe(
+                    entry
+                        .chapter_index
+                        .map(|index| crate::app::Message::ReadChapter(book.clone(), index)),
+                );
+            let row = widget::row()
+                .push(
+                    widget::image(app.cover(&book.url, CoverSize::Thumbnail))
+                        .content_fit(cosmic::iced::ContentFit::Cover)
+                        .border_radius([spacing.space_xxxs as f32; 4])
+                        .width(Length::Fixed(40.0))
+                        .height(Length::Fixed(56.0)),
+                )
+                .push(
+                    widget::column()
+                        .push(widget::text(book.name.clone()))
+                        .push(widget::text::caption(chapter))
+                        .width(Length::Fill),
+                )
+                .push(continue_reading)
+                .spacing(spacing.space_xs)
+                .align_items(Alignment::Center);
+            widget::button::button(row)
+                .on_press(crate::app::Message::OpenBook(book.clone()))
+                .width(Length::Fill)
+                .into()
+        });
 
-        // column()
-        //     .push(search_bar)
-        //     // .push(flex_row)
-        //     .push(grid)
-        //     .spacing(spacing.space_xs)
-        //     .height(Length::Fill)
-        //     .width(Length::Fill)
-        //     // .align_items(Alignment::Center)
-        //     // .apply(container)
-        //     // .padding(Padding {
-        //     //     top: spacing.space_xl as f32,
-        //     //     bottom: 0.0,
-        //     //     left: spacing.space_xs as f32,
-        //     //     right: spacing.space_m as f32,
-        //     // })
-        //     // .apply(scrollable)
-        //     .into()
+        widget::column::with_children(rows.collect())
+            .spacing(spacing.space_xxxs)
+            .width(Length::Fill)
+            .apply(scrollable)
+            .apply(container)
+            .padding([0, spacing.space_m, 0, spacing.space_xs])
+            .into()
     }
 }
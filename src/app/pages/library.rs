@@ -1,123 +1,325 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+
 use crate::app::*;
-use crate::core::Book;
+use crate::config::{LibrarySort, LibraryView};
+use crate::core::cover::CoverSize;
+use crate::core::search::{Match, Query};
+use crate::core::{Book, Chapter, LibraryStats};
+use crate::fl;
 
+use cosmic::app::{message, Command};
 use cosmic::iced::alignment::{Horizontal, Vertical};
 use cosmic::iced::{Alignment, Length, Padding, Size};
 use cosmic::widget::*;
 use cosmic::{theme, Apply, Element};
-use fuzzy_matcher::FuzzyMatcher;
+use fuzzy_matcher::skim::SkimMatcherV2;
 use itertools::Itertools;
 
-// #[derive(Debug, Clone)]
-// pub enum Message {
-//     LibraryLoad,
-//     /// Toggles to in_library flag for cache and db for book
-//     LibraryToggle(Book),
+#[derive(Debug, Clone)]
+pub enum Message {
+    /// Callback for changing the library text input field, searched as a
+    /// [`Query`]
+    InputChanged(String),
+    /// Opens or closes the sort, view and filter popover
+    ToggleOptions,
+    /// Enters or leaves selection mode, clearing the selection
+    ToggleSelecting,
+    /// Selects or deselects the book with the id, shift selects the range
+    /// from the book clicked before
+    Select(String),
+    /// Selects every book listed
+    SelectAll,
+    ClearSelection,
+    /// Opens or closes the category picker
+    ToggleMove,
+    /// Callback for the new category field
+    CategoryInput(String),
+    /// Runs the action on every selected book
+    Batch(BatchAction),
+}
 
-//     /// Searches the library db (unused)
-//     LibrarySearch(String),
+#[derive(Debug, Clone)]
+pub enum BatchAction {
+    Remove,
+    /// Files the books under the category, or none
+    MoveTo(Option<String>),
+    MarkRead,
+    DownloadUnread,
+    Refresh,
+    Export,
+}
+
+impl From<Message> for crate::app::Message {
+    fn from(message: Message) -> Self {
+        pages::Message::Library(message).into()
+    }
+}
 
-//     /// Callback for changing the library text input field
-//     /// Is used for the library view's fuzzy search
-//     LibraryInputChanged(String),
+/// The books in the library, searched and sorted, filtered and laid out as
+/// set in the config
+#[derive(Default)]
+pub struct LibraryPage {
+    input: String,
+    show_options: bool,
+    /// Clicking a book selects it instead of opening it
+    selecting: bool,
+    /// Ids of the selected books
+    selected: HashSet<String>,
+    /// The book clicked last, where a shift-click range starts
+    anchor: Option<String>,
+    show_move: bool,
+    category_input: String,
+}
 
-//     /// The result for the library search
-//     LibraryResult(Vec<String>),
+/// The books a library search found, kept so that typing on only scores
+/// the books the shorter input matched
+pub struct SearchResults {
+    input: String,
+    query: Query,
+    /// How each matching book matched, by id
+    matches: HashMap<String, Match>,
+}
 
-//     /// Does nothing
-//     Ignore,
-// }
+impl LibraryPage {
+    fn select(&mut self, app: &App, id: String) {
+        let range = self
+            .anchor
+            .as_ref()
+            .filter(|_| app.modifiers.shift())
+            .and_then(|anchor| {
+                let listed = app
+                    .library_books()
+                    .into_iter()
+                    .map(|(b, _)| b.id())
+                    .collect::<Vec<String>>();
+                let from = listed.iter().position(|i| i == anchor)?;
+                let to = listed.iter().position(|i| *i == id)?;
+                Some(listed[from.min(to)..=from.max(to)].to_vec())
+            });
+        match range {
+            Some(range) => self.selected.extend(range),
+            None => {
+                if !self.selected.remove(&id) {
+                    self.selected.insert(id.clone());
+                }
+            }
+        }
+        self.anchor = Some(id);
+    }
 
-// impl From<Message> for crate::app::Message {
-//     fn from(message: Message) -> Self {
-//         crate::app::Message::LibraryMessage(message)
-//     }
-// }
+    fn batch(&mut self, app: &mut App, action: BatchAction) -> Command<crate::app::Message> {
+        let books = app
+            .library
+            .iter()
+            .filter(|b| self.selected.contains(&b.id()))
+            .cloned()
+            .collect::<Vec<Book>>();
+        if books.is_empty() {
+            return Command::none();
+        }
 
-impl App {
-    pub fn view_library(&self, size: Size) -> Element<crate::app::Message> {
+        let each = |books: Vec<Book>, to_message: fn(Book) -> crate::app::Message| {
+            Command::batch(books.into_iter().map(move |book| {
+                let next = to_message(book);
+                Command::perform(async move { message::app(next) }, |x| x)
+            }))
+        };
+        match action {
+            BatchAction::Remove => {
+                // The books leave the list, and the selection with them
+                self.selected.clear();
+                self.anchor = None;
+                app.remove_books(books)
+            }
+            BatchAction::MoveTo(category) => {
+                self.show_move = false;
+                self.category_input.clear();
+                app.move_books(books, category)
+            }
+            BatchAction::MarkRead => app.mark_books_read(books),
+            BatchAction::DownloadUnread => app.download_unread(books),
+            BatchAction::Refresh => each(books, |book| crate::app::Message::RefreshBook(book.url)),
+            BatchAction::Export => each(books, crate::app::Message::ExportBook),
+        }
+    }
+
+    /// The selection count with the actions for the selected books
+    fn batch_bar<'a>(&'a self, app: &'a App) -> Element<'a, crate::app::Message> {
         let spacing = theme::active().cosmic().spacing;
-        let item_width = 180;
-        let item_height = 400;
-        let (width, _height) = (
-            (size.width.floor() as usize)
-                .checked_sub(spacing.space_s as usize)
-                .unwrap_or(0)
-                .max(item_width),
-            (size.height.floor() as usize).max(item_height),
-        );
+        let any = !self.selected.is_empty();
+        let action = |label: String, batch: BatchAction| {
+            widget::button(widget::text(label))
+                .on_press_maybe(any.then(|| Message::Batch(batch).into()))
+        };
 
-        let (cols, column_spacing) = {
-            let width_m1 = width.checked_sub(item_width).unwrap_or(0);
-            let cols_m1 = width_m1 / (item_width + spacing.space_xxs as usize);
-            let cols = cols_m1 + 1;
-            let spacing = width_m1
-                .checked_div(cols_m1)
-                .unwrap_or(0)
-                .checked_sub(item_width)
-                .unwrap_or(0);
-            (cols, spacing as u16)
+        let categories = app.library_categories.iter().skip(1).map(|category| {
+            widget::button(widget::text(category.clone()))
+                .on_press(Message::Batch(BatchAction::MoveTo(Some(category.clone()))).into())
+                .width(Length::Fill)
+                .into()
+        });
+        let new_category = self.category_input.trim().to_string();
+        let move_menu =
+            widget::column::with_children(categories.collect())
+                .push(
+                    widget::button(widget::text(fl!("category-none")))
+                        .on_press(Message::Batch(BatchAction::MoveTo(None)).into())
+                        .width(Length::Fill),
+                )
+                .push(
+                    widget::text_input(fl!("category-new"), &self.category_input)
+                        .on_input(|input| Message::CategoryInput(input).into())
+                        .on_submit_maybe((!new_category.is_empty()).then(|| {
+                            Message::Batch(BatchAction::MoveTo(Some(new_category))).into()
+                        })),
+                )
+                .spacing(spacing.space_xxs)
+                .width(Length::Fixed(240.0))
+                .apply(widget::container)
+                .style(theme::Container::Dialog)
+                .padding(spacing.space_s);
+        let move_button = widget::button(widget::text(fl!("batch-move")))
+            .on_press_maybe(any.then(|| Message::ToggleMove.into()));
+        let move_button = match self.show_move && any {
+            true => widget::popover(move_button)
+                .popup(move_menu)
+                .on_close(Message::ToggleMove.into()),
+            false => widget::popover(move_button),
+        };
+
+        widget::row()
+            .push(
+                widget::text(fl!("library-selected", count = self.selected.len()))
+                    .width(Length::Fill),
+            )
+            .push(
+                widget::button(widget::text(fl!("select-all"))).on_press(Message::SelectAll.into()),
+            )
+            .push(
+                widget::button(widget::text(fl!("select-none")))
+                    .on_press_maybe(any.then(|| Message::ClearSelection.into())),
+            )
+            .push(action(fl!("batch-remove"), BatchAction::Remove))
+            .push(move_button)
+            .push(action(fl!("mark-read"), BatchAction::MarkRead))
+            .push(action(
+                fl!("batch-download-unread"),
+                BatchAction::DownloadUnread,
+            ))
+            .push(action(fl!("batch-refresh"), BatchAction::Refresh))
+            .push(action(fl!("batch-export"), BatchAction::Export))
+            .spacing(spacing.space_xxs)
+            .align_items(Alignment::Center)
+            .padding([0, spacing.space_s])
+            .into()
+    }
+}
+
+impl pages::page::Page for LibraryPage {
+    fn id(&self) -> Page {
+        Page::Library
+    }
+
+    fn title(&self, _app: &App) -> String {
+        fl!("library")
+    }
+
+    fn update(&mut self, app: &mut App, message: pages::Message) -> Command<crate::app::Message> {
+        let pages::Message::Library(message) = message else {
+            return Command::none();
+        };
+        match message {
+            Message::InputChanged(input) => {
+                app.search_library(&input);
+                self.input = input;
+            }
+            Message::ToggleOptions => self.show_options = !self.show_options,
+            Message::ToggleSelecting => {
+                self.selecting = !self.selecting;
+                self.selected.clear();
+                self.anchor = None;
+                self.show_move = false;
+            }
+            Message::Select(id) => self.select(app, id),
+            Message::SelectAll => {
+                self.selected = app
+                    .library_books()
+                    .into_iter()
+                    .map(|(b, _)| b.id())
+                    .collect();
+            }
+            Message::ClearSelection => {
+                self.selected.clear();
+                self.anchor = None;
+            }
+            Message::ToggleMove => self.show_move = !self.show_move,
+            Message::CategoryInput(input) => self.category_input = input,
+            Message::Batch(action) => return self.batch(app, action),
+        }
+        Command::none()
+    }
+
+    fn refresh(&mut self, _app: &mut App) -> Command<crate::app::Message> {
+        Command::perform(
+            async move { message::app(crate::app::Message::UpdateLibrary) },
+            |x| x,
+        )
+    }
+
+    fn view<'a>(&'a self, app: &'a App, size: Size) -> Element<'a, crate::app::Message> {
+        let spacing = theme::active().cosmic().spacing;
+
+        let options_button = widget::button::icon(icon::from_name("view-more-symbolic"))
+            .on_press(Message::ToggleOptions.into());
+        let options_button = match self.show_options {
+            true => widget::popover(options_button)
+                .popup(
+                    app.library_options()
+                        .width(Length::Fixed(360.0))
+                        .apply(widget::container)
+                        .style(theme::Container::Dialog)
+                        .padding(spacing.space_s),
+                )
+                .on_close(Message::ToggleOptions.into()),
+            false => widget::popover(options_button),
         };
 
         let search_bar = cosmic::widget::row()
             .align_items(Alignment::Center)
             .spacing(spacing.space_xs)
             .push(
-                cosmic::widget::search_input("Search for books...", &self.library_input)
+                cosmic::widget::search_input(fl!("library-search"), &self.input)
                     .width(Length::Fill)
-                    .on_input(Message::LibraryInputChanged)
-                    .on_submit_maybe(Some(Message::Ignore)),
-                // .on_submit_maybe(Some(Message::LibrarySearch(self.library_input.clone()))),
+                    .on_input(|input| Message::InputChanged(input).into())
+                    .on_submit_maybe(Some(crate::app::Message::Ignore)),
+            )
+            .push(options_button)
+            .push(
+                widget::button::icon(icon::from_name("object-select-symbolic"))
+                    .on_press(Message::ToggleSelecting.into()),
             )
             .apply(container);
 
-        let books = if self.library_input.is_empty() {
-            match self.data_manager.get_library_books() {
-                Ok(b) => b,
-                Err(e) => {
-                    dbg!(e);
-                    vec![]
-                }
-            }
-        } else {
-            match self.get_library_books_like(self.library_input.clone()) {
-                Ok(b) => b,
-                Err(e) => {
-                    dbg!(e);
-                    vec![]
-                }
-            }
-        };
+        let books = app.library_books();
+        let selection = self.selecting.then_some(&self.selected);
 
-        let content;
-        if books.is_empty() {
-            content = cosmic::widget::container("No results")
+        let content: Element<crate::app::Message> = if books.is_empty() {
+            cosmic::widget::container("No results")
                 .width(Length::Fill)
                 .height(Length::Fill)
                 .padding(spacing.space_xxs)
                 .align_x(Horizontal::Center)
                 .align_y(Vertical::Center)
-                .style(cosmic::theme::Container::default());
+                .style(cosmic::theme::Container::default())
+                .into()
         } else {
-            let mut grid = cosmic::widget::grid()
-                .width(Length::Fill)
-                .column_spacing(column_spacing)
-                .row_spacing(spacing.space_xs)
-                .insert_row();
-
-            let card_size = Size::new(item_width as f32, item_height as f32);
-            let mut col = 0;
-            for (_i, book) in books.clone().into_iter().enumerate() {
-                grid = grid.push(self.create_book_card(&book, card_size));
-                col += 1;
-                if col >= cols {
-                    col = 0;
-                    grid = grid.insert_row();
-                }
-            }
-
-            let grid = grid
-                .apply(container)
+            let list = match app.config.library_view {
+                LibraryView::CoverGrid => app.library_grid(books, size, 180, selection),
+                LibraryView::CompactGrid => app.library_grid(books, size, 120, selection),
+                LibraryView::List => app.library_list(books, selection),
+            };
+            list.apply(container)
                 .center_x()
                 .height(Length::Fill)
                 .width(Length::Fill)
@@ -129,13 +331,13 @@ impl App {
                 })
                 .apply(scrollable)
                 .height(Length::Fill)
-                .width(Length::Fill);
-
-            content = container::Container::new(grid);
-        }
+                .width(Length::Fill)
+                .into()
+        };
 
         column()
             .push(search_bar)
+            .push_maybe(self.selecting.then(|| self.batch_bar(app)))
             .push(content)
             .spacing(spacing.space_xxs)
             .apply(container)
@@ -143,24 +345,331 @@ impl App {
             .width(Length::Fill)
             .into()
     }
+}
 
-    /// Fuzzy searches library for books like search_term
-    fn get_library_books_like(
-        &self,
-        search_term: String,
-    ) -> Result<Vec<Book>, Box<dyn std::error::Error>> {
-        let books = self.data_manager.get_library_books()?;
-        let matcher = fuzzy_matcher::skim::SkimMatcherV2::default();
-        let books = books
+impl App {
+    /// The library books the filters and search let through with how they
+    /// matched, best matches first while searching for text, else in the
+    /// configured order
+    fn library_books(&self) -> Vec<(&Book, Option<&Match>)> {
+        let none = LibraryStats::default();
+        let filter = &self.config.library_filter;
+        let search = self.library_search.as_ref();
+        let mut books = self
+            .library
             .iter()
-            .map(|b| (matcher.fuzzy_match(&b.name, &search_term), b))
-            .filter_map(|(score_opt, b)| match score_opt {
-                Some(s) => Some((s, b.clone())),
-                None => None,
+            .filter_map(|b| {
+                let id = b.id();
+                let found = match search {
+                    Some(search) => Some(search.matches.get(&id)?),
+                    None => None,
+                };
+                let stats = self.library_stats.get(&id).unwrap_or(&none);
+                filter.matches(b, stats).then_some((b, stats, found))
             })
-            .sorted_by(|a, b| b.0.cmp(&a.0))
-            .map(|(_, b)| b.clone())
-            .collect::<Vec<Book>>();
-        Ok(books)
+            .collect::<Vec<(&Book, &LibraryStats, Option<&Match>)>>();
+
+        // Stable sorts, so ties keep the order the library was stored in
+        if search.is_some_and(|s| s.query.is_ranked()) {
+            books.sort_by_key(|(_, _, found)| Reverse(found.map(|m| m.score)));
+            return books.into_iter().map(|(b, _, found)| (b, found)).collect();
+        }
+        match self.config.default_sort {
+            LibrarySort::DateAdded => books.sort_by_key(|(_, s, _)| Reverse(s.added)),
+            LibrarySort::Title => books.sort_by_cached_key(|(b, _, _)| b.name.to_lowercase()),
+            LibrarySort::LastRead => books.sort_by_key(|(_, s, _)| Reverse(s.last_read)),
+            LibrarySort::LastUpdated => {
+                books.sort_by(|(_, a, _), (_, b, _)| b.last_updated.cmp(&a.last_updated))
+            }
+            LibrarySort::Unread => books.sort_by_key(|(_, s, _)| Reverse(s.unread)),
+            LibrarySort::Source => {
+                books.sort_by_cached_key(|(b, _, _)| (b.source.clone(), b.name.to_lowercase()))
+            }
+        }
+        books.into_iter().map(|(b, _, found)| (b, found)).collect()
+    }
+
+    /// Searches the library for the input. When it only narrows the last
+    /// search, just the books that one found are scored again
+    pub fn search_library(&mut self, input: &str) {
+        let query = Query::parse(input);
+        if query.is_empty() {
+            self.library_search = None;
+            return;
+        }
+
+        let previous = self
+            .library_search
+            .take()
+            .filter(|previous| query.narrows(&previous.query));
+        let matcher = SkimMatcherV2::default();
+        let matches = self
+            .library
+            .iter()
+            .filter_map(|b| {
+                let id = b.id();
+                if previous
+                    .as_ref()
+                    .is_some_and(|p| !p.matches.contains_key(&id))
+                {
+                    return None;
+                }
+                Some((id, query.score(b, &matcher)?))
+            })
+            .collect();
+        self.library_search = Some(SearchResults {
+            input: input.to_string(),
+            query,
+            matches,
+        });
+    }
+
+    /// Collects the sources and tags the library can be filtered by, keeping
+    /// the ones filtered by even if no book has them anymore, and searches
+    /// the library again
+    pub fn update_library_filters(&mut self) {
+        let filter = &self.config.library_filter;
+        let sources = self
+            .library
+            .iter()
+            .map(|b| b.source.clone())
+            .chain(filter.source.clone())
+            .sorted()
+            .dedup();
+        self.library_sources = std::iter::once("Any source".to_string())
+            .chain(sources)
+            .collect();
+        let tags = self
+            .library
+            .iter()
+            .flat_map(|b| b.tags.iter().cloned())
+            .chain(filter.tag.clone())
+            .sorted()
+            .dedup();
+        self.library_tags = std::iter::once("Any tag".to_string()).chain(tags).collect();
+        let categories = self
+            .library
+            .iter()
+            .filter_map(|b| b.category.clone())
+            .chain(filter.category.clone())
+            .sorted()
+            .dedup();
+        self.library_categories = std::iter::once("Any category".to_string())
+            .chain(categories)
+            .collect();
+
+        // The books changed, so must what the search found
+        if let Some(search) = self.library_search.take() {
+            self.search_library(&search.input);
+        }
+    }
+
+    /// Takes the books out of the library
+    fn remove_books(&mut self, mut books: Vec<Book>) -> Command<crate::app::Message> {
+        for book in &mut books {
+            book.in_library = false;
+        }
+        let done = fl!("notification-batch-removed", count = books.len());
+        self.store_books(books, done)
+    }
+
+    /// Files the books under the category, or takes them out of theirs
+    fn move_books(
+        &mut self,
+        mut books: Vec<Book>,
+        category: Option<String>,
+    ) -> Command<crate::app::Message> {
+        for book in &mut books {
+            book.category = category.clone();
+        }
+        let done = fl!(
+            "notification-batch-moved",
+            count = books.len(),
+            category = category.unwrap_or_else(|| fl!("category-none"))
+        );
+        self.store_books(books, done)
+    }
+
+    fn store_books(&mut self, books: Vec<Book>, done: String) -> Command<crate::app::Message> {
+        self.storage.perform(
+            move |data| {
+                for book in &books {
+                    data.set_book(book)?;
+                }
+                Ok(books)
+            },
+            move |books| crate::app::Message::BooksStored(books, done),
+        )
+    }
+
+    /// Marks every chapter of the books as read
+    fn mark_books_read(&mut self, books: Vec<Book>) -> Command<crate::app::Message> {
+        let notify = self.notify(LogMessage::Success(fl!(
+            "notification-batch-read",
+            count = books.len()
+        )));
+        let mark = self.storage.perform(
+            move |data| {
+                for book in &books {
+                    let urls = data
+                        .get_chapters(&book.url)?
+                        .into_iter()
+                        .filter_map(|c| c.url)
+                        .collect::<Vec<String>>();
+                    data.set_read(&urls, true)?;
+                }
+                Ok(())
+            },
+            // The unread counts changed
+            |()| crate::app::Message::LibraryLoad,
+        );
+        Command::batch([notify, mark])
+    }
+
+    /// Downloads the chapters of the books that are neither read nor stored
+    fn download_unread(&mut self, books: Vec<Book>) -> Command<crate::app::Message> {
+        self.storage.perform(
+            move |data| {
+                books
+                    .into_iter()
+                    .map(|book| {
+                        let read = data.get_read_chapters(&book.url)?;
+                        let downloaded = data.get_downloaded_chapters(&book.url)?;
+                        let missing = data
+                            .get_chapters(&book.url)?
+                            .into_iter()
+                            .filter(|c| {
+                                c.url
+                                    .as_ref()
+                                    .is_some_and(|u| !read.contains(u) && !downloaded.contains(u))
+                            })
+                            .collect::<Vec<Chapter>>();
+                        Ok((book.url, missing))
+                    })
+                    .collect::<crate::core::Result<Vec<_>>>()
+            },
+            crate::app::Message::DownloadChapters,
+        )
+    }
+
+    /// Cover cards as wide as `item_width`, as many to a row as fit
+    fn library_grid(
+        &self,
+        books: Vec<(&Book, Option<&Match>)>,
+        size: Size,
+        item_width: usize,
+        selection: Option<&HashSet<String>>,
+    ) -> Element<crate::app::Message> {
+        let spacing = theme::active().cosmic().spacing;
+        let width = (size.width.floor() as usize)
+            .checked_sub(spacing.space_s as usize)
+            .unwrap_or(0)
+            .max(item_width);
+
+        let card_size = Size::new(item_width as f32, item_width as f32 * 2.2);
+        let (cols, column_spacing) = {
+            let width_m1 = width.checked_sub(item_width).unwrap_or(0);
+            let cols_m1 = width_m1 / (item_width + spacing.space_xxs as usize);
+            let cols = cols_m1 + 1;
+            let spacing = width_m1
+                .checked_div(cols_m1)
+                .unwrap_or(0)
+                .checked_sub(item_width)
+                .unwrap_or(0);
+            (cols, spacing as u16)
+        };
+
+        // While selecting a click picks the book instead of opening it
+        let card = |book: &Book, found: Option<&Match>| {
+            let highlight = found.map(|m| m.name_indices.as_slice()).unwrap_or_default();
+            let (selected, on_press) = match selection {
+                Some(selected) => {
+                    let id = book.id();
+                    (selected.contains(&id), Message::Select(id).into())
+                }
+                None => (
+                    false,
+                    crate::app::Message::ToggleContextPage(ContextPage::BookContext(book.clone())),
+                ),
+            };
+            self.book_card(book, card_size, on_press, selected, highlight)
+        };
+
+        let mut grid = cosmic::widget::grid()
+            .width(Length::Fill)
+            .column_spacing(column_spacing)
+            .row_spacing(spacing.space_xs)
+            .insert_row();
+
+        let mut col = 0;
+        for (book, found) in books {
+            grid = grid.push(card(book, found));
+            col += 1;
+            if col >= cols {
+                col = 0;
+                grid = grid.insert_row();
+            }
+        }
+        grid.into()
+    }
+
+    /// One row per book with a small cover and its unread count
+    fn library_list(
+        &self,
+        books: Vec<(&Book, Option<&Match>)>,
+        selection: Option<&HashSet<String>>,
+    ) -> Element<crate::app::Message> {
+        let spacing = theme::active().cosmic().spacing;
+        let rows = books.into_iter().map(|(book, found)| {
+            let unread = self
+                .library_stats
+                .get(&book.id())
+                .map(|s| s.unread)
+                .unwrap_or_default();
+            let row = widget::row()
+                .push(
+                    widget::image(self.cover(&book.url, CoverSize::Thumbnail))
+                        .content_fit(cosmic::iced::ContentFit::Cover)
+                        .border_radius([spacing.space_xxxs as f32; 4])
+                        .width(Length::Fixed(40.0))
+                        .height(Length::Fixed(56.0)),
+                )
+                .push(
+                    widget::column()
+                        .push(utils::highlighted_text(
+                            &book.name,
+                            found.map(|m| m.name_indices.as_slice()).unwrap_or_default(),
+                        ))
+                        .push(widget::text::caption(book.source.clone()))
+                        .width(Length::Fill),
+                )
+                .push_maybe(
+                    (unread > 0)
+                        .then(|| widget::text::caption(fl!("library-unread", count = unread))),
+                )
+                .spacing(spacing.space_xs)
+                .align_items(Alignment::Center);
+            let (selected, on_press) = match selection {
+                Some(selected) => {
+                    let id = book.id();
+                    (selected.contains(&id), Message::Select(id).into())
+                }
+                None => (
+                    false,
+                    crate::app::Message::ToggleContextPage(ContextPage::BookContext(book.clone())),
+                ),
+            };
+            let button = widget::button::button(row)
+                .on_press(on_press)
+                .width(Length::Fill);
+            match selected {
+                true => button.style(theme::Button::Suggested).into(),
+                false => button.into(),
+            }
+        });
+        widget::column::with_children(rows.collect())
+            .spacing(spacing.space_xxxs)
+            .width(Length::Fill)
+            .into()
     }
 }
@@ -1,20 +1,33 @@
-// use crate::core::page::Entity;
-
+pub mod book;
 pub mod explore;
 pub mod history;
 pub mod library;
 pub mod page;
 pub mod reading;
 
-// #[derive(Clone, Debug)]
-// pub enum Message {
-//     Explore(explore::Message),
-//     External { id: String, message: Vec<u8> },
-//     Page(Entity),
-// }
+/// Messages for the page that sent them, see [`page::Page::update`]
+#[derive(Clone, Debug)]
+pub enum Message {
+    Explore(explore::Message),
+    Library(library::Message),
+    Book(book::Message),
+    History(history::Message),
+}
+
+impl Message {
+    /// The page the message is for
+    pub fn page(&self) -> crate::app::Page {
+        match self {
+            Message::Explore(_) => crate::app::Page::Explore,
+            Message::Library(_) => crate::app::Page::Library,
+            Message::Book(_) => crate::app::Page::Book,
+            Message::History(_) => crate::app::Page::History,
+        }
+    }
+}
 
-// impl From<Message> for crate::app::Message {
-//     fn from(message: Message) -> Self {
-//         crate::app::Message::PageMessage(message)
-//     }
-// }
+impl From<Message> for crate::app::Message {
+    fn from(message: Message) -> Self {
+        crate::app::Message::PageMessage(message)
+    }
+}
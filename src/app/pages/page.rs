@@ -1,9 +1,34 @@
-use crate::app::{App, Message};
-use cosmic::iced::{Alignment, Length, Padding, Size};
-use cosmic::{theme, Apply, Element};
+use crate::app::{self, App, Message};
+use crate::core::Book;
+use cosmic::app::Command;
+use cosmic::iced::Size;
+use cosmic::Element;
+use downcast_rs::{impl_downcast, Downcast};
 
-pub trait Page: Send + Sync {
-    fn view(&self, app: &App, size: Size) -> Element<Message> {
-        todo!();
+/// A screen of the app with its own state. The nav bar pages live for the
+/// whole session, the book page and reader are pushed on top of them
+pub trait Page: Downcast {
+    fn id(&self) -> app::Page;
+
+    /// Header and window title while the page is shown
+    fn title(&self, app: &App) -> String;
+
+    fn view<'a>(&'a self, app: &'a App, size: Size) -> Element<'a, Message>;
+
+    /// Handles the messages sent from the page's view
+    fn update(&mut self, _app: &mut App, _message: super::Message) -> Command<Message> {
+        Command::none()
+    }
+
+    /// Reloads what the page shows
+    fn refresh(&mut self, _app: &mut App) -> Command<Message> {
+        Command::none()
+    }
+
+    /// The book the page is about, if any
+    fn book<'a>(&'a self, _app: &'a App) -> Option<&'a Book> {
+        None
     }
 }
+
+impl_downcast!(Page);
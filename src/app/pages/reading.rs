@@ -1,109 +1,1500 @@
-use std::collections::HashMap;
-use std::path::Display;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
 
 use crate::app::*;
-use crate::core::Book;
+use crate::config::{self, Config, ReadingMode};
+use crate::core::{source, tts, Annotation, AnnotationKind, Book, Chapter, Progress};
 use crate::fl;
-use cosmic::app::{Command, Core};
+use cosmic::app::message;
+use cosmic::iced::advanced::graphics::text::Paragraph;
+use cosmic::iced::advanced::text::{self as advanced_text, Paragraph as _};
 use cosmic::iced::alignment::{Horizontal, Vertical};
-use cosmic::iced::window::Icon;
-use cosmic::iced::{Alignment, Length, Padding, Size};
+use cosmic::iced::mouse::{self, ScrollDelta};
+use cosmic::iced::widget::scrollable::{AbsoluteOffset, Viewport};
+use cosmic::iced::widget::text::LineHeight;
+use cosmic::iced::widget::Space;
+use cosmic::iced::{
+    event, Alignment, Background, Border, Color, Event, Font, Length, Pixels, Size,
+};
 use cosmic::widget::*;
-use cosmic::{cosmic_theme, theme, ApplicationExt, Apply, Element};
+use cosmic::{theme, Apply, Element};
+use once_cell::sync::Lazy;
+
+static SCROLLABLE: Lazy<cosmic::widget::Id> = Lazy::new(cosmic::widget::Id::unique);
+
+/// The chapter a [`ReaderPage`] shows and where it is in it
+#[derive(Debug, Clone, Default)]
+pub struct Reader {
+    pub book: Book,
+    pub chapters: Vec<Chapter>,
+    /// Position of the open chapter in `chapters`
+    pub index: usize,
+    /// Paragraphs of the open chapter, `None` while it downloads
+    pub content: Option<Vec<String>>,
+    /// Page of the open chapter in paged mode, past the end means the last page
+    pub page: usize,
+    /// Whether the typography popover is open
+    pub show_style: bool,
+    /// Scroll position in scroll mode
+    pub viewport: Option<Viewport>,
+    /// Paragraph being read aloud, set before the chapter text arrives when
+    /// reading carries on into the next chapter
+    pub speaking: Option<usize>,
+    /// Bookmarks and highlights in the open chapter, anchored to its current text
+    pub annotations: Vec<Annotation>,
+    /// Paragraph picked with a right click, for the annotation bar
+    pub selected: Option<usize>,
+    /// Paragraph to bring into view once the chapter is laid out
+    pub anchor: Option<usize>,
+    /// Annotation to jump to once the chapter text arrives
+    pub jump: Option<i64>,
+    /// Pages of the open chapter, `None` until it is shown in paged mode
+    layout: RefCell<Option<Layout>>,
+}
+
+/// Where to open the reader, read from storage before it shows
+#[derive(Debug, Clone)]
+pub struct Opening {
+    pub book: Book,
+    pub chapters: Vec<Chapter>,
+    pub index: usize,
+    pub page: usize,
+    /// Annotation to jump to, see [`Reader::jump`]
+    pub jump: Option<i64>,
+}
+
+/// Paragraphs on one page with their position in the chapter, a paragraph
+/// split across pages appears on both
+type PageText = Vec<(usize, String)>;
+
+/// Pages of a chapter with what they were split for
+#[derive(Debug, Clone)]
+struct Layout {
+    metrics: PageMetrics,
+    /// See [`Reader::passages`]
+    passages: Vec<(usize, String)>,
+    pages: Rc<Vec<PageText>>,
+}
+
+/// Everything that decides where a page breaks
+#[derive(Debug, Clone, PartialEq)]
+struct PageMetrics {
+    width: f32,
+    height: f32,
+    font: Font,
+    font_size: u16,
+    line_height: f32,
+    paragraph_spacing: f32,
+}
+
+impl PageMetrics {
+    fn new(config: &Config, size: Size) -> PageMetrics {
+        let margin = f32::from(config.margin) * 2.0;
+        PageMetrics {
+            width: (size.width - margin)
+                .min(f32::from(config.column_width))
+                .max(1.0),
+            height: (size.height - margin).max(1.0),
+            font: config::reader_font(&config.reader_font),
+            font_size: config.font_size,
+            line_height: config.line_height,
+            paragraph_spacing: f32::from(config.paragraph_spacing),
+        }
+    }
+
+    /// Laid out height of a paragraph, measured with the same shaping the text widget uses
+    fn height_of(&self, text: &str) -> f32 {
+        Paragraph::with_text(advanced_text::Text {
+            content: text,
+            bounds: Size::new(self.width, f32::INFINITY),
+            size: Pixels(f32::from(self.font_size)),
+            line_height: LineHeight::Relative(self.line_height),
+            font: self.font,
+            horizontal_alignment: Horizontal::Left,
+            vertical_alignment: Vertical::Top,
+            shaping: advanced_text::Shaping::Advanced,
+        })
+        .min_bounds()
+        .height
+    }
+
+    /// Splits the reader's chapter into pages of this size, see [`paginate`].
+    /// Highlighted passages are laid out apart from the text around them
+    fn paginate(&self, reader: &Reader, paragraphs: &[String]) -> Vec<PageText> {
+        paginate(
+            paragraphs,
+            self.height,
+            self.paragraph_spacing,
+            |i, offset, text| {
+                reader
+                    .runs(i, offset, text)
+                    .into_iter()
+                    .map(|(run, _)| self.height_of(run))
+                    .sum()
+            },
+        )
+    }
+}
+
+/// Fills pages `page_height` tall with paragraphs, splitting a paragraph
+/// between words when it runs over the bottom of a page. `height_of` lays
+/// out a text of a paragraph, starting at the given word, at the page width
+fn paginate(
+    paragraphs: &[String],
+    page_height: f32,
+    paragraph_spacing: f32,
+    height_of: impl Fn(usize, usize, &str) -> f32,
+) -> Vec<PageText> {
+    let mut pages = vec![];
+    let mut page: PageText = vec![];
+    let mut used = 0.0;
+    let mut queue = paragraphs
+        .iter()
+        .cloned()
+        .enumerate()
+        .map(|(i, paragraph)| (i, 0, paragraph))
+        .collect::<VecDeque<(usize, usize, String)>>();
+
+    while let Some((i, offset, paragraph)) = queue.pop_front() {
+        let gap = match page.is_empty() {
+            true => 0.0,
+            false => paragraph_spacing,
+        };
+        let height = height_of(i, offset, &paragraph);
+        if used + gap + height <= page_height {
+            page.push((i, paragraph));
+            used += gap + height;
+            continue;
+        }
+
+        // Binary search for the most words that still fit
+        let room = page_height - used - gap;
+        let words = paragraph.split_whitespace().collect::<Vec<&str>>();
+        let (mut low, mut high) = (0, words.len());
+        while low < high {
+            let mid = (low + high).div_ceil(2);
+            match height_of(i, offset, &words[..mid].join(" ")) <= room {
+                true => low = mid,
+                false => high = mid - 1,
+            }
+        }
+
+        match low {
+            // A single word taller than the page gets a page of its own
+            0 if page.is_empty() => page.push((i, paragraph)),
+            0 => queue.push_front((i, offset, paragraph)),
+            n if n >= words.len() => page.push((i, paragraph)),
+            n => {
+                page.push((i, words[..n].join(" ")));
+                queue.push_front((i, offset + n, words[n..].join(" ")));
+            }
+        }
+        pages.push(std::mem::take(&mut page));
+        used = 0.0;
+    }
+
+    if !page.is_empty() || pages.is_empty() {
+        pages.push(page);
+    }
+    pages
+}
+
+impl Reader {
+    pub fn chapter(&self) -> Option<&Chapter> {
+        self.chapters.get(self.index)
+    }
+
+    pub fn previous(&self) -> Option<usize> {
+        self.index.checked_sub(1)
+    }
+
+    pub fn next(&self) -> Option<usize> {
+        Some(self.index + 1).filter(|i| *i < self.chapters.len())
+    }
+
+    /// Sets the text of the open chapter, dropping its old pages
+    pub fn set_content(&mut self, paragraphs: Vec<String>) {
+        self.content = Some(paragraphs);
+        self.layout.replace(None);
+    }
+
+    /// Number of pages the open chapter was last split into, `None` before
+    /// it has been shown in paged mode
+    pub fn page_count(&self) -> Option<usize> {
+        self.layout
+            .borrow()
+            .as_ref()
+            .map(|layout| layout.pages.len())
+    }
+
+    /// The page the paragraph starts on, `None` before the chapter has been
+    /// shown in paged mode
+    pub fn page_of(&self, paragraph: usize) -> Option<usize> {
+        let layout = self.layout.borrow();
+        layout
+            .as_ref()?
+            .pages
+            .iter()
+            .position(|page| page.iter().any(|(i, _)| *i == paragraph))
+    }
+
+    /// Turns to the page of the anchored paragraph, once there are pages
+    pub fn settle_anchor(&mut self) {
+        if let Some(page) = self.anchor.and_then(|p| self.page_of(p)) {
+            self.page = page;
+            self.anchor = None;
+        }
+    }
+
+    /// The first paragraph on screen
+    pub fn top_paragraph(&self, mode: ReadingMode) -> usize {
+        match mode {
+            ReadingMode::Paged => self
+                .layout
+                .borrow()
+                .as_ref()
+                .and_then(|layout| layout.pages.get(self.page)?.first().map(|(i, _)| *i))
+                .unwrap_or(0),
+            ReadingMode::Scroll => {
+                let count = self.content.as_ref().map(Vec::len).unwrap_or(0);
+                let y = self.viewport.map(|v| v.relative_offset().y).unwrap_or(0.0);
+                ((y * count as f32) as usize).min(count.saturating_sub(1))
+            }
+        }
+    }
+
+    fn pages(&self, metrics: &PageMetrics) -> Rc<Vec<PageText>> {
+        let passages = self.passages();
+        if let Some(layout) = self.layout.borrow().as_ref() {
+            if layout.metrics == *metrics && layout.passages == passages {
+                return layout.pages.clone();
+            }
+        }
+
+        let paragraphs = self.content.as_deref().unwrap_or_default();
+        let pages = Rc::new(metrics.paginate(self, paragraphs));
+        self.layout.replace(Some(Layout {
+            metrics: metrics.clone(),
+            passages,
+            pages: pages.clone(),
+        }));
+        pages
+    }
+
+    /// Highlighted passages by paragraph, see [`Annotation::passage`]
+    fn passages(&self) -> Vec<(usize, String)> {
+        self.annotations
+            .iter()
+            .filter(|a| a.kind == AnnotationKind::Highlight && !a.passage.is_empty())
+            .map(|a| (a.paragraph, a.passage.clone()))
+            .collect()
+    }
+
+    /// Splits the text of paragraph `i` into runs that are highlighted or
+    /// not. `offset` is the paragraph's word the text starts at, when a page
+    /// break split it
+    fn runs<'a>(&self, i: usize, offset: usize, text: &'a str) -> Vec<(&'a str, bool)> {
+        let paragraph = self
+            .content
+            .as_ref()
+            .and_then(|c| c.get(i))
+            .map_or(text, String::as_str);
+        let starts = text
+            .split_whitespace()
+            .map(|word| word.as_ptr() as usize - text.as_ptr() as usize)
+            .collect::<Vec<usize>>();
+
+        let mut marked = vec![false; starts.len()];
+        let highlights = self
+            .annotations
+            .iter()
+            .filter(|a| a.paragraph == i && a.kind == AnnotationKind::Highlight);
+        for annotation in highlights {
+            let words = annotation.words(paragraph);
+            for word in words.start.max(offset)..words.end.min(offset + starts.len()) {
+                marked[word - offset] = true;
+            }
+        }
+
+        let mut runs = vec![];
+        let mut start = 0;
+        for word in 1..=starts.len() {
+            if word == starts.len() || marked[word] != marked[start] {
+                let end = starts.get(word).copied().unwrap_or(text.len());
+                runs.push((text[starts[start]..end].trim_end(), marked[start]));
+                start = word;
+            }
+        }
+        if runs.is_empty() {
+            runs.push((text, false));
+        }
+        runs
+    }
+}
+
+/// Shows a chapter, with its own [`Reader`] so a reader further down the
+/// stack keeps its place
+#[derive(Default)]
+pub struct ReaderPage {
+    pub reader: Reader,
+}
+
+impl pages::page::Page for ReaderPage {
+    fn id(&self) -> Page {
+        Page::Reader
+    }
+
+    fn title(&self, _app: &App) -> String {
+        self.reader
+            .chapter()
+            .and_then(|c| c.name.clone())
+            .unwrap_or_default()
+    }
+
+    fn view<'a>(&'a self, app: &'a App, size: Size) -> Element<'a, Message> {
+        app.view_reading(&self.reader, size)
+    }
+
+    fn refresh(&mut self, _app: &mut App) -> Command<Message> {
+        let Some(chapter) = self.reader.chapter().cloned() else {
+            return Command::none();
+        };
+        Command::perform(
+            async move { message::app(Message::RefreshChapter(chapter)) },
+            |x| x,
+        )
+    }
+
+    fn book<'a>(&'a self, _app: &'a App) -> Option<&'a Book> {
+        Some(&self.reader.book)
+    }
+}
 
 impl App {
-    pub fn view_reading(&self, size: Size) -> Element<Message> {
-        todo!("reading tab view")
-        // let spacing = theme::active().cosmic().spacing;
-        // let item_width = 180;
-        // let item_height = 400;
-        // let (width, _height) = (
-        //     (size.width.floor() as usize)
-        //         .checked_sub(spacing.space_s as usize)
-        //         .unwrap_or(0)
-        //         .max(item_width),
-        //     (size.height.floor() as usize).max(item_height),
-        // );
-
-        // let (cols, column_spacing) = {
-        //     let width_m1 = width.checked_sub(item_width).unwrap_or(0);
-        //     let cols_m1 = width_m1 / (item_width + spacing.space_xxs as usize);
-        //     let cols = cols_m1 + 1;
-        //     let spacing = width_m1
-        //         .checked_div(cols_m1)
-        //         .unwrap_or(0)
-        //         .checked_sub(item_width)
-        //         .unwrap_or(0);
-        //     (cols, spacing as u16)
-        // };
-
-        // let search_bar = cosmic::widget::row()
-        //     .align_items(Alignment::Center)
-        //     .spacing(spacing.space_xs)
-        //     .push(
-        //         cosmic::widget::search_input("Search for books...", &self.explore_input)
-        //             .width(Length::Fill)
-        //             .on_input(Message::SearchInputChanged)
-        //             .on_submit_maybe(Some(Message::ExploreSearch(self.explore_input.clone()))),
-        //     )
-        //     .apply(container);
-
-        // let mut grid = cosmic::widget::grid()
-        //     .width(Length::Fill)
-        //     .column_spacing(column_spacing)
-        //     .row_spacing(spacing.space_xs)
-        //     // .padding([0, spacing.space_m].into())
-        //     // .height(Length::Fill)
-        //     .insert_row();
-
-        // let card_size = Size::new(item_width as f32, item_height as f32);
-        // // let cols = 4;
-        // let mut col = 0;
-        // for (_i, url) in self.explore_results.clone().into_iter().enumerate() {
-        //     let book = match self.books.get(&url) {
-        //         Some(b) => b.clone(),
-        //         None => continue,
-        //     };
-        //     grid = grid.push(self.create_book_card(&book, card_size));
-        //     col += 1;
-        //     if col >= cols {
-        //         col = 0;
-        //         grid = grid.insert_row();
-        //     }
-        // }
-
-        // let grid = grid
-        //     .apply(container)
-        //     .center_x()
-        //     .height(Length::Fill)
-        //     .width(Length::Fill)
-        //     .padding(Padding {
-        //         top: 0.0,
-        //         bottom: 0.0,
-        //         left: spacing.space_xs as f32,
-        //         right: spacing.space_m as f32,
-        //     })
-        //     .apply(scrollable)
-        //     .height(Length::Fill)
-        //     .width(Length::Fill)
-        //     .apply(container);
-
-        // column()
-        //     .push(search_bar)
-        //     // .push(flex_row)
-        //     .push(grid)
-        //     .spacing(spacing.space_xs)
-        //     .height(Length::Fill)
-        //     .width(Length::Fill)
-        //     // .align_items(Alignment::Center)
-        //     // .apply(container)
-        //     // .padding(Padding {
-        //     //     top: spacing.space_xl as f32,
-        //     //     bottom: 0.0,
-        //     //     left: spacing.space_xs as f32,
-        //     //     right: spacing.space_m as f32,
-        //     // })
-        //     // .apply(scrollable)
-        //     .into()
+    /// The reader nearest the page shown: the topmost in the stack, else the
+    /// last one gone back from, which reading aloud carries on in
+    pub fn reader(&self) -> Option<&Reader> {
+        self.stack
+            .iter()
+            .rev()
+            .chain(self.forward.iter().rev())
+            .find_map(|p| p.downcast_ref::<ReaderPage>())
+            .map(|p| &p.reader)
+    }
+
+    pub fn reader_mut(&mut self) -> Option<&mut Reader> {
+        self.stack
+            .iter_mut()
+            .rev()
+            .chain(self.forward.iter_mut().rev())
+            .find_map(|p| p.downcast_mut::<ReaderPage>())
+            .map(|p| &mut p.reader)
+    }
+
+    /// Every open reader, for changes to the book they show
+    pub fn readers_mut(&mut self) -> impl Iterator<Item = &mut Reader> {
+        self.stack
+            .iter_mut()
+            .chain(self.forward.iter_mut())
+            .filter_map(|p| p.downcast_mut::<ReaderPage>())
+            .map(|p| &mut p.reader)
+    }
+
+    /// Whether the reader is the page on screen
+    pub fn is_reading(&self) -> bool {
+        self.page().is_some_and(|p| p.id() == Page::Reader)
+    }
+
+    /// Moves a page forward or back, crossing into the neighbouring chapter at either end
+    pub fn turn_page(&mut self, turn: Direction) -> Command<Message> {
+        let Some(reader) = self.reader_mut() else {
+            return Command::none();
+        };
+
+        // Scroll mode moves by most of a screen so a line of context stays
+        if self.config.reading_mode == ReadingMode::Scroll {
+            let Some(viewport) = reader.viewport else {
+                return Command::none();
+            };
+            let step = viewport.bounds().height * 0.9;
+            let y = match turn {
+                Direction::Next => viewport.absolute_offset().y + step,
+                Direction::Previous => viewport.absolute_offset().y - step,
+            };
+            return cosmic::iced::widget::scrollable::scroll_to(
+                SCROLLABLE.clone(),
+                AbsoluteOffset {
+                    x: 0.0,
+                    y: y.max(0.0),
+                },
+            );
+        }
+
+        reader.settle_anchor();
+        let Some(count) = reader.page_count() else {
+            return Command::none();
+        };
+        let page = reader.page.min(count.saturating_sub(1));
+
+        match turn {
+            Direction::Next if page + 1 < count => reader.page = page + 1,
+            Direction::Previous if page > 0 => reader.page = page - 1,
+            Direction::Next => {
+                return match reader.next() {
+                    Some(index) => self.open_chapter(index, 0),
+                    None => Command::none(),
+                }
+            }
+            Direction::Previous => {
+                return match reader.previous() {
+                    Some(index) => self.open_chapter(index, usize::MAX),
+                    None => Command::none(),
+                }
+            }
+        }
+
+        self.save_progress()
+    }
+
+    /// Shows the text of a chapter if the reader is still on it
+    pub fn show_chapter(&mut self, chapter: &Chapter, content: &str) -> Command<Message> {
+        // Drop downloads for chapters the reader already moved away from
+        let Some(reader) = self
+            .reader_mut()
+            .filter(|r| r.chapter().map(|c| &c.url) == Some(&chapter.url))
+        else {
+            return Command::none();
+        };
+
+        reader.set_content(
+            content
+                .lines()
+                .map(str::trim)
+                .filter(|p| !p.is_empty())
+                .map(String::from)
+                .collect(),
+        );
+        let speaking = reader.speaking;
+        let anchored = self.anchor_annotations();
+        match speaking {
+            Some(paragraph) => Command::batch([anchored, self.read_aloud(paragraph)]),
+            None => anchored,
+        }
+    }
+
+    /// Downloads the chapters after the open one that aren't stored yet,
+    /// following the sites' next links once the stored list runs out
+    pub fn prefetch(&self) -> Command<Message> {
+        let Some(reader) = self.reader() else {
+            return Command::none();
+        };
+        let count = usize::from(self.config.prefetch_chapters);
+        if count == 0 {
+            return Command::none();
+        }
+
+        let ahead = &reader.chapters[(reader.index + 1).min(reader.chapters.len())..];
+        let upcoming = ahead
+            .iter()
+            .take(count)
+            .filter(|c| c.url.is_some())
+            .cloned()
+            .collect::<Vec<Chapter>>();
+        let extra = count.saturating_sub(ahead.len());
+        let follow = match extra {
+            0 => None,
+            _ => reader.chapters.last().and_then(|c| c.url.clone()),
+        };
+        if upcoming.is_empty() && follow.is_none() {
+            return Command::none();
+        }
+
+        let book_url = reader.book.url.clone();
+        let storage = self.storage.clone();
+        Command::perform(
+            async move {
+                let known = storage
+                    .run(move |data| {
+                        Ok(upcoming
+                            .into_iter()
+                            .filter(|c| {
+                                c.url.as_deref().is_some_and(|url| {
+                                    !data.has_chapter_content(url).unwrap_or(false)
+                                })
+                            })
+                            .collect::<Vec<Chapter>>())
+                    })
+                    .await
+                    .unwrap_or_default();
+                if known.is_empty() && follow.is_none() {
+                    return message::app(Message::Ignore);
+                }
+
+                let chapters = App::download_ahead(&book_url, known, follow, extra).await;
+                message::app(Message::Prefetched(book_url, chapters))
+            },
+            |x| x,
+        )
+    }
+
+    /// Downloads `known` then up to `extra` chapters after `follow`, one at a
+    /// time through the shared request limit, keeping what arrived before any error
+    pub async fn download_ahead(
+        book_url: &str,
+        known: Vec<Chapter>,
+        follow: Option<String>,
+        extra: usize,
+    ) -> Vec<(Chapter, String)> {
+        let mut done = vec![];
+        let res: crate::core::Result<()> = async {
+            let source = source::for_url(book_url)?;
+            for chapter in known {
+                let content = source.download_chapter(&chapter).await?;
+                done.push((chapter, content));
+            }
+
+            let mut next = match follow {
+                Some(url) => source.scrape_chapter(url).await?.1,
+                None => None,
+            };
+            for _ in 0..extra {
+                let Some(url) = next.take() else {
+                    break;
+                };
+                let (chapter, after) = source.scrape_chapter(url).await?;
+                let content = source.download_chapter(&chapter).await?;
+                done.push((chapter, content));
+                next = after;
+            }
+            Ok(())
+        }
+        .await;
+
+        if let Err(e) = res {
+            tracing::warn!("stopped prefetching {book_url}: {e}");
+        }
+        done
+    }
+
+    /// Stores prefetched chapters, adding new ones to the book's chapter
+    /// list. Chapters past the end are dropped if the book was closed meanwhile
+    pub fn prefetched(
+        &mut self,
+        book_url: String,
+        chapters: Vec<(Chapter, String)>,
+    ) -> Command<Message> {
+        // The reader's list is the stored one
+        let book_id = source::book_id(&book_url);
+        let reader = self.reader_mut().filter(|r| r.book.id() == book_id);
+        let mut list = reader.as_ref().map(|r| r.chapters.clone());
+        let known = list.as_ref().map(Vec::len);
+
+        let mut contents = vec![];
+        for (mut chapter, content) in chapters {
+            let Some(url) = chapter.url.clone() else {
+                continue;
+            };
+            if let Some(list) = list
+                .as_mut()
+                .filter(|list| !list.iter().any(|c| c.url.as_ref() == Some(&url)))
+            {
+                chapter.number = Some(list.len() as u32 + 1);
+                list.push(chapter);
+            }
+            contents.push((url, content));
+        }
+
+        let list = list.filter(|list| Some(list.len()) != known);
+        if let (Some(reader), Some(list)) = (reader, &list) {
+            reader.chapters = list.clone();
+        }
+        for details in self.book_details_mut().filter(|d| d.book.id() == book_id) {
+            details
+                .downloaded
+                .extend(contents.iter().map(|(url, _)| url.clone()));
+        }
+        self.storage.write(move |data| {
+            for (url, content) in &contents {
+                data.set_chapter_content(url, content)?;
+            }
+            match list {
+                Some(list) => data.set_chapters(&book_url, &list),
+                None => Ok(()),
+            }
+        })
+    }
+
+    /// Stores the open chapter and page of the reader
+    pub fn save_progress(&self) -> Command<Message> {
+        let Some(reader) = self.reader() else {
+            return Command::none();
+        };
+        let Some(chapter_url) = reader.chapter().and_then(|c| c.url.clone()) else {
+            return Command::none();
+        };
+
+        let progress = Progress {
+            chapter_url,
+            page: reader.page,
+        };
+        let book_url = reader.book.url.clone();
+        self.storage
+            .write(move |data| data.set_progress(&book_url, &progress))
+    }
+
+    /// Starts reading aloud from the top of the page, or stops
+    pub fn toggle_read_aloud(&mut self) -> Command<Message> {
+        let mode = self.config.reading_mode;
+        let Some(reader) = self.reader_mut() else {
+            return Command::none();
+        };
+        if reader.speaking.is_some() {
+            self.stop_read_aloud();
+            return Command::none();
+        }
+
+        reader.settle_anchor();
+        let start = reader.top_paragraph(mode);
+        self.read_aloud(start)
+    }
+
+    pub fn stop_read_aloud(&mut self) {
+        if let Some(reader) = self.reader_mut() {
+            reader.speaking = None;
+        }
+        // Anything still speaking belongs to an old session and is ignored
+        self.tts_session += 1;
+        self.tts_cancel.cancel();
+        self.tts_cancel = tts::Cancel::default();
+        self.tts.for_name(&self.config.tts_backend).stop();
+    }
+
+    /// Speaks a paragraph of the open chapter and turns to it, waits for the
+    /// text if the chapter is still downloading
+    pub fn read_aloud(&mut self, paragraph: usize) -> Command<Message> {
+        let mode = self.config.reading_mode;
+        let Some(reader) = self.reader_mut() else {
+            return Command::none();
+        };
+        reader.speaking = Some(paragraph);
+        let Some(paragraphs) = &reader.content else {
+            return Command::none();
+        };
+        let Some(text) = paragraphs.get(paragraph).cloned() else {
+            return Command::none();
+        };
+
+        let follow = match mode {
+            ReadingMode::Paged => {
+                if let Some(page) = reader.page_of(paragraph).filter(|p| *p != reader.page) {
+                    reader.page = page;
+                }
+                self.save_progress()
+            }
+            ReadingMode::Scroll => cosmic::iced::widget::scrollable::snap_to(
+                SCROLLABLE.clone(),
+                cosmic::iced::widget::scrollable::RelativeOffset {
+                    x: 0.0,
+                    y: paragraph as f32 / paragraphs.len() as f32,
+                },
+            ),
+        };
+
+        let session = self.tts_session;
+        let cancel = self.tts_cancel.clone();
+        let backend = self.tts.for_name(&self.config.tts_backend);
+        let voice = self.config.voice();
+        Command::batch([
+            follow,
+            Command::perform(
+                async move {
+                    let res = backend.speak(&text, &voice, &cancel).await;
+                    message::app(Message::Spoken(session, paragraph, res))
+                },
+                |x| x,
+            ),
+        ])
+    }
+
+    /// Moves on once a paragraph has been said, into the next chapter at the end
+    pub fn spoken(
+        &mut self,
+        session: u64,
+        paragraph: usize,
+        res: crate::core::Result<()>,
+    ) -> Command<Message> {
+        if session != self.tts_session {
+            return Command::none();
+        }
+        if let Err(e) = res {
+            self.stop_read_aloud();
+            return self.log_error(e);
+        }
+        // A reader opened over the one that was speaking starts out quiet
+        let Some(reader) = self.reader().filter(|r| r.speaking == Some(paragraph)) else {
+            return Command::none();
+        };
+
+        let count = reader.content.as_ref().map(Vec::len).unwrap_or(0);
+        if paragraph + 1 < count {
+            return self.read_aloud(paragraph + 1);
+        }
+        match reader.next() {
+            // Opening a chapter carries reading aloud over to it
+            Some(index) => self.open_chapter(index, 0),
+            None => {
+                self.stop_read_aloud();
+                Command::none()
+            }
+        }
+    }
+
+    /// Loads the annotations of the chapter that just arrived
+    pub fn anchor_annotations(&self) -> Command<Message> {
+        let Some(reader) = self.reader() else {
+            return Command::none();
+        };
+        let (Some(_), Some(chapter_url)) = (
+            &reader.content,
+            reader.chapter().and_then(|c| c.url.clone()),
+        ) else {
+            return Command::none();
+        };
+
+        let book_url = reader.book.url.clone();
+        self.storage.perform(
+            move |data| {
+                let mut annotations = data.get_annotations(&book_url)?;
+                annotations.retain(|a| a.chapter_url == chapter_url);
+                Ok((chapter_url, annotations))
+            },
+            |(chapter_url, annotations)| Message::AnnotationsLoaded(chapter_url, annotations),
+        )
+    }
+
+    /// Moves annotations whose paragraph shifted, then jumps to the one asked for
+    pub fn annotations_loaded(
+        &mut self,
+        chapter_url: String,
+        mut annotations: Vec<Annotation>,
+    ) -> Command<Message> {
+        let mode = self.config.reading_mode;
+        let Some(reader) = self
+            .reader_mut()
+            .filter(|r| r.chapter().and_then(|c| c.url.as_ref()) == Some(&chapter_url))
+        else {
+            return Command::none();
+        };
+        let Some(paragraphs) = &reader.content else {
+            return Command::none();
+        };
+
+        let mut moved = vec![];
+        for annotation in annotations.iter_mut() {
+            // Annotations whose text is gone keep their old place
+            match annotation.locate(paragraphs) {
+                Some(i) if i != annotation.paragraph => {
+                    tracing::debug!("annotation {} moved to paragraph {i}", annotation.id);
+                    annotation.paragraph = i;
+                    moved.push(annotation.clone());
+                }
+                _ => (),
+            }
+        }
+        let count = paragraphs.len();
+        reader.annotations = annotations;
+
+        let jump = reader.jump.take().and_then(|id| {
+            reader
+                .annotations
+                .iter()
+                .find(|a| a.id == id)
+                .map(|a| a.paragraph.min(count.saturating_sub(1)))
+        });
+        let jump = match jump {
+            None => Command::none(),
+            Some(paragraph) => {
+                reader.selected = Some(paragraph);
+                match mode {
+                    ReadingMode::Paged => {
+                        reader.anchor = Some(paragraph);
+                        Command::none()
+                    }
+                    ReadingMode::Scroll => cosmic::iced::widget::scrollable::snap_to(
+                        SCROLLABLE.clone(),
+                        cosmic::iced::widget::scrollable::RelativeOffset {
+                            x: 0.0,
+                            y: paragraph as f32 / count as f32,
+                        },
+                    ),
+                }
+            }
+        };
+        let store = match moved.is_empty() {
+            true => Command::none(),
+            false => self.storage.write(move |data| {
+                moved
+                    .iter()
+                    .try_for_each(|annotation| data.update_annotation(annotation))
+            }),
+        };
+        Command::batch([store, jump])
+    }
+
+    /// Bookmarks or highlights the selected paragraph with the typed note, a
+    /// bookmark without a selection marks the top of the screen. Highlights
+    /// cover the passage left in the annotation bar
+    pub fn annotate(&mut self, kind: AnnotationKind) -> Command<Message> {
+        let mode = self.config.reading_mode;
+        let note = self.annotation_note.trim().to_string();
+        let passage = self.annotation_passage.clone();
+        let Some(reader) = self.reader_mut() else {
+            return Command::none();
+        };
+        reader.settle_anchor();
+        let paragraph = match (reader.selected, kind) {
+            (Some(paragraph), _) => paragraph,
+            (None, AnnotationKind::Bookmark) => reader.top_paragraph(mode),
+            (None, AnnotationKind::Highlight) => return Command::none(),
+        };
+        let (Some(text), Some(chapter)) = (
+            reader.content.as_ref().and_then(|p| p.get(paragraph)),
+            reader.chapter(),
+        ) else {
+            return Command::none();
+        };
+        let Some(chapter_url) = chapter.url.clone() else {
+            return Command::none();
+        };
+
+        let annotation = Annotation::new(
+            reader.book.url.clone(),
+            chapter_url,
+            chapter.name.clone(),
+            kind,
+            paragraph,
+            text.clone(),
+            note,
+        );
+        let annotation = match kind {
+            AnnotationKind::Highlight => annotation.with_passage(&passage),
+            AnnotationKind::Bookmark => annotation,
+        };
+        self.storage.perform(
+            move |data| data.add_annotation(&annotation),
+            Message::Annotated,
+        )
+    }
+
+    /// Shows a stored annotation in the reader and the book drawer
+    pub fn annotated(&mut self, annotation: Annotation) {
+        if let Some(reader) = self
+            .reader_mut()
+            .filter(|r| r.chapter().and_then(|c| c.url.as_ref()) == Some(&annotation.chapter_url))
+        {
+            reader.annotations.push(annotation.clone());
+            reader.selected = None;
+        }
+        self.annotation_passage.clear();
+        let book_id = source::book_id(&annotation.book_url);
+        for details in self.book_details_mut().filter(|d| d.book.id() == book_id) {
+            details.annotations.push(annotation.clone());
+        }
+        self.annotation_note.clear();
+    }
+
+    pub fn view_reading<'a>(&'a self, reader: &'a Reader, _size: Size) -> Element<'a, Message> {
+        let spacing = theme::active().cosmic().spacing;
+        let config = &self.config;
+
+        let title = reader
+            .chapter()
+            .and_then(|c| c.name.clone())
+            .unwrap_or_default();
+
+        let style_button = widget::button::icon(icon::from_name("font-select-symbolic"))
+            .on_press(Message::ToggleReaderStyle);
+        let style_button = match reader.show_style {
+            true => widget::popover(style_button)
+                .popup(
+                    self.reader_options()
+                        .width(Length::Fixed(360.0))
+                        .apply(widget::container)
+                        .style(theme::Container::Dialog)
+                        .padding(spacing.space_s),
+                )
+                .on_close(Message::ToggleReaderStyle),
+            false => widget::popover(style_button),
+        };
+
+        let header = widget::row()
+            .push(
+                widget::button::icon(icon::from_name("go-previous-symbolic"))
+                    .on_press_maybe(reader.previous().map(Message::OpenChapter)),
+            )
+            .push(
+                widget::text::heading(title)
+                    .width(Length::Fill)
+                    .horizontal_alignment(Horizontal::Center),
+            )
+            .push(
+                widget::button::icon(icon::from_name("bookmark-new-symbolic"))
+                    .on_press(Message::Annotate(AnnotationKind::Bookmark)),
+            )
+            .push(
+                widget::button::icon(icon::from_name(match reader.speaking {
+                    Some(_) => "media-playback-stop-symbolic",
+                    None => "audio-speakers-symbolic",
+                }))
+                .on_press(Message::ReadAloud),
+            )
+            .push(style_button)
+            .push(
+                widget::button::icon(icon::from_name("go-next-symbolic"))
+                    .on_press_maybe(reader.next().map(Message::OpenChapter)),
+            )
+            .align_items(Alignment::Center)
+            .spacing(spacing.space_xxs)
+            .padding([spacing.space_xxs, spacing.space_s]);
+
+        let body: Element<Message> = match (&reader.content, config.reading_mode) {
+            (None, _) => widget::text(fl!("loading"))
+                .apply(widget::container)
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .align_x(Horizontal::Center)
+                .align_y(Vertical::Center)
+                .into(),
+            (Some(paragraphs), ReadingMode::Scroll) => self
+                .view_paragraphs(
+                    reader,
+                    paragraphs
+                        .iter()
+                        .cloned()
+                        .enumerate()
+                        .map(|(i, p)| (i, 0, p))
+                        .collect(),
+                )
+                .max_width(config.column_width)
+                .apply(widget::container)
+                .width(Length::Fill)
+                .center_x()
+                .padding(config.margin)
+                .apply(widget::scrollable)
+                .id(SCROLLABLE.clone())
+                .on_scroll(Message::ReaderScrolled)
+                .height(Length::Fill)
+                .into(),
+            (Some(_), ReadingMode::Paged) => {
+                widget::responsive(move |size| self.view_page(reader, size)).into()
+            }
+        };
+
+        let page = widget::column()
+            .push(header)
+            .push(body)
+            .push_maybe(reader.selected.map(|i| self.view_annotation_bar(reader, i)));
+
+        // Without reader colours the page keeps the app theme's background
+        match config.reader_theme.colors() {
+            Some((background, text)) => page
+                .apply(widget::container)
+                .style(theme::Container::custom(move |_| {
+                    cosmic::iced::widget::container::Appearance {
+                        text_color: Some(text),
+                        icon_color: Some(text),
+                        background: Some(Background::Color(background)),
+                        ..Default::default()
+                    }
+                }))
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .into(),
+            None => page.into(),
+        }
+    }
+
+    /// One screen of the chapter, clicking the left side goes back and
+    /// anywhere else goes forward
+    fn view_page<'a>(&'a self, reader: &'a Reader, size: Size) -> Element<'a, Message> {
+        let metrics = PageMetrics::new(&self.config, size);
+        let pages = reader.pages(&metrics);
+        let index = reader
+            .anchor
+            .and_then(|p| {
+                pages
+                    .iter()
+                    .position(|page| page.iter().any(|(i, _)| *i == p))
+            })
+            .unwrap_or(reader.page)
+            .min(pages.len().saturating_sub(1));
+        // A paragraph split by a page break carries on after the words shown before
+        let page = pages
+            .get(index)
+            .cloned()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(i, text)| {
+                let offset = pages[..index]
+                    .iter()
+                    .flatten()
+                    .filter(|(shown, _)| *shown == i)
+                    .map(|(_, text)| text.split_whitespace().count())
+                    .sum();
+                (i, offset, text)
+            })
+            .collect();
+
+        let zone = |turn| {
+            widget::mouse_area(Space::new(Length::Fill, Length::Fill))
+                .on_press(Message::TurnPage(turn))
+        };
+
+        widget::row()
+            .push(zone(Direction::Previous))
+            .push(
+                widget::mouse_area(
+                    self.view_paragraphs(reader, page)
+                        .width(Length::Fixed(metrics.width))
+                        .height(Length::Fill),
+                )
+                .on_press(Message::TurnPage(Direction::Next)),
+            )
+            .push(zone(Direction::Next))
+            .padding(self.config.margin)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into()
+    }
+
+    /// Note input and actions for the paragraph picked with a right click
+    fn view_annotation_bar<'a>(
+        &'a self,
+        reader: &'a Reader,
+        paragraph: usize,
+    ) -> Element<'a, Message> {
+        let spacing = theme::active().cosmic().spacing;
+
+        let bar = widget::row()
+            .push(
+                widget::text_input(fl!("annotation-passage"), &self.annotation_passage)
+                    .on_input(Message::AnnotationPassageChanged)
+                    .width(Length::Fill),
+            )
+            .push(
+                widget::text_input(fl!("annotation-note"), &self.annotation_note)
+                    .on_input(Message::AnnotationNoteChanged)
+                    .on_submit(Message::Annotate(AnnotationKind::Highlight))
+                    .width(Length::Fill),
+            )
+            .push(
+                widget::button::standard(fl!("highlight"))
+                    .on_press(Message::Annotate(AnnotationKind::Highlight)),
+            )
+            .push(
+                widget::button::standard(fl!("bookmark"))
+                    .on_press(Message::Annotate(AnnotationKind::Bookmark)),
+            )
+            .align_items(Alignment::Center)
+            .spacing(spacing.space_xxs)
+            .padding([spacing.space_xxs, spacing.space_s]);
+
+        reader
+            .annotations
+            .iter()
+            .filter(|a| a.paragraph == paragraph)
+            .fold(bar, |bar, a| {
+                bar.push(
+                    widget::button::text(match a.kind {
+                        AnnotationKind::Bookmark => fl!("annotation-remove-bookmark"),
+                        AnnotationKind::Highlight => fl!("annotation-remove-highlight"),
+                    })
+                    .on_press(Message::DeleteAnnotation(a.id)),
+                )
+            })
+            .push(
+                widget::button::icon(icon::from_name("window-close-symbolic"))
+                    .on_press(Message::SelectParagraph(None)),
+            )
+            .into()
+    }
+
+    /// The paragraphs as text, marking highlights, the one being read aloud
+    /// and the one picked for annotating. Each comes with the word it starts
+    /// at, see [`Reader::runs`]
+    fn view_paragraphs(
+        &self,
+        reader: &Reader,
+        paragraphs: Vec<(usize, usize, String)>,
+    ) -> widget::Column<Message> {
+        let config = &self.config;
+        let font = config::reader_font(&config.reader_font);
+        let text = |text: String| {
+            widget::text(text)
+                .font(font)
+                .size(config.font_size)
+                .line_height(LineHeight::Relative(config.line_height))
+                .width(Length::Fill)
+        };
+
+        widget::column::with_children(
+            paragraphs
+                .into_iter()
+                .map(|(i, offset, p)| {
+                    let runs = reader.runs(i, offset, &p);
+                    let speaking = reader.speaking == Some(i);
+                    let selected = reader.selected == Some(i);
+                    let highlighted = runs.iter().all(|(_, highlighted)| *highlighted);
+
+                    // A passage is highlighted as a block of its own, the
+                    // text widget has no styled spans
+                    let content: Element<Message> = match runs.len() {
+                        1 => text(p.clone()).into(),
+                        _ => widget::column::with_children(
+                            runs.into_iter()
+                                .map(|(run, highlighted)| match highlighted {
+                                    true => text(run.to_string())
+                                        .apply(widget::container)
+                                        .style(theme::Container::custom(|theme| {
+                                            let warning: Color =
+                                                theme.cosmic().warning_color().into();
+                                            cosmic::iced::widget::container::Appearance {
+                                                background: Some(Background::Color(Color {
+                                                    a: 0.3,
+                                                    ..warning
+                                                })),
+                                                ..Default::default()
+                                            }
+                                        }))
+                                        .into(),
+                                    false => text(run.to_string()).into(),
+                                })
+                                .collect(),
+                        )
+                        .into(),
+                    };
+
+                    let paragraph: Element<Message> = if speaking || selected || highlighted {
+                        content
+                            .apply(widget::container)
+                            .style(theme::Container::custom(move |theme| {
+                                let cosmic = theme.cosmic();
+                                let accent: Color = cosmic.accent_color().into();
+                                let warning: Color = cosmic.warning_color().into();
+                                let background = match (speaking, highlighted) {
+                                    (true, _) => Some(Color { a: 0.2, ..accent }),
+                                    (false, true) => Some(Color { a: 0.3, ..warning }),
+                                    (false, false) => None,
+                                };
+                                cosmic::iced::widget::container::Appearance {
+                                    background: background.map(Background::Color),
+                                    border: Border {
+                                        radius: cosmic.corner_radii.radius_xs.into(),
+                                        width: if selected { 1.0 } else { 0.0 },
+                                        color: accent,
+                                    },
+                                    ..Default::default()
+                                }
+                            }))
+                            .into()
+                    } else {
+                        content
+                    };
+
+                    widget::mouse_area(paragraph)
+                        .on_right_press(Message::SelectParagraph(Some(i)))
+                        .into()
+                })
+                .collect(),
+        )
+        .spacing(config.paragraph_spacing)
+    }
+}
+
+/// Starts a newly opened chapter at the top in scroll mode
+pub fn scroll_to_top() -> Command<Message> {
+    cosmic::iced::widget::scrollable::snap_to(
+        SCROLLABLE.clone(),
+        cosmic::iced::widget::scrollable::RelativeOffset::START,
+    )
+}
+
+/// Page turns from the mouse wheel while the paged reader is open, the keys
+/// go through the key bindings
+pub fn page_turn_events(event: Event, status: event::Status) -> Option<Message> {
+    if status == event::Status::Captured {
+        return None;
+    }
+
+    let Event::Mouse(mouse::Event::WheelScrolled { delta }) = event else {
+        return None;
+    };
+    let (ScrollDelta::Lines { y, .. } | ScrollDelta::Pixels { y, .. }) = delta;
+    match y {
+        y if y < 0.0 => Some(Message::TurnPage(Direction::Next)),
+        y if y > 0.0 => Some(Message::TurnPage(Direction::Previous)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lines of 20 chars, 10 tall
+    fn height_of(text: &str) -> f32 {
+        (text.chars().count() as f32 / 20.0).ceil().max(1.0) * 10.0
+    }
+
+    fn chapter(paragraphs: &[&str]) -> Vec<String> {
+        paragraphs.iter().map(|p| p.to_string()).collect()
+    }
+
+    /// Every word with its paragraph, in the order the pages show them
+    fn words(pages: &[PageText]) -> Vec<(usize, String)> {
+        pages
+            .iter()
+            .flatten()
+            .flat_map(|(i, text)| text.split_whitespace().map(|w| (*i, w.to_string())))
+            .collect()
+    }
+
+    #[test]
+    fn empty_chapter_is_one_empty_page() {
+        assert_eq!(
+            paginate(&[], 100.0, 5.0, |_, _, text| height_of(text)),
+            vec![vec![]]
+        );
+    }
+
+    #[test]
+    fn paragraphs_that_fit_share_a_page() {
+        let pages = paginate(&chapter(&["one", "two"]), 100.0, 5.0, |_, _, text| {
+            height_of(text)
+        });
+        assert_eq!(pages, vec![vec![(0, "one".into()), (1, "two".into())]]);
+    }
+
+    #[test]
+    fn spacing_pushes_a_paragraph_over() {
+        // 10 + 5 + 10 is over 20
+        let pages = paginate(&chapter(&["one", "two"]), 20.0, 5.0, |_, _, text| {
+            height_of(text)
+        });
+        assert_eq!(
+            pages,
+            vec![vec![(0, "one".into())], vec![(1, "two".into())]]
+        );
+    }
+
+    #[test]
+    fn long_paragraph_splits_between_words() {
+        let text = (0..50).map(|i| format!("word{i}")).collect::<Vec<_>>();
+        let paragraphs = vec![text.join(" ")];
+        let pages = paginate(&paragraphs, 30.0, 5.0, |_, _, text| height_of(text));
+
+        assert!(pages.len() > 1);
+        for page in &pages {
+            let used = page.iter().map(|(_, text)| height_of(text)).sum::<f32>();
+            assert!(used <= 30.0, "page overflows: {page:?}");
+        }
+        let expected = text.into_iter().map(|w| (0, w)).collect::<Vec<_>>();
+        assert_eq!(words(&pages), expected);
+    }
+
+    #[test]
+    fn word_taller_than_the_page_gets_a_page_of_its_own() {
+        let long = "x".repeat(100);
+        let pages = paginate(
+            &chapter(&["before", &long, "after"]),
+            20.0,
+            5.0,
+            |_, _, text| height_of(text),
+        );
+        assert_eq!(
+            pages,
+            vec![
+                vec![(0, "before".into())],
+                vec![(1, long.clone())],
+                vec![(2, "after".into())],
+            ]
+        );
+    }
+
+    #[test]
+    fn resizing_keeps_every_word_in_order() {
+        let paragraphs = (0..12)
+            .map(|p| {
+                (0..15)
+                    .map(|w| format!("p{p}w{w}"))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect::<Vec<String>>();
+        let small = paginate(&paragraphs, 40.0, 5.0, |_, _, text| height_of(text));
+        let large = paginate(&paragraphs, 200.0, 5.0, |_, _, text| height_of(text));
+
+        assert!(small.len() > large.len());
+        assert_eq!(words(&small), words(&large));
+        // The same text again gives the same pages
+        assert_eq!(
+            paginate(&paragraphs, 40.0, 5.0, |_, _, text| height_of(text)),
+            small
+        );
+    }
+
+    #[test]
+    fn highlighted_passage_is_a_run_of_its_own_on_both_sides_of_a_page_break() {
+        let paragraph = "one two three four five six";
+        let reader = Reader {
+            content: Some(chapter(&[paragraph])),
+            annotations: vec![Annotation::new(
+                String::new(),
+                String::new(),
+                None,
+                AnnotationKind::Highlight,
+                0,
+                paragraph.into(),
+                String::new(),
+            )
+            .with_passage("three four")],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            reader.runs(0, 0, paragraph),
+            vec![
+                ("one two", false),
+                ("three four", true),
+                ("five six", false)
+            ]
+        );
+        // Split after "three" by a page break
+        assert_eq!(
+            reader.runs(0, 0, "one two three"),
+            vec![("one two", false), ("three", true)]
+        );
+        assert_eq!(
+            reader.runs(0, 3, "four five six"),
+            vec![("four", true), ("five six", false)]
+        );
+        // Other paragraphs are left alone
+        assert_eq!(reader.runs(1, 0, "seven"), vec![("seven", false)]);
+    }
+
+    use std::sync::Arc;
+
+    use cosmic::iced_runtime::command::Action;
+
+    use crate::core::tts::fake::FakeBackend;
+
+    /// An app reading the first of `chapters` chapters, with the text of it
+    /// shown, that speaks into a fake engine
+    fn reading(paragraphs: &[&str], chapters: usize) -> (App, Arc<FakeBackend>) {
+        let fake = Arc::new(FakeBackend::default());
+        let mut app = App::default();
+        app.tts = tts::Backends::new(vec![fake.clone()]);
+        app.config.tts_backend = "fake".into();
+        app.config.prefetch_chapters = 0;
+
+        let reader = Reader {
+            chapters: (1..=chapters)
+                .map(|i| {
+                    Chapter::new(
+                        Some(i as u32),
+                        Some(format!("Chapter {i}")),
+                        Some(format!("https://example.com/chapter/{i}")),
+                    )
+                })
+                .collect(),
+            content: Some(chapter(paragraphs)),
+            ..Default::default()
+        };
+        app.stack.push(Box::new(ReaderPage { reader }));
+        (app, fake)
+    }
+
+    /// Runs the command's futures and hands what the engine said back to
+    /// the app, until reading aloud stops or waits on a chapter
+    async fn speak(app: &mut App, command: Command<Message>) {
+        let mut pending = vec![command];
+        while let Some(command) = pending.pop() {
+            for action in command.actions() {
+                let Action::Future(future) = action else {
+                    continue;
+                };
+                if let cosmic::app::Message::App(Message::Spoken(session, paragraph, res)) =
+                    future.await
+                {
+                    pending.push(app.spoken(session, paragraph, res));
+                }
+            }
+        }
+    }
+
+    fn spoken(fake: &FakeBackend) -> Vec<String> {
+        fake.spoken.lock().unwrap().clone()
+    }
+
+    #[tokio::test]
+    async fn reading_aloud_goes_through_the_paragraphs() {
+        let (mut app, fake) = reading(&["One", "Two", "Three"], 1);
+
+        let command = app.read_aloud(1);
+        speak(&mut app, command).await;
+
+        assert_eq!(spoken(&fake), ["Two", "Three"]);
+        // The end of the last chapter stops
+        assert_eq!(app.reader().unwrap().speaking, None);
+        assert_eq!(*fake.stops.lock().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn stopping_cuts_off_speech_that_has_not_started() {
+        let (mut app, fake) = reading(&["One", "Two"], 1);
+
+        let command = app.read_aloud(0);
+        app.stop_read_aloud();
+        speak(&mut app, command).await;
+
+        assert!(spoken(&fake).is_empty());
+        assert_eq!(*fake.cancelled.lock().unwrap(), ["One"]);
+        // The late reply belongs to the old session and doesn't go on
+        assert_eq!(app.reader().unwrap().speaking, None);
+    }
+
+    #[tokio::test]
+    async fn stopping_is_not_carried_into_the_next_session() {
+        let (mut app, fake) = reading(&["One"], 1);
+
+        app.stop_read_aloud();
+        let command = app.toggle_read_aloud();
+        speak(&mut app, command).await;
+
+        assert_eq!(spoken(&fake), ["One"]);
+        assert!(fake.cancelled.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn reading_aloud_carries_on_into_the_next_chapter() {
+        let (mut app, fake) = reading(&["One", "Two"], 2);
+
+        let command = app.read_aloud(0);
+        speak(&mut app, command).await;
+
+        let reader = app.reader().unwrap();
+        assert_eq!(reader.index, 1);
+        // Waiting on the text of the next chapter to speak its start
+        assert_eq!(reader.content, None);
+        assert_eq!(reader.speaking, Some(0));
+        assert_eq!(spoken(&fake), ["One", "Two"]);
+
+        let next = reader.chapter().unwrap().clone();
+        let command = app.show_chapter(&next, "Three\n\nFour");
+        speak(&mut app, command).await;
+
+        assert_eq!(spoken(&fake), ["One", "Two", "Three", "Four"]);
+        assert_eq!(app.reader().unwrap().speaking, None);
     }
 }
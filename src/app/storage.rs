@@ -0,0 +1,71 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Runs [`DataManager`] calls on the blocking thread pool so SQLite never
+//! holds up a frame. Results come back as messages, `update` keeps what the
+//! views need and the views only read that.
+
+use std::sync::Arc;
+
+use cosmic::app::{message, Command};
+
+use super::{LogMessage, Message};
+use crate::core::data::DataManager;
+use crate::core::{self, Error};
+
+/// The database, shared with the commands using it. Calls don't wait on
+/// each other, SQLite sorts out concurrent writes
+#[derive(Clone, Default)]
+pub struct Storage {
+    data: Arc<DataManager>,
+}
+
+impl Storage {
+    /// The data manager itself, for core code that keeps its calls off the
+    /// async threads on its own
+    pub fn data(&self) -> Arc<DataManager> {
+        Arc::clone(&self.data)
+    }
+
+    /// Runs `f` with the data manager on a blocking thread
+    pub async fn run<T, F>(&self, f: F) -> core::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&DataManager) -> core::Result<T> + Send + 'static,
+    {
+        let data = Arc::clone(&self.data);
+        tokio::task::spawn_blocking(move || f(&data))
+            .await
+            .map_err(|e| Error::Storage(e.to_string()))?
+    }
+
+    /// Runs `f` and sends its result on as the message `done` makes, errors
+    /// go to the notification center
+    pub fn perform<T, F>(
+        &self,
+        f: F,
+        done: impl FnOnce(T) -> Message + Send + 'static,
+    ) -> Command<Message>
+    where
+        T: Send + 'static,
+        F: FnOnce(&DataManager) -> core::Result<T> + Send + 'static,
+    {
+        let storage = self.clone();
+        Command::perform(
+            async move {
+                match storage.run(f).await {
+                    Ok(value) => message::app(done(value)),
+                    Err(e) => message::app(Message::Log(LogMessage::Error(e))),
+                }
+            },
+            |x| x,
+        )
+    }
+
+    /// Runs a write nothing waits on, only reporting errors
+    pub fn write<F>(&self, f: F) -> Command<Message>
+    where
+        F: FnOnce(&DataManager) -> core::Result<()> + Send + 'static,
+    {
+        self.perform(f, |()| Message::Ignore)
+    }
+}
@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use std::path::Display;
 
 use super::*;
+use crate::core::cover::{Cover, CoverSize};
 use crate::core::Book;
 use crate::fl;
 use cosmic::app::{Command, Core};
@@ -10,9 +11,50 @@ use cosmic::iced::window::Icon;
 use cosmic::iced::{Alignment, Length, Padding, Size};
 use cosmic::widget::*;
 use cosmic::{cosmic_theme, theme, ApplicationExt, Apply, Element};
+use itertools::Itertools;
+
+/// Shown for books without a cover and while covers load
+const PLACEHOLDER_COVER: &str = "res/covers/rr-image.png";
+
+/// The text with the chars at `indices` in the accent color, as search
+/// matches are shown. Without any it is plain text that wraps
+pub fn highlighted_text(text: &str, indices: &[usize]) -> Element<'static, Message> {
+    if indices.is_empty() {
+        return widget::text(text.to_string()).into();
+    }
+    let chunks = text
+        .chars()
+        .enumerate()
+        .chunk_by(|(i, _)| indices.contains(i));
+    let segments = (&chunks)
+        .into_iter()
+        .map(|(matched, chars)| {
+            let segment = widget::text(chars.map(|(_, c)| c).collect::<String>());
+            match matched {
+                true => segment.style(theme::Text::Accent).into(),
+                false => segment.into(),
+            }
+        })
+        .collect();
+    widget::row::with_children(segments).into()
+}
 
 impl App {
     pub fn create_book_card(&self, book: &Book, size: Size) -> Element<Message> {
+        let open = Message::ToggleContextPage(ContextPage::BookContext(book.clone()));
+        self.book_card(book, size, open, false, &[])
+    }
+
+    /// A cover card sending `on_press`, highlighted while `selected`, with
+    /// the name's chars at `highlight` in the accent color
+    pub fn book_card(
+        &self,
+        book: &Book,
+        size: Size,
+        on_press: Message,
+        selected: bool,
+        highlight: &[usize],
+    ) -> Element<Message> {
         let spacing = theme::active().cosmic().spacing;
 
         let mut card_content = cosmic::widget::column()
@@ -20,10 +62,8 @@ impl App {
             .width(Length::Fill);
         // .height(Length::Shrink);
 
-        let handle = self.data_manager.get_image_handle(book);
-
         card_content = card_content.push(
-            cosmic::iced::widget::image(handle.clone())
+            cosmic::iced::widget::image(self.cover(&book.url, CoverSize::Thumbnail))
                 .content_fit(cosmic::iced::ContentFit::Contain)
                 .width(Length::Fill)
                 .border_radius([spacing.space_xxs as f32; 4])
@@ -32,7 +72,9 @@ impl App {
         );
 
         card_content = card_content.push(
-            cosmic::widget::text(book.name.clone()).height(Length::Fixed(spacing.space_xl as f32)),
+            highlighted_text(&book.name, highlight)
+                .apply(container)
+                .height(Length::Fixed(spacing.space_xl as f32)),
         );
 
         let card = container(card_content)
@@ -40,9 +82,8 @@ impl App {
             .style(cosmic::theme::Container::Secondary);
 
         let button = widget::button::custom_image_button(card, None)
-            .on_press(Message::ToggleContextPage(ContextPage::BookContext(
-                book.clone(),
-            )))
+            .on_press(on_press)
+            .selected(selected)
             .style(cosmic::theme::Button::Image)
             .width(Length::Fixed(size.width))
             .height(Length::Shrink);
@@ -50,16 +91,101 @@ impl App {
         button.into()
     }
 
-    pub async fn download_book_cover(
-        image_url: String,
-    ) -> Result<bytes::Bytes, Box<dyn std::error::Error>> {
-        let response = reqwest::blocking::get(image_url)?;
-        let content = response.bytes()?;
-        _ = cosmic::widget::image::Handle::from_memory(content.clone());
-        Ok(content)
+    /// The book's cover if it is loaded, otherwise the placeholder
+    pub fn cover(&self, url: &str, size: CoverSize) -> widget::image::Handle {
+        self.covers
+            .peek(url, size)
+            .unwrap_or_else(|| widget::image::Handle::from_path(PLACEHOLDER_COVER))
+    }
+
+    /// Loads the covers the cache is missing for the books by url
+    pub fn load_covers(&mut self, urls: Vec<String>, size: CoverSize) -> Command<Message> {
+        let missing = urls
+            .into_iter()
+            .filter(|url| self.covers.get(url, size).is_none())
+            .collect::<Vec<String>>();
+        if missing.is_empty() {
+            return Command::none();
+        }
+
+        self.storage.perform(
+            move |data| {
+                // A cover that can't be read is logged and left as the placeholder
+                Ok(missing
+                    .into_iter()
+                    .map(|url| {
+                        let cover = data.get_cover(&url).ok().flatten();
+                        (url, cover.map(|c| c.get(size).clone()))
+                    })
+                    .collect())
+            },
+            move |covers| Message::CoversLoaded(size, covers),
+        )
+    }
+
+    pub fn covers_loaded(&mut self, size: CoverSize, covers: Vec<(String, Option<bytes::Bytes>)>) {
+        for (url, bytes) in covers {
+            match bytes {
+                Some(bytes) => {
+                    let len = bytes.len();
+                    self.covers
+                        .insert(&url, size, widget::image::Handle::from_memory(bytes), len);
+                }
+                // Books without a cover cache the placeholder so they aren't
+                // asked for again, unless a downloaded cover got there first
+                None if self.covers.peek(&url, size).is_none() => {
+                    let handle = widget::image::Handle::from_path(PLACEHOLDER_COVER);
+                    self.covers.insert(&url, size, handle, url.len());
+                }
+                None => (),
+            }
+        }
+    }
+
+    /// Downloads a cover and processes it off the UI thread
+    pub async fn download_book_cover(image_url: String) -> crate::core::Result<Cover> {
+        let content = crate::core::http::CACHE.get_image(&image_url).await?;
+        tokio::task::spawn_blocking(move || Cover::process(&content))
+            .await
+            .map_err(|e| crate::core::Error::Image(e.to_string()))?
+    }
+
+    /// Stacks the visible toasts, newest last
+    pub fn view_toasts(&self) -> Element<Message> {
+        let spacing = theme::active().cosmic().spacing;
+
+        let toasts = self.notifications.toasts().map(|n| {
+            let mut row = widget::row()
+                .push(icon::from_name(n.level.icon_name()).size(16).icon())
+                .push(widget::text(n.text.clone()).width(Length::Fill));
+            if let Some(action) = &n.action {
+                row =
+                    row.push(widget::button::text(fl!("retry")).on_press(action.as_ref().clone()));
+            }
+            row.push(
+                widget::button::icon(icon::from_name("window-close-symbolic"))
+                    .on_press(Message::DismissNotification(n.id)),
+            )
+            .align_items(Alignment::Center)
+            .spacing(spacing.space_xxs)
+            .apply(container)
+            .padding(spacing.space_xxs)
+            .style(cosmic::theme::Container::Card)
+            .into()
+        });
+
+        widget::column::with_children(toasts.collect())
+            .spacing(spacing.space_xxs)
+            .padding([0, spacing.space_xs])
+            .into()
+    }
+
+    /// Posts to the notification center from a synchronous handler
+    pub fn notify(&self, log: LogMessage) -> Command<Message> {
+        Command::perform(async move { message::app(Message::Log(log)) }, |x| x)
     }
 
-    pub fn log_error(&self, err: String) -> Command<Message> {
+    pub fn log_error(&self, err: crate::core::Error) -> Command<Message> {
         Command::perform(
             async move { message::app(Message::Log(LogMessage::Error(err))) },
             |x| x,
@@ -0,0 +1,182 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Commands run from the terminal instead of opening the window
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::core::audio::{AudioExport, AudioFormat};
+use crate::core::data::DataManager;
+use crate::core::export::{self, ExportBook};
+use crate::core::{self, source};
+
+const USAGE: &str = concat!(
+    "usage: ",
+    env!("CARGO_PKG_NAME"),
+    " export-audio <book url> [options]
+       ",
+    env!("CARGO_PKG_NAME"),
+    " export <book url> [options]
+
+export-audio options:
+    --from <n>         first chapter, counting from 1
+    --to <n>           last chapter
+    --format <format>  opus or mp3
+    --out <dir>        directory the file is written to
+
+export writes the chapters stored by the app, options:
+    --format <format>  txt, markdown, markdown-chapters or html
+    --out <dir>        directory the file is written to"
+);
+
+/// Runs the command in `args` if there is one, returning the exit code
+pub fn run(args: &[String], config: &Config, data_dir: Option<PathBuf>) -> Option<i32> {
+    let (command, args) = args.split_first()?;
+
+    let res = match command.as_str() {
+        "export-audio" => export_audio(args, config, data_dir),
+        "export" => export_book(args, data_dir),
+        "-h" | "--help" | "help" => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        // Anything else, like the files the desktop entry passes, opens the window
+        _ => return None,
+    };
+
+    match res {
+        Ok(()) => Some(0),
+        Err(e) => {
+            eprintln!("{e}");
+            Some(1)
+        }
+    }
+}
+
+fn export_audio(args: &[String], config: &Config, data_dir: Option<PathBuf>) -> Result<(), String> {
+    let mut url = None;
+    let mut from = 1;
+    let mut to = None;
+    let mut format = config.audio_format;
+    let mut dest = std::env::current_dir().map_err(|e| e.to_string())?;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("{arg} needs a value\n\n{USAGE}"))
+        };
+        match arg.as_str() {
+            "--from" => from = parse_number(value()?)?,
+            "--to" => to = Some(parse_number(value()?)?),
+            "--format" => {
+                let name = value()?;
+                format = AudioFormat::from_name(name)
+                    .ok_or_else(|| format!("unknown format {name:?}, use opus or mp3"))?;
+            }
+            "--out" => dest = PathBuf::from(value()?),
+            arg if url.is_none() && !arg.starts_with("--") => url = Some(arg.to_string()),
+            arg => return Err(format!("unexpected argument {arg:?}\n\n{USAGE}")),
+        }
+    }
+    let url = url.ok_or_else(|| USAGE.to_string())?;
+
+    // Chapters downloaded in the app aren't fetched again
+    let mut data = None;
+    if let Some(dir) = data_dir {
+        if let Err(e) = core::http::CACHE.init(dir.join("http-cache")) {
+            tracing::warn!("running without the http cache: {e}");
+        }
+        let manager = DataManager::new();
+        match manager.init(dir).into_iter().next() {
+            Some(e) => tracing::warn!("reading chapters without storage: {e}"),
+            None => data = Some(Arc::new(manager)),
+        }
+    }
+
+    let runtime = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+    runtime.block_on(async move {
+        let source = source::for_url(&url).map_err(|e| e.to_string())?;
+        let book = source
+            .scrape_book(url.clone())
+            .await
+            .map_err(|e| e.to_string())?;
+        let chapters = source
+            .scrape_chapter_list(url)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let to = to.unwrap_or(chapters.len()).min(chapters.len());
+        if from == 0 || from > to {
+            return Err(format!(
+                "no chapters in {from}..={to}, the book has {}",
+                chapters.len()
+            ));
+        }
+
+        let export = AudioExport {
+            book,
+            chapters: chapters[from - 1..to].to_vec(),
+            format,
+            backend: config.tts_backend.clone(),
+            voice: config.voice(),
+            dest,
+        };
+        let path = export
+            .run(data, |done, total| eprintln!("{done}/{total} chapters"))
+            .await
+            .map_err(|e| e.to_string())?;
+        println!("{}", path.display());
+        Ok(())
+    })
+}
+
+fn export_book(args: &[String], data_dir: Option<PathBuf>) -> Result<(), String> {
+    let mut url = None;
+    let mut exporter = export::for_name("txt").map_err(|e| e.to_string())?;
+    let mut dest = std::env::current_dir().map_err(|e| e.to_string())?;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("{arg} needs a value\n\n{USAGE}"))
+        };
+        match arg.as_str() {
+            "--format" => exporter = export::for_name(value()?).map_err(|e| e.to_string())?,
+            "--out" => dest = PathBuf::from(value()?),
+            arg if url.is_none() && !arg.starts_with("--") => url = Some(arg.to_string()),
+            arg => return Err(format!("unexpected argument {arg:?}\n\n{USAGE}")),
+        }
+    }
+    let url = url.ok_or_else(|| USAGE.to_string())?;
+
+    let data = DataManager::new();
+    let dir = data_dir.ok_or_else(|| "no data directory".to_string())?;
+    if let Some(e) = data.init(dir).into_iter().next() {
+        return Err(e.to_string());
+    }
+
+    let book = data
+        .get_book(&url)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("{url} isn't stored, open it in the app first"))?;
+    let chapters = data.get_stored_chapters(&url).map_err(|e| e.to_string())?;
+    let export = ExportBook {
+        cover: data.get_cover(&book.url).ok().flatten().map(|c| c.detail),
+        book,
+        chapters,
+    };
+
+    let path = exporter.export(&export, &dest).map_err(|e| e.to_string())?;
+    eprintln!("{} chapters", export.chapters.len());
+    println!("{}", path.display());
+    Ok(())
+}
+
+fn parse_number(value: &str) -> Result<usize, String> {
+    value
+        .parse()
+        .map_err(|_| format!("expected a chapter number, got {value:?}"))
+}
@@ -0,0 +1,415 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::collections::HashMap;
+
+use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
+use cosmic::iced::advanced::graphics::text::font_system;
+use cosmic::iced::font::Family;
+use cosmic::iced::{Color, Font};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::app::MenuAction;
+use crate::core::audio::AudioFormat;
+use crate::core::{Book, LibraryStats};
+use crate::fl;
+
+pub const CONFIG_VERSION: u64 = 1;
+
+/// User settings, persisted with cosmic-config under the app id
+#[derive(Debug, Clone, CosmicConfigEntry, PartialEq, Serialize, Deserialize)]
+#[version = 1]
+pub struct Config {
+    pub app_theme: AppTheme,
+    /// Family name, one of [`reader_fonts`]
+    pub reader_font: String,
+    pub font_size: u16,
+    pub line_height: f32,
+    /// Pixels between paragraphs
+    pub paragraph_spacing: u16,
+    /// Widest the text column grows to, in pixels
+    pub column_width: u16,
+    /// Pixels around the text column
+    pub margin: u16,
+    pub reader_theme: ReaderTheme,
+    pub reading_mode: ReadingMode,
+    pub default_sort: LibrarySort,
+    pub library_view: LibraryView,
+    pub library_filter: LibraryFilter,
+    /// Sources searched from Explore, by [`crate::core::source::Source::as_str`]
+    pub enabled_sources: Vec<String>,
+    pub download_concurrency: u16,
+    /// Chapters after the open one downloaded ahead of time, 0 turns it off
+    pub prefetch_chapters: u16,
+    /// Hours between library metadata refreshes, 0 turns them off
+    pub update_interval: u32,
+    /// Seconds a stored page is served without asking the site
+    pub cache_ttl_secs: u64,
+    /// Seconds a stored cover is served without asking the site
+    pub cache_image_ttl_secs: u64,
+    pub cache_max_bytes: u64,
+    pub log_level: String,
+    /// Speech engine for reading aloud, by [`crate::core::tts::SpeechBackend::as_str`]
+    pub tts_backend: String,
+    /// Engine voice, empty for the engine's default
+    pub tts_voice: String,
+    /// -100 to 100 around the engine's default
+    pub tts_rate: i16,
+    /// -100 to 100 around the engine's default
+    pub tts_pitch: i16,
+    pub audio_format: AudioFormat,
+    /// Shortcuts replacing the defaults of their action, see [`crate::app::key_binds`]
+    pub key_binds: HashMap<MenuAction, String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let cache = crate::core::http::CacheConfig::default();
+        Config {
+            app_theme: AppTheme::default(),
+            reader_font: SANS.into(),
+            font_size: 18,
+            line_height: 1.5,
+            paragraph_spacing: 12,
+            column_width: 720,
+            margin: 24,
+            reader_theme: ReaderTheme::default(),
+            reading_mode: ReadingMode::default(),
+            default_sort: LibrarySort::default(),
+            library_view: LibraryView::default(),
+            library_filter: LibraryFilter::default(),
+            enabled_sources: crate::core::source::all()
+                .iter()
+                .map(|s| s.as_str())
+                .collect(),
+            download_concurrency: cache.concurrency as u16,
+            prefetch_chapters: 3,
+            update_interval: 0,
+            cache_ttl_secs: cache.page_ttl.as_secs(),
+            cache_image_ttl_secs: cache.image_ttl.as_secs(),
+            cache_max_bytes: cache.max_bytes,
+            log_level: crate::core::logging::DEFAULT_LEVEL.into(),
+            tts_backend: crate::core::tts::Backends::default().names()[0].into(),
+            tts_voice: String::new(),
+            tts_rate: 0,
+            tts_pitch: 0,
+            audio_format: AudioFormat::default(),
+            key_binds: HashMap::new(),
+        }
+    }
+}
+
+impl Config {
+    pub const FONT_SIZE_MIN: u16 = 10;
+    pub const FONT_SIZE_MAX: u16 = 40;
+    pub const PARAGRAPH_SPACING_MAX: u16 = 64;
+    pub const COLUMN_WIDTH_MIN: u16 = 320;
+    pub const COLUMN_WIDTH_MAX: u16 = 2000;
+    pub const COLUMN_WIDTH_STEP: u16 = 40;
+    pub const MARGIN_MAX: u16 = 200;
+    pub const MARGIN_STEP: u16 = 8;
+    pub const DOWNLOAD_CONCURRENCY_MAX: u16 = 8;
+    pub const PREFETCH_CHAPTERS_MAX: u16 = 10;
+
+    /// Loads the stored config, falling back to defaults for anything unreadable
+    pub fn load(app_id: &str) -> (Option<cosmic_config::Config>, Config) {
+        match cosmic_config::Config::new(app_id, CONFIG_VERSION) {
+            Ok(handler) => {
+                let config = match Config::get_entry(&handler) {
+                    Ok(config) => config,
+                    Err((errors, config)) => {
+                        for e in errors {
+                            tracing::error!("failed to load config: {e}");
+                        }
+                        config
+                    }
+                };
+                (Some(handler), config)
+            }
+            Err(e) => {
+                tracing::error!("failed to create config handler: {e}");
+                (None, Config::default())
+            }
+        }
+    }
+
+    pub fn voice(&self) -> crate::core::tts::Voice {
+        crate::core::tts::Voice {
+            rate: self.tts_rate,
+            pitch: self.tts_pitch,
+            name: Some(self.tts_voice.clone()).filter(|v| !v.is_empty()),
+        }
+    }
+
+    pub fn cache_config(&self) -> crate::core::http::CacheConfig {
+        crate::core::http::CacheConfig {
+            page_ttl: std::time::Duration::from_secs(self.cache_ttl_secs),
+            image_ttl: std::time::Duration::from_secs(self.cache_image_ttl_secs),
+            max_bytes: self.cache_max_bytes,
+            concurrency: usize::from(self.download_concurrency.max(1)),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AppTheme {
+    Dark,
+    Light,
+    #[default]
+    System,
+}
+
+impl AppTheme {
+    pub const ALL: [AppTheme; 3] = [AppTheme::System, AppTheme::Dark, AppTheme::Light];
+    pub fn labels() -> &'static [String] {
+        static LABELS: Lazy<Vec<String>> = Lazy::new(|| {
+            vec![
+                fl!("app-theme-system"),
+                fl!("app-theme-dark"),
+                fl!("app-theme-light"),
+            ]
+        });
+        &LABELS
+    }
+
+    pub fn theme(&self) -> cosmic::theme::Theme {
+        match self {
+            Self::Dark => cosmic::theme::Theme::dark(),
+            Self::Light => cosmic::theme::Theme::light(),
+            Self::System => cosmic::theme::system_preference(),
+        }
+    }
+}
+
+const SANS: &str = "Sans";
+const SERIF: &str = "Serif";
+const MONO: &str = "Monospace";
+
+/// The generic families followed by every family installed on the system
+pub fn reader_fonts() -> &'static [&'static str] {
+    static FONTS: Lazy<Vec<&'static str>> = Lazy::new(|| {
+        let mut font_system = font_system().write().unwrap();
+        let mut families = font_system
+            .raw()
+            .db()
+            .faces()
+            .filter_map(|face| face.families.first())
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<String>>();
+        families.sort();
+        families.dedup();
+
+        // iced wants 'static family names, the list is built once
+        [SANS, SERIF, MONO]
+            .into_iter()
+            .chain(
+                families
+                    .into_iter()
+                    .map(|f| &*Box::leak(f.into_boxed_str())),
+            )
+            .collect()
+    });
+    &FONTS
+}
+
+/// Resolves a family name from [`reader_fonts`], unknown names fall back to sans
+pub fn reader_font(name: &str) -> Font {
+    let family = match name {
+        SERIF => Family::Serif,
+        MONO => Family::Monospace,
+        name => match reader_fonts().iter().skip(3).find(|f| **f == name) {
+            Some(f) => Family::Name(f),
+            None => Family::SansSerif,
+        },
+    };
+    Font {
+        family,
+        ..Default::default()
+    }
+}
+
+/// Reading colours, independent of the app theme unless set to follow it
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReaderTheme {
+    #[default]
+    System,
+    Light,
+    Sepia,
+    Dark,
+    Black,
+}
+
+impl ReaderTheme {
+    pub const ALL: [ReaderTheme; 5] = [
+        ReaderTheme::System,
+        ReaderTheme::Light,
+        ReaderTheme::Sepia,
+        ReaderTheme::Dark,
+        ReaderTheme::Black,
+    ];
+    pub fn labels() -> &'static [String] {
+        static LABELS: Lazy<Vec<String>> = Lazy::new(|| {
+            vec![
+                fl!("reader-theme-system"),
+                fl!("reader-theme-light"),
+                fl!("reader-theme-sepia"),
+                fl!("reader-theme-dark"),
+                fl!("reader-theme-black"),
+            ]
+        });
+        &LABELS
+    }
+
+    /// Background and text colours, `None` follows the app theme
+    pub fn colors(&self) -> Option<(Color, Color)> {
+        match self {
+            Self::System => None,
+            Self::Light => Some((
+                Color::from_rgb8(0xfa, 0xfa, 0xfa),
+                Color::from_rgb8(0x1e, 0x1e, 0x1e),
+            )),
+            Self::Sepia => Some((
+                Color::from_rgb8(0xf4, 0xec, 0xd8),
+                Color::from_rgb8(0x5b, 0x46, 0x36),
+            )),
+            Self::Dark => Some((
+                Color::from_rgb8(0x24, 0x24, 0x24),
+                Color::from_rgb8(0xdc, 0xdc, 0xdc),
+            )),
+            Self::Black => Some((Color::BLACK, Color::from_rgb8(0xb4, 0xb4, 0xb4))),
+        }
+    }
+}
+
+/// One long scroll per chapter, or screen-sized pages
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReadingMode {
+    #[default]
+    Scroll,
+    Paged,
+}
+
+impl ReadingMode {
+    pub const ALL: [ReadingMode; 2] = [ReadingMode::Scroll, ReadingMode::Paged];
+    pub fn labels() -> &'static [String] {
+        static LABELS: Lazy<Vec<String>> =
+            Lazy::new(|| vec![fl!("reading-mode-scroll"), fl!("reading-mode-paged")]);
+        &LABELS
+    }
+}
+
+/// Order of the library, newest or most first except for the names
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LibrarySort {
+    #[default]
+    DateAdded,
+    Title,
+    LastRead,
+    LastUpdated,
+    Unread,
+    Source,
+}
+
+impl LibrarySort {
+    pub const ALL: [LibrarySort; 6] = [
+        LibrarySort::DateAdded,
+        LibrarySort::Title,
+        LibrarySort::LastRead,
+        LibrarySort::LastUpdated,
+        LibrarySort::Unread,
+        LibrarySort::Source,
+    ];
+    pub fn labels() -> &'static [String] {
+        static LABELS: Lazy<Vec<String>> = Lazy::new(|| {
+            vec![
+                fl!("sort-date-added"),
+                fl!("sort-title"),
+                fl!("sort-last-read"),
+                fl!("sort-last-updated"),
+                fl!("sort-unread"),
+                fl!("sort-source"),
+            ]
+        });
+        &LABELS
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LibraryView {
+    #[default]
+    CoverGrid,
+    CompactGrid,
+    List,
+}
+
+impl LibraryView {
+    pub const ALL: [LibraryView; 3] = [
+        LibraryView::CoverGrid,
+        LibraryView::CompactGrid,
+        LibraryView::List,
+    ];
+    pub fn labels() -> &'static [String] {
+        static LABELS: Lazy<Vec<String>> = Lazy::new(|| {
+            vec![
+                fl!("library-view-covers"),
+                fl!("library-view-compact"),
+                fl!("library-view-list"),
+            ]
+        });
+        &LABELS
+    }
+}
+
+/// Which library books are shown, every set condition has to hold
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LibraryFilter {
+    /// Books with chapters left to read
+    pub unread: bool,
+    /// Books with at least one chapter downloaded
+    pub downloaded: bool,
+    /// Books the source lists as completed
+    pub completed: bool,
+    pub source: Option<String>,
+    pub tag: Option<String>,
+    pub category: Option<String>,
+}
+
+impl LibraryFilter {
+    pub fn matches(&self, book: &Book, stats: &LibraryStats) -> bool {
+        (!self.unread || stats.unread > 0)
+            && (!self.downloaded || stats.downloaded > 0)
+            && (!self.completed || book.status.as_deref() == Some("completed"))
+            && self.source.as_ref().map_or(true, |s| *s == book.source)
+            && self.tag.as_ref().map_or(true, |t| book.tags.contains(t))
+            && self
+                .category
+                .as_ref()
+                .map_or(true, |c| book.category.as_ref() == Some(c))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == LibraryFilter::default()
+    }
+}
+
+/// Line height presets offered in the settings
+pub const LINE_HEIGHTS: [f32; 4] = [1.2, 1.5, 1.8, 2.0];
+pub const LINE_HEIGHT_LABELS: [&str; 4] = ["1.2", "1.5", "1.8", "2.0"];
+
+/// Library update interval presets in hours
+pub const UPDATE_INTERVALS: [u32; 5] = [0, 1, 6, 12, 24];
+
+/// Names of [`UPDATE_INTERVALS`] in the same order, for pickers
+pub fn update_interval_labels() -> &'static [String] {
+    static LABELS: Lazy<Vec<String>> = Lazy::new(|| {
+        UPDATE_INTERVALS
+            .into_iter()
+            .map(|hours| match hours {
+                0 => fl!("update-interval-never"),
+                24 => fl!("update-interval-daily"),
+                hours => fl!("update-interval-hours", hours = hours),
+            })
+            .collect()
+    });
+    &LABELS
+}
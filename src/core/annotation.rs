@@ -0,0 +1,326 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::fmt::Write as _;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use super::{file_name, Book, Result};
+
+/// Words of a paragraph kept in its fingerprint
+const FINGERPRINT_WORDS: usize = 12;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum AnnotationKind {
+    #[default]
+    Bookmark,
+    Highlight,
+}
+
+impl AnnotationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AnnotationKind::Bookmark => "bookmark",
+            AnnotationKind::Highlight => "highlight",
+        }
+    }
+
+    pub fn from_name(kind: &str) -> AnnotationKind {
+        match kind {
+            "highlight" => AnnotationKind::Highlight,
+            _ => AnnotationKind::Bookmark,
+        }
+    }
+}
+
+/// A bookmark or a highlighted paragraph or passage, anchored so it can be
+/// found again after the chapter is downloaded anew and paragraphs moved
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Annotation {
+    /// Row id, 0 until stored
+    pub id: i64,
+    pub book_url: String,
+    pub chapter_url: String,
+    pub chapter_name: Option<String>,
+    pub kind: AnnotationKind,
+    /// Paragraph index when the annotation was made
+    pub paragraph: usize,
+    /// See [`fingerprint`]
+    pub fingerprint: String,
+    /// The paragraph's text when the annotation was made
+    pub text: String,
+    /// Highlighted words of the paragraph, separated by single spaces. Empty
+    /// when the whole paragraph is highlighted
+    pub passage: String,
+    pub note: String,
+    /// Seconds since the epoch
+    pub created: i64,
+}
+
+impl Annotation {
+    pub fn new(
+        book_url: String,
+        chapter_url: String,
+        chapter_name: Option<String>,
+        kind: AnnotationKind,
+        paragraph: usize,
+        text: String,
+        note: String,
+    ) -> Annotation {
+        Annotation {
+            id: 0,
+            book_url,
+            chapter_url,
+            chapter_name,
+            kind,
+            paragraph,
+            fingerprint: fingerprint(&text),
+            text,
+            passage: String::new(),
+            note,
+            created: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0),
+        }
+    }
+
+    /// Narrows the annotation to a passage of its paragraph. A passage that
+    /// isn't in the paragraph, or is all of it, keeps the whole paragraph
+    pub fn with_passage(mut self, passage: &str) -> Annotation {
+        let wanted = passage.split_whitespace().collect::<Vec<&str>>();
+        let words = self.text.split_whitespace().collect::<Vec<&str>>();
+        self.passage = match find_words(&words, &wanted) {
+            Some(_) if wanted.len() < words.len() => wanted.join(" "),
+            _ => String::new(),
+        };
+        self
+    }
+
+    /// Where the annotated paragraph is now: the stored index if the text
+    /// still matches, otherwise the matching paragraph closest to it. A
+    /// passage whose paragraph was edited is found by its own words
+    pub fn locate(&self, paragraphs: &[String]) -> Option<usize> {
+        let matches = |i: &usize| {
+            paragraphs
+                .get(*i)
+                .is_some_and(|p| fingerprint(p) == self.fingerprint)
+        };
+        if matches(&self.paragraph) {
+            return Some(self.paragraph);
+        }
+
+        let closest = |found: &dyn Fn(&usize) -> bool| {
+            (0..paragraphs.len())
+                .filter(found)
+                .min_by_key(|i| i.abs_diff(self.paragraph))
+        };
+        closest(&matches).or_else(|| {
+            let passage = self.passage.split_whitespace().collect::<Vec<&str>>();
+            closest(&|i| {
+                let words = paragraphs[*i].split_whitespace().collect::<Vec<&str>>();
+                find_words(&words, &passage).is_some()
+            })
+        })
+    }
+
+    /// Words of `paragraph` the annotation covers, by their index: the
+    /// passage where it is still found, otherwise all of them
+    pub fn words(&self, paragraph: &str) -> Range<usize> {
+        let words = paragraph.split_whitespace().collect::<Vec<&str>>();
+        let passage = self.passage.split_whitespace().collect::<Vec<&str>>();
+        match find_words(&words, &passage) {
+            Some(start) => start..start + passage.len(),
+            None => 0..words.len(),
+        }
+    }
+}
+
+/// Index of the first run of `wanted` in `words`, never found when empty
+fn find_words(words: &[&str], wanted: &[&str]) -> Option<usize> {
+    if wanted.is_empty() {
+        return None;
+    }
+    words.windows(wanted.len()).position(|run| run == wanted)
+}
+
+/// The first words of a paragraph, lowercased without punctuation, so
+/// whitespace and typography fixes on the site don't lose the anchor
+pub fn fingerprint(text: &str) -> String {
+    text.split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_alphanumeric())
+                .flat_map(char::to_lowercase)
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .take(FINGERPRINT_WORDS)
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// The book's highlights and bookmarks as Markdown, grouped by chapter in
+/// the order given
+pub fn markdown(book: &Book, annotations: &[Annotation]) -> String {
+    let mut text = format!("# {}\n\n<{}>\n", book.name, book.url);
+
+    let mut chapter = None;
+    for annotation in annotations {
+        if chapter != Some(&annotation.chapter_url) {
+            chapter = Some(&annotation.chapter_url);
+            let name = annotation
+                .chapter_name
+                .as_deref()
+                .unwrap_or(&annotation.chapter_url);
+            _ = write!(text, "\n## [{name}]({})\n", annotation.chapter_url);
+        }
+
+        text.push('\n');
+        match annotation.kind {
+            AnnotationKind::Highlight => {
+                let quoted = match annotation.passage.is_empty() {
+                    true => &annotation.text,
+                    false => &annotation.passage,
+                };
+                for line in quoted.lines() {
+                    _ = writeln!(text, "> {line}");
+                }
+            }
+            AnnotationKind::Bookmark => {
+                _ = writeln!(text, "Bookmark at paragraph {}", annotation.paragraph + 1);
+            }
+        }
+        if !annotation.note.is_empty() {
+            _ = writeln!(text, "\n{}", annotation.note);
+        }
+    }
+
+    text
+}
+
+/// Writes [`markdown`] for the book into `dir`, returning the file's path
+pub async fn export(book: &Book, annotations: &[Annotation], dir: &Path) -> Result<PathBuf> {
+    tokio::fs::create_dir_all(dir).await?;
+    let path = dir.join(format!("{} - Notes.md", file_name(&book.name, "notes")));
+    tokio::fs::write(&path, markdown(book, annotations)).await?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapter(paragraphs: &[&str]) -> Vec<String> {
+        paragraphs.iter().map(|p| p.to_string()).collect()
+    }
+
+    fn highlight(paragraph: usize, text: &str) -> Annotation {
+        Annotation::new(
+            "https://example.com/book".into(),
+            "https://example.com/book/1".into(),
+            None,
+            AnnotationKind::Highlight,
+            paragraph,
+            text.into(),
+            String::new(),
+        )
+    }
+
+    #[test]
+    fn fingerprint_ignores_case_punctuation_and_spacing() {
+        assert_eq!(
+            fingerprint("“Hello,”  she said —\nquietly."),
+            fingerprint("hello she said quietly")
+        );
+        assert_eq!(fingerprint("— …"), "");
+    }
+
+    #[test]
+    fn unchanged_paragraph_stays() {
+        let paragraphs = chapter(&["one", "two", "three"]);
+        assert_eq!(highlight(1, "two").locate(&paragraphs), Some(1));
+    }
+
+    #[test]
+    fn edited_paragraph_stays_when_its_opening_survives() {
+        let opening = "It was late when the last of the guests finally found their coats and";
+        let paragraphs = chapter(&["one", &format!("{opening} left."), "three"]);
+        assert_eq!(
+            highlight(1, &format!("{opening}  went home.")).locate(&paragraphs),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn reordered_paragraph_is_followed() {
+        let paragraphs = chapter(&["three", "one", "two"]);
+        assert_eq!(highlight(1, "two").locate(&paragraphs), Some(2));
+    }
+
+    #[test]
+    fn repeated_paragraph_picks_the_closest() {
+        let paragraphs = chapter(&["Meanwhile.", "one", "two", "Meanwhile.", "three"]);
+        assert_eq!(highlight(4, "Meanwhile.").locate(&paragraphs), Some(3));
+        assert_eq!(highlight(1, "Meanwhile.").locate(&paragraphs), Some(0));
+    }
+
+    #[test]
+    fn removed_paragraph_is_lost() {
+        let paragraphs = chapter(&["one", "three"]);
+        assert_eq!(highlight(1, "two").locate(&paragraphs), None);
+    }
+
+    #[test]
+    fn passage_finds_its_paragraph_after_the_opening_changed() {
+        let paragraphs = chapter(&[
+            "one",
+            "Rewritten start, then the bit worth keeping and more.",
+        ]);
+        let annotation = highlight(1, "Old start, then the bit worth keeping and more.")
+            .with_passage("the bit worth keeping");
+        assert_eq!(annotation.locate(&paragraphs), Some(1));
+        assert_eq!(annotation.words(&paragraphs[1]), 3..7);
+    }
+
+    #[test]
+    fn passage_must_be_part_of_the_paragraph() {
+        let text = "The quick brown fox jumps over the lazy dog.";
+        assert_eq!(
+            highlight(0, text)
+                .with_passage(" brown\n fox  jumps ")
+                .passage,
+            "brown fox jumps"
+        );
+        // All of it, words that aren't there and half words leave the whole paragraph
+        for passage in [text, "red fox", "row", ""] {
+            assert_eq!(highlight(0, text).with_passage(passage).passage, "");
+        }
+    }
+
+    #[test]
+    fn passage_gone_from_the_paragraph_covers_all_of_it() {
+        let annotation = highlight(0, "a b c d").with_passage("b c");
+        assert_eq!(annotation.words("a b c d"), 1..3);
+        assert_eq!(annotation.words("a b x c d"), 0..5);
+        assert_eq!(highlight(0, "a b c d").words("a b c d"), 0..4);
+    }
+
+    #[test]
+    fn markdown_quotes_the_passage() {
+        let book = Book::new(
+            "source".into(),
+            "https://example.com/book".into(),
+            "Book".into(),
+            None,
+            false,
+        );
+        let annotations = [
+            highlight(0, "whole paragraph"),
+            highlight(1, "only part of this").with_passage("part of"),
+        ];
+        let text = markdown(&book, &annotations);
+        assert!(text.contains("> whole paragraph\n"));
+        assert!(text.contains("> part of\n"));
+        assert!(!text.contains("only part of this"));
+    }
+}
@@ -0,0 +1,375 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Renders chapters to an audiobook file: each chapter is synthesized to WAV
+//! and ffmpeg joins them into one Opus or MP3 file with chapter markers
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+use super::data::DataManager;
+use super::source;
+use super::tts::{self, Voice};
+use super::{file_name, Book, Chapter, Error, Result};
+
+const ENCODER: &str = "ffmpeg";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AudioFormat {
+    #[default]
+    Opus,
+    Mp3,
+}
+
+impl AudioFormat {
+    pub const ALL: [AudioFormat; 2] = [AudioFormat::Opus, AudioFormat::Mp3];
+    pub const LABELS: [&'static str; 2] = ["Opus", "MP3"];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AudioFormat::Opus => "opus",
+            AudioFormat::Mp3 => "mp3",
+        }
+    }
+
+    /// Codec arguments, bitrates are plenty for a single voice
+    fn encoder_args(&self) -> &'static [&'static str] {
+        match self {
+            AudioFormat::Opus => &["-c:a", "libopus", "-b:a", "32k"],
+            AudioFormat::Mp3 => &["-c:a", "libmp3lame", "-q:a", "6", "-id3v2_version", "3"],
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<AudioFormat> {
+        AudioFormat::ALL
+            .into_iter()
+            .find(|f| f.extension().eq_ignore_ascii_case(name))
+    }
+}
+
+/// A book or a run of its chapters to render
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioExport {
+    pub book: Book,
+    pub chapters: Vec<Chapter>,
+    pub format: AudioFormat,
+    /// Engine by [`tts::SpeechBackend::as_str`], swapped for one that can
+    /// write files if needed
+    pub backend: String,
+    pub voice: Voice,
+    /// Directory the file is written to
+    pub dest: PathBuf,
+}
+
+impl AudioExport {
+    /// Speaks and encodes the chapters, reading their text from `data` where
+    /// it is stored and downloading the rest. `progress` hears how many
+    /// chapters are done out of how many
+    #[tracing::instrument(skip_all, fields(book = %self.book.url, chapters = self.chapters.len()), err)]
+    pub async fn run(
+        &self,
+        data: Option<Arc<DataManager>>,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Result<PathBuf> {
+        let total = self.chapters.len();
+        if total == 0 {
+            return Err(Error::NotFound("chapters to export".into()));
+        }
+
+        // Removed when dropped, also when the job is cancelled mid-way
+        let work = tempfile::Builder::new()
+            .prefix(concat!(env!("CARGO_PKG_NAME"), "-audio-"))
+            .tempdir()?;
+        self.render(work.path(), data, &mut progress).await
+    }
+
+    async fn render(
+        &self,
+        work: &Path,
+        data: Option<Arc<DataManager>>,
+        progress: &mut impl FnMut(usize, usize),
+    ) -> Result<PathBuf> {
+        let backend = tts::Backends::default().for_synthesis(&self.backend);
+        let source = source::for_url(&self.book.url)?;
+        let total = self.chapters.len();
+
+        let mut parts = vec![];
+        progress(0, total);
+        for (i, chapter) in self.chapters.iter().enumerate() {
+            let content = match stored_content(data.clone(), chapter).await {
+                Some(content) => content,
+                None => source.download_chapter(chapter).await?,
+            };
+            let text = match &chapter.name {
+                Some(name) => format!("{name}.\n\n{content}"),
+                None => content,
+            };
+
+            let wav = work.join(format!("{i:05}.wav"));
+            backend.synthesize(&text, &self.voice, &wav).await?;
+            let duration = wav_duration(&tokio::fs::read(&wav).await?)?;
+            parts.push((wav, duration, chapter));
+            progress(i + 1, total);
+        }
+
+        let list = work.join("parts.txt");
+        let mut list_text = String::new();
+        for (wav, _, _) in &parts {
+            _ = writeln!(
+                list_text,
+                "file '{}'",
+                wav.display().to_string().replace('\'', "'\\''")
+            );
+        }
+        tokio::fs::write(&list, list_text).await?;
+
+        let metadata = work.join("metadata.txt");
+        tokio::fs::write(&metadata, self.metadata(&parts)).await?;
+
+        tokio::fs::create_dir_all(&self.dest).await?;
+        let out = self.dest.join(format!(
+            "{}.{}",
+            file_name(&self.book.name, "audiobook"),
+            self.format.extension()
+        ));
+
+        let mut command = Command::new(ENCODER);
+        command
+            .args([
+                "-y",
+                "-loglevel",
+                "error",
+                "-f",
+                "concat",
+                "-safe",
+                "0",
+                "-i",
+            ])
+            .arg(&list)
+            .arg("-i")
+            .arg(&metadata)
+            .args(["-map", "0:a", "-map_metadata", "1", "-map_chapters", "1"])
+            .args(self.format.encoder_args())
+            .arg(&out)
+            .kill_on_drop(true);
+        let output = command
+            .output()
+            .await
+            .map_err(|e| Error::Speech(format!("{ENCODER}: {e}")))?;
+        if !output.status.success() {
+            return Err(Error::Speech(format!(
+                "{ENCODER} failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        Ok(out)
+    }
+
+    /// Tags from the book and a chapter marker per part, in ffmpeg's metadata format
+    fn metadata(&self, parts: &[(PathBuf, Duration, &Chapter)]) -> String {
+        let book = &self.book;
+        let mut text = String::from(";FFMETADATA1\n");
+        _ = writeln!(text, "title={}", escape(&book.name));
+        _ = writeln!(text, "album={}", escape(&book.name));
+        _ = writeln!(text, "publisher={}", escape(&book.source));
+        if !book.tags.is_empty() {
+            _ = writeln!(text, "genre={}", escape(&book.tags.join(", ")));
+        }
+        if let Some(description) = &book.description {
+            _ = writeln!(text, "comment={}", escape(description));
+        }
+
+        let mut start = Duration::ZERO;
+        for (i, (_, duration, chapter)) in parts.iter().enumerate() {
+            let end = start + *duration;
+            let title = chapter
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("Chapter {}", i + 1));
+            _ = write!(
+                text,
+                "\n[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n",
+                start.as_millis(),
+                end.as_millis(),
+                escape(&title)
+            );
+            start = end;
+        }
+        text
+    }
+}
+
+/// The chapter's downloaded text, if it was stored
+async fn stored_content(data: Option<Arc<DataManager>>, chapter: &Chapter) -> Option<String> {
+    let (data, url) = (data?, chapter.url.clone()?);
+    tokio::task::spawn_blocking(move || data.get_chapter_content(&url))
+        .await
+        .ok()?
+        .ok()
+        .flatten()
+}
+
+/// Escapes the characters ffmpeg's metadata format treats specially
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Length of a PCM WAV file from its `fmt ` and `data` chunks
+fn wav_duration(bytes: &[u8]) -> Result<Duration> {
+    let invalid = || Error::Speech("unreadable WAV file".into());
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(invalid());
+    }
+
+    let mut byte_rate = None;
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let id = &bytes[offset..offset + 4];
+        let size = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into().unwrap()) as usize;
+        let body = offset + 8;
+        match id {
+            b"fmt " if body + 12 <= bytes.len() => {
+                byte_rate = Some(u32::from_le_bytes(
+                    bytes[body + 8..body + 12].try_into().unwrap(),
+                ));
+            }
+            b"data" => {
+                let rate = byte_rate.filter(|r| *r > 0).ok_or_else(invalid)?;
+                // Streamed output can leave the size at the maximum, trust the file length
+                let size = size.min(bytes.len() - body);
+                return Ok(Duration::from_secs_f64(size as f64 / f64::from(rate)));
+            }
+            _ => (),
+        }
+        // Chunks are padded to an even length
+        offset = body + size + (size & 1);
+    }
+    Err(invalid())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A RIFF chunk, padded to an even length
+    fn chunk(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut chunk = id.to_vec();
+        chunk.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(body);
+        if body.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+
+    /// 16 bit mono PCM at 8 kHz, 16000 bytes a second
+    fn fmt() -> Vec<u8> {
+        let mut body = Vec::new();
+        body.extend_from_slice(&1u16.to_le_bytes());
+        body.extend_from_slice(&1u16.to_le_bytes());
+        body.extend_from_slice(&8000u32.to_le_bytes());
+        body.extend_from_slice(&16000u32.to_le_bytes());
+        body.extend_from_slice(&2u16.to_le_bytes());
+        body.extend_from_slice(&16u16.to_le_bytes());
+        chunk(b"fmt ", &body)
+    }
+
+    fn wav(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let body = chunks.concat();
+        let mut wav = b"RIFF".to_vec();
+        wav.extend_from_slice(&(body.len() as u32 + 4).to_le_bytes());
+        wav.extend_from_slice(b"WAVE");
+        wav.extend_from_slice(&body);
+        wav
+    }
+
+    #[test]
+    fn duration_comes_from_the_byte_rate_and_data_size() {
+        let bytes = wav(&[fmt(), chunk(b"data", &[0; 32000])]);
+        assert_eq!(wav_duration(&bytes).unwrap(), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn odd_sized_chunks_are_skipped_with_their_padding() {
+        let bytes = wav(&[
+            chunk(b"LIST", b"abc"),
+            fmt(),
+            chunk(b"junk", &[0; 7]),
+            chunk(b"data", &[0; 8000]),
+        ]);
+        assert_eq!(wav_duration(&bytes).unwrap(), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn streamed_data_size_is_taken_from_the_file_length() {
+        let mut bytes = wav(&[fmt()]);
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        bytes.extend_from_slice(&[0; 4000]);
+        assert_eq!(wav_duration(&bytes).unwrap(), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn unreadable_files_are_errors() {
+        assert!(wav_duration(b"").is_err());
+        assert!(wav_duration(b"RIFF\0\0\0\0AVI ").is_err());
+        // No format to tell the byte rate
+        assert!(wav_duration(&wav(&[chunk(b"data", &[0; 100])])).is_err());
+        // No data
+        assert!(wav_duration(&wav(&[fmt()])).is_err());
+    }
+
+    #[test]
+    fn escapes_metadata_syntax() {
+        assert_eq!(escape("plain text"), "plain text");
+        assert_eq!(escape("a=b;c#d\\e\nf"), "a\\=b\\;c\\#d\\\\e\\\nf");
+    }
+
+    #[test]
+    fn metadata_has_tags_and_chapter_markers() {
+        let export = AudioExport {
+            book: Book {
+                name: "Book=1".into(),
+                source: "RoyalRoad".into(),
+                tags: vec!["Fantasy".into(), "LitRPG".into()],
+                description: Some("First line\n#Second; line".into()),
+                ..Default::default()
+            },
+            chapters: vec![],
+            format: AudioFormat::Opus,
+            backend: String::new(),
+            voice: Voice::default(),
+            dest: PathBuf::new(),
+        };
+        let first = Chapter::new(Some(1), Some("Part 1: A=B".into()), None);
+        let second = Chapter::new(Some(2), None, None);
+        let parts = [
+            (PathBuf::from("1.wav"), Duration::from_millis(1500), &first),
+            (PathBuf::from("2.wav"), Duration::from_secs(2), &second),
+        ];
+
+        assert_eq!(
+            export.metadata(&parts),
+            ";FFMETADATA1\n\
+             title=Book\\=1\n\
+             album=Book\\=1\n\
+             publisher=RoyalRoad\n\
+             genre=Fantasy, LitRPG\n\
+             comment=First line\\\n\\#Second\\; line\n\
+             \n[CHAPTER]\nTIMEBASE=1/1000\nSTART=0\nEND=1500\ntitle=Part 1: A\\=B\n\
+             \n[CHAPTER]\nTIMEBASE=1/1000\nSTART=1500\nEND=3500\ntitle=Chapter 2\n"
+        );
+    }
+}
@@ -1,8 +1,8 @@
 use async_trait::async_trait;
 
-use crate::core::Chapter;
+use crate::core::{source, Chapter};
 
-use super::Book;
+use super::{Book, Error, Result};
 
 impl Book {
     pub fn new(
@@ -18,13 +18,20 @@ impl Book {
             image,
             name,
             in_library,
+            ..Default::default()
         }
     }
 
-    pub async fn download_cover(&self) -> Result<bytes::Bytes, Box<dyn std::error::Error>> {
-        let url = self.image.clone().ok_or("No image url")?;
-        let response = reqwest::get(url).await?;
-        let content = response.bytes().await?;
-        Ok(content)
+    /// The key the book is stored under, see [`source::book_id`]
+    pub fn id(&self) -> String {
+        source::book_id(&self.url)
+    }
+
+    pub async fn download_cover(&self) -> Result<bytes::Bytes> {
+        let url = self
+            .image
+            .clone()
+            .ok_or(Error::NotFound(format!("cover for {}", self.url)))?;
+        Ok(crate::core::http::CACHE.get_image(&url).await?)
     }
 }
@@ -4,6 +4,11 @@ use super::Chapter;
 
 impl Chapter {
     pub fn new(number: Option<u32>, name: Option<String>, url: Option<String>) -> Chapter {
-        Chapter { number, name, url }
+        Chapter {
+            number,
+            name,
+            url,
+            released: None,
+        }
     }
 }
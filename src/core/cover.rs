@@ -0,0 +1,209 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Covers are decoded once when they are downloaded and kept at two sizes,
+//! a small one for grid cards and a larger one for the book drawer
+
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use cosmic::widget::image::Handle;
+use image::codecs::jpeg::JpegEncoder;
+use image::DynamicImage;
+
+use super::{Error, Result};
+
+/// JPEG quality both sizes are stored at
+const QUALITY: u8 = 85;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CoverSize {
+    /// Grid cards in the library and explore pages
+    Thumbnail,
+    /// The book drawer
+    Detail,
+}
+
+impl CoverSize {
+    /// Box the image is scaled down to fit, in pixels
+    fn bounds(&self) -> (u32, u32) {
+        match self {
+            CoverSize::Thumbnail => (200, 300),
+            CoverSize::Detail => (480, 720),
+        }
+    }
+}
+
+/// A cover re-encoded at both sizes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cover {
+    pub thumbnail: bytes::Bytes,
+    pub detail: bytes::Bytes,
+}
+
+impl Cover {
+    /// Decodes a downloaded image and encodes it at both sizes, failing for
+    /// anything that isn't a readable image
+    #[tracing::instrument(skip_all, fields(len = bytes.len()), err(level = "warn"))]
+    pub fn process(bytes: &[u8]) -> Result<Cover> {
+        let image = image::load_from_memory(bytes).map_err(|e| Error::Image(e.to_string()))?;
+        Ok(Cover {
+            thumbnail: encode(&image, CoverSize::Thumbnail)?,
+            detail: encode(&image, CoverSize::Detail)?,
+        })
+    }
+
+    pub fn get(&self, size: CoverSize) -> &bytes::Bytes {
+        match size {
+            CoverSize::Thumbnail => &self.thumbnail,
+            CoverSize::Detail => &self.detail,
+        }
+    }
+
+    /// Size in memory, for cache budgets
+    pub fn byte_size(&self) -> usize {
+        self.thumbnail.len() + self.detail.len()
+    }
+}
+
+/// Scales the image down to fit the size, never up, and encodes it as JPEG
+fn encode(image: &DynamicImage, size: CoverSize) -> Result<bytes::Bytes> {
+    let (width, height) = size.bounds();
+    let image = match image.width() > width || image.height() > height {
+        true => image.thumbnail(width, height),
+        false => image.clone(),
+    };
+
+    let mut out = Cursor::new(vec![]);
+    JpegEncoder::new_with_quality(&mut out, QUALITY)
+        .encode_image(&image.to_rgb8())
+        .map_err(|e| Error::Image(e.to_string()))?;
+    Ok(bytes::Bytes::from(out.into_inner()))
+}
+
+/// Counters for the diagnostics section of the settings
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+    pub bytes: usize,
+    pub budget: usize,
+}
+
+/// Ready-to-draw cover handles, the least recently used go first once the
+/// encoded bytes they hold pass the budget. Reusing a handle also lets the
+/// renderer keep the decoded image instead of decoding it every frame
+#[derive(Debug)]
+pub struct CoverCache {
+    budget: usize,
+    used: usize,
+    /// Bumped on every access, entries remember when they were last used
+    clock: u64,
+    entries: HashMap<(String, CoverSize), CacheEntry>,
+    hits: u64,
+    misses: u64,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    handle: Handle,
+    bytes: usize,
+    used: u64,
+}
+
+impl Default for CoverCache {
+    fn default() -> Self {
+        CoverCache::new(CoverCache::DEFAULT_BUDGET)
+    }
+}
+
+impl CoverCache {
+    pub const DEFAULT_BUDGET: usize = 32 * 1024 * 1024;
+
+    pub fn new(budget: usize) -> CoverCache {
+        CoverCache {
+            budget,
+            used: 0,
+            clock: 0,
+            entries: HashMap::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub fn get(&mut self, url: &str, size: CoverSize) -> Option<Handle> {
+        self.clock += 1;
+        match self.entries.get_mut(&(url.to_owned(), size)) {
+            Some(entry) => {
+                self.hits += 1;
+                entry.used = self.clock;
+                Some(entry.handle.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Looks without counting or refreshing the entry, for views
+    pub fn peek(&self, url: &str, size: CoverSize) -> Option<Handle> {
+        self.entries
+            .get(&(url.to_owned(), size))
+            .map(|entry| entry.handle.clone())
+    }
+
+    /// Adds or replaces a handle, `bytes` is what it costs against the budget
+    pub fn insert(&mut self, url: &str, size: CoverSize, handle: Handle, bytes: usize) {
+        self.clock += 1;
+        let entry = CacheEntry {
+            handle,
+            bytes,
+            used: self.clock,
+        };
+        if let Some(old) = self.entries.insert((url.to_owned(), size), entry) {
+            self.used -= old.bytes;
+        }
+        self.used += bytes;
+
+        // The entry just added stays even if it alone is over the budget
+        while self.used > self.budget && self.entries.len() > 1 {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&oldest) {
+                tracing::trace!("evicted cover {}", oldest.0);
+                self.used -= entry.bytes;
+            }
+        }
+    }
+
+    /// Drops both sizes of a book's cover
+    pub fn remove(&mut self, url: &str) {
+        for size in [CoverSize::Thumbnail, CoverSize::Detail] {
+            if let Some(entry) = self.entries.remove(&(url.to_owned(), size)) {
+                self.used -= entry.bytes;
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.used = 0;
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            entries: self.entries.len(),
+            bytes: self.used,
+            budget: self.budget,
+        }
+    }
+}
@@ -1,47 +1,81 @@
+use crate::core::cover::Cover;
+use crate::core::pool::{Pool, PooledConnection};
 use crate::core::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
 
+/// Books are keyed by [`source::book_id`], so every form of a book's url
+/// finds the same rows. Shared by every task using storage, each call runs
+/// on its own pooled connection
 #[derive(Default)]
 pub struct DataManager {
-    storage_path: std::path::PathBuf,
-    books: HashMap<String, Book>,                   // book url, book
-    pub book_covers: HashMap<String, bytes::Bytes>, // book url, bytes
+    books: Mutex<HashMap<String, Book>>, // book id, book
+    /// Open once `init` succeeds, only locked to hand out or swap the pool
+    pool: RwLock<Option<Arc<Pool>>>,
 }
 
 impl DataManager {
     const STORAGE_FILE: &'static str = "data.db";
+    const BOOK_COLUMNS: &'static str =
+        "source, book_url, name, image_url, in_library, tags, description, partial, status, category, author";
 
     pub fn new() -> DataManager {
         DataManager::default()
     }
 
-    pub fn clear_all(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        if self.storage_path.join(Self::STORAGE_FILE).exists() {
-            if let Err(e) = std::fs::remove_file(self.storage_path.join(Self::STORAGE_FILE)) {
-                return Err(e.into());
+    #[tracing::instrument(skip(self), err)]
+    pub fn clear_all(&self) -> Result<()> {
+        // Close the idle connections before the files go
+        let pool = self
+            .pool
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+            .ok_or_else(|| Error::Storage("storage isn't initialised".into()))?;
+        let dir = pool
+            .path()
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        drop(pool);
+        self.books.lock().unwrap_or_else(|e| e.into_inner()).clear();
+
+        for suffix in ["", "-wal", "-shm"] {
+            let path = dir.join(format!("{}{suffix}", Self::STORAGE_FILE));
+            if path.exists() {
+                std::fs::remove_file(path)?;
             }
         }
-        let res = self.init(self.storage_path.clone());
-        if !res.is_empty() {
-            return Err("Failed to reinit storage".into());
+        let mut errors = self.init(dir);
+        if !errors.is_empty() {
+            return Err(errors.remove(0));
         }
         Ok(())
     }
 
-    pub fn init(&mut self, dir: std::path::PathBuf) -> Vec<Box<dyn std::error::Error>> {
-        self.storage_path = dir;
-
-        if !self.storage_path.exists() {
-            if let Err(e) = std::fs::create_dir(&self.storage_path) {
+    #[tracing::instrument(skip(self))]
+    pub fn init(&self, dir: PathBuf) -> Vec<Error> {
+        if !dir.exists() {
+            if let Err(e) = std::fs::create_dir(&dir) {
                 return vec![e.into()];
             };
         }
 
-        let mut errors = vec![];
-        match rusqlite::Connection::open(self.storage_path.join(Self::STORAGE_FILE)) {
-            Ok(conn) => {
-                let mut errors = vec![];
+        match Pool::open(&dir.join(Self::STORAGE_FILE)) {
+            Ok(pool) => {
+                *self.pool.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(pool))
+            }
+            Err(e) => {
+                tracing::error!("failed to initialise storage: {e}");
+                return vec![e];
+            }
+        }
 
+        let mut errors: Vec<Error> = vec![];
+        match self.conn() {
+            Ok(mut conn) => {
+                // Tables are created as first released, `migrate` brings them up to date
                 // Create books table
                 if let Err(e) = conn.execute(
                     "CREATE TABLE if not exists books (
@@ -50,12 +84,30 @@ impl DataManager {
                     name TEXT, 
                     book_url TEXT, 
                     image_url TEXT, 
-                    in_library BIT);",
+                    in_library BIT,
+                    tags TEXT,
+                    description TEXT,
+                    partial BIT);",
                     (),
                 ) {
-                    errors.push(e);
+                    errors.push(e.into());
                 };
 
+                // Columns added after the books table was first released
+                for (column, decl) in [
+                    ("tags", "TEXT"),
+                    ("description", "TEXT"),
+                    ("partial", "BIT"),
+                    ("status", "TEXT"),
+                    ("added", "INTEGER"),
+                    ("category", "TEXT"),
+                    ("author", "TEXT"),
+                ] {
+                    if let Err(e) = Self::add_column_if_missing(&conn, "books", column, decl) {
+                        errors.push(e.into());
+                    }
+                }
+
                 // Create thumbnails table
                 if let Err(e) = conn.execute(
                     "CREATE TABLE if not exists thumbnails (
@@ -64,9 +116,16 @@ impl DataManager {
                     image_data BLOB);",
                     (),
                 ) {
-                    errors.push(e);
+                    errors.push(e.into());
                 };
 
+                // Larger size for the book drawer, image_data holds the grid thumbnail
+                if let Err(e) =
+                    Self::add_column_if_missing(&conn, "thumbnails", "detail_data", "BLOB")
+                {
+                    errors.push(e.into());
+                }
+
                 // Create chapters table
                 if let Err(e) = conn.execute(
                     "CREATE TABLE if not exists chapters (
@@ -77,263 +136,883 @@ impl DataManager {
                     release_date TEXT);",
                     (),
                 ) {
-                    errors.push(e);
+                    errors.push(e.into());
+                };
+
+                // Create progress table, one row per book
+                if let Err(e) = conn.execute(
+                    "CREATE TABLE if not exists progress (
+                    book_url TEXT PRIMARY KEY,
+                    chapter_url TEXT,
+                    page INTEGER);",
+                    (),
+                ) {
+                    errors.push(e.into());
+                };
+                if let Err(e) = Self::add_column_if_missing(&conn, "progress", "updated", "INTEGER")
+                {
+                    errors.push(e.into());
+                }
+
+                // Create chapter_content table, downloaded text kept for offline reading
+                if let Err(e) = conn.execute(
+                    "CREATE TABLE if not exists chapter_content (
+                    chapter_url TEXT PRIMARY KEY,
+                    content TEXT,
+                    fetched INTEGER);",
+                    (),
+                ) {
+                    errors.push(e.into());
                 };
+
+                // Create read_chapters table, chapters finished or marked as read
+                if let Err(e) = conn.execute(
+                    "CREATE TABLE if not exists read_chapters (
+                    chapter_url TEXT PRIMARY KEY,
+                    read INTEGER);",
+                    (),
+                ) {
+                    errors.push(e.into());
+                };
+
+                // Create annotations table, bookmarks and highlights anchored to a paragraph
+                if let Err(e) = conn.execute(
+                    "CREATE TABLE if not exists annotations (
+                    id INTEGER PRIMARY KEY,
+                    book_url TEXT,
+                    chapter_url TEXT,
+                    chapter_name TEXT,
+                    kind TEXT,
+                    paragraph INTEGER,
+                    fingerprint TEXT,
+                    text TEXT,
+                    note TEXT,
+                    created INTEGER);",
+                    (),
+                ) {
+                    errors.push(e.into());
+                };
+                // Highlighted words of the paragraph, empty when it's all of it
+                if let Err(e) = Self::add_column_if_missing(&conn, "annotations", "passage", "TEXT")
+                {
+                    errors.push(e.into());
+                }
+
+                if let Err(e) = Self::migrate(&mut conn) {
+                    errors.push(e);
+                }
             }
-            Err(e) => errors.push(e.into()),
+            Err(e) => errors.push(e),
         };
+        for e in &errors {
+            tracing::error!("failed to initialise storage: {e}");
+        }
         errors
     }
 
-    pub fn get_book_from_storage(
-        &self,
-        url: String,
-    ) -> Result<Option<Book>, Box<dyn std::error::Error>> {
-        let conn = rusqlite::Connection::open(self.storage_path.join(Self::STORAGE_FILE))?;
-        let mut stmt = conn.prepare(
-            "SELECT source, book_url, name, image_url, in_library FROM books WHERE book_url = :url;",
-        )?;
+    /// A connection from the pool, errors before `init`
+    fn conn(&self) -> Result<PooledConnection> {
+        let pool = self.pool.read().unwrap_or_else(|e| e.into_inner()).clone();
+        pool.ok_or_else(|| Error::Storage("storage isn't initialised".into()))?
+            .get()
+    }
 
-        let book_iter = stmt.query_map(&[(":url", &url)], |row| {
-            Ok(Book::new(
-                row.get(0)?,
-                row.get(1)?,
-                row.get(2)?,
-                row.get(3)?,
-                row.get(4)?,
-            ))
-        })?;
+    #[tracing::instrument(skip(self), err(level = "warn"))]
+    pub fn get_book_from_storage(&self, url: String) -> Result<Option<Book>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM books WHERE book_id = :id;",
+            Self::BOOK_COLUMNS
+        ))?;
 
-        for book in book_iter {
-            let mut book = book.unwrap();
-            if book.image == Some("".into()) {
-                book.image = None;
-            }
+        let mut book_iter =
+            stmt.query_map(&[(":id", &source::book_id(&url))], Self::book_from_row)?;
+
+        match book_iter.next() {
+            Some(book) => Ok(Some(book?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_book(&self, url: &String) -> Result<Option<Book>> {
+        let cached = self
+            .books
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&source::book_id(url))
+            .cloned();
+        if let Some(book) = cached {
             return Ok(Some(book));
         }
 
-        Ok(None)
+        self.get_book_from_storage(url.clone())
+    }
+
+    /// Stores the book, replacing the row with the same id in one statement.
+    /// A renamed book keeps its row and takes the new url, and the time it
+    /// joined the library until it leaves
+    #[tracing::instrument(skip_all, fields(url = %book.url), err)]
+    pub fn set_book(&self, book: &Book) -> Result<()> {
+        let conn = self.conn()?;
+        conn.prepare_cached(
+            "INSERT INTO books (source, name, book_url, image_url, in_library, tags, description, partial, status, category, author, book_id, added)
+            values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, CASE WHEN ?5 = '1' THEN CAST(strftime('%s', 'now') AS INTEGER) END)
+            ON CONFLICT (book_id) DO UPDATE SET source = excluded.source, name = excluded.name, book_url = excluded.book_url, image_url = excluded.image_url, in_library = excluded.in_library, tags = excluded.tags, description = excluded.description, partial = excluded.partial, status = excluded.status, category = excluded.category, author = excluded.author,
+            added = CASE WHEN excluded.in_library = 0 THEN NULL ELSE COALESCE(books.added, CAST(strftime('%s', 'now') AS INTEGER)) END;",
+        )?
+        .execute(Self::book_params(book))?;
+
+        _ = self
+            .books
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(book.id(), book.clone());
+        Ok(())
     }
 
-    pub fn get_book(&self, url: &String) -> Result<Option<Book>, Box<dyn std::error::Error>> {
-        if let Some(book) = self.books.get(url) {
-            return Ok(Some(book.clone()));
+    #[tracing::instrument(skip(self), err)]
+    pub fn get_library_books(&self) -> Result<Vec<Book>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {} FROM books WHERE in_library = 1",
+            Self::BOOK_COLUMNS
+        ))?;
+
+        let books = stmt
+            .query_map([], Self::book_from_row)?
+            .collect::<rusqlite::Result<Vec<Book>>>()?;
+
+        Ok(books)
+    }
+
+    /// Reading state of every library book by book id, for sorting and
+    /// filtering the library
+    #[tracing::instrument(skip(self), err)]
+    pub fn get_library_stats(&self) -> Result<HashMap<String, LibraryStats>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT b.book_id, COALESCE(b.added, 0),
+                (SELECT MAX(r.read) FROM chapters c JOIN read_chapters r ON r.chapter_id = c.chapter_id WHERE c.book_id = b.book_id),
+                (SELECT p.updated FROM progress p WHERE p.book_id = b.book_id),
+                (SELECT MAX(c.release_date) FROM chapters c WHERE c.book_id = b.book_id),
+                (SELECT COUNT(*) FROM chapters c WHERE c.book_id = b.book_id),
+                (SELECT COUNT(*) FROM chapters c JOIN read_chapters r ON r.chapter_id = c.chapter_id WHERE c.book_id = b.book_id),
+                (SELECT COUNT(*) FROM chapters c JOIN chapter_content cc ON cc.chapter_id = c.chapter_id WHERE c.book_id = b.book_id)
+            FROM books b WHERE b.in_library = 1;",
+        )?;
+
+        let stats = stmt
+            .query_map([], |row| {
+                let chapters = row.get::<usize, i64>(5)?.max(0) as usize;
+                let read = row.get::<usize, i64>(6)?.max(0) as usize;
+                // Finishing a chapter or just paging through one both count
+                let last_read = row
+                    .get::<usize, Option<i64>>(2)?
+                    .max(row.get::<usize, Option<i64>>(3)?);
+                Ok((
+                    row.get::<usize, String>(0)?,
+                    LibraryStats {
+                        added: row.get(1)?,
+                        last_read,
+                        last_updated: row.get(4)?,
+                        chapters,
+                        unread: chapters.saturating_sub(read),
+                        downloaded: row.get::<usize, i64>(7)?.max(0) as usize,
+                    },
+                ))
+            })?
+            .collect::<rusqlite::Result<HashMap<String, LibraryStats>>>()?;
+
+        Ok(stats)
+    }
+
+    /// Replaces the stored chapter list of a book
+    #[tracing::instrument(skip(self, chapters), fields(count = chapters.len()), err)]
+    pub fn set_chapters(&self, book_url: &str, chapters: &[Chapter]) -> Result<()> {
+        let book_id = source::book_id(book_url);
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM chapters WHERE book_id = ?1;", [&book_id])?;
+        {
+            // A chapter listed twice keeps its first place
+            let mut stmt = tx.prepare(
+                "INSERT INTO chapters (book_id, name, chapter_url, chapter_id, release_date) values (?1, ?2, ?3, ?4, ?5)
+                ON CONFLICT (book_id, chapter_id) DO UPDATE SET name = excluded.name, release_date = excluded.release_date;",
+            )?;
+            for chapter in chapters {
+                let chapter_id = chapter.url.as_deref().map(source::chapter_id);
+                stmt.execute((
+                    &book_id,
+                    &chapter.name,
+                    &chapter.url,
+                    chapter_id,
+                    &chapter.released,
+                ))?;
+            }
         }
+        tx.commit()?;
+        Ok(())
+    }
 
-        let conn = rusqlite::Connection::open(self.storage_path.join(Self::STORAGE_FILE))?;
-        let mut stmt = conn.prepare(
-            "SELECT source, book_url, name, image_url, in_library FROM books WHERE book_url = :url;",
+    /// The stored chapter list of a book in reading order
+    #[tracing::instrument(skip(self), err)]
+    pub fn get_chapters(&self, book_url: &str) -> Result<Vec<Chapter>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT name, chapter_url, release_date FROM chapters WHERE book_id = ?1 ORDER BY id;",
         )?;
 
-        let mut book_iter = stmt.query_map(&[(":url", &url)], |row| {
-            Ok(Book::new(
-                row.get(0)?,
-                row.get(1)?,
-                row.get(2)?,
-                row.get(3)?,
-                row.get(4)?,
-            ))
+        let chapters = stmt
+            .query_map([source::book_id(book_url)], |row| {
+                Ok((
+                    row.get::<usize, Option<String>>(0)?,
+                    row.get::<usize, Option<String>>(1)?,
+                    row.get::<usize, Option<String>>(2)?,
+                ))
+            })?
+            .enumerate()
+            .map(|(i, row)| {
+                let (name, url, released) = row?;
+                Ok(Chapter {
+                    released,
+                    ..Chapter::new(Some(i as u32 + 1), name, url)
+                })
+            })
+            .collect::<rusqlite::Result<Vec<Chapter>>>()?;
+
+        Ok(chapters)
+    }
+
+    /// Stores the downloaded text of a chapter, replacing an older download
+    #[tracing::instrument(skip(self, content), fields(len = content.len()), err)]
+    pub fn set_chapter_content(&self, chapter_url: &str, content: &str) -> Result<()> {
+        let conn = self.conn()?;
+        let fetched = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        conn.execute(
+            "INSERT OR REPLACE INTO chapter_content (chapter_id, content, fetched) values (?1, ?2, ?3)",
+            (source::chapter_id(chapter_url), content, fetched),
+        )?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self), err(level = "warn"))]
+    pub fn get_chapter_content(&self, chapter_url: &str) -> Result<Option<String>> {
+        let conn = self.conn()?;
+        let mut stmt =
+            conn.prepare_cached("SELECT content FROM chapter_content WHERE chapter_id = ?1;")?;
+        let mut rows = stmt.query_map([source::chapter_id(chapter_url)], |row| {
+            row.get::<usize, String>(0)
         })?;
 
-        match book_iter.next() {
-            Some(Ok(mut book)) => {
-                if book.image == Some("".into()) {
-                    book.image = None;
-                }
-                return Ok(Some(book));
+        match rows.next() {
+            Some(content) => Ok(Some(content?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The book's chapters that have text stored, in reading order
+    #[tracing::instrument(skip(self), err)]
+    pub fn get_stored_chapters(&self, book_url: &str) -> Result<Vec<(Chapter, String)>> {
+        let mut stored = vec![];
+        for chapter in self.get_chapters(book_url)? {
+            let Some(url) = &chapter.url else {
+                continue;
+            };
+            if let Some(content) = self.get_chapter_content(url)? {
+                stored.push((chapter, content));
             }
-            Some(Err(e)) => return Err(e.into()),
-            None => return Ok(None),
         }
+        Ok(stored)
     }
 
-    pub fn set_book(&mut self, book: &Book) -> Result<(), Box<dyn std::error::Error>> {
-        match self.get_book(&book.url) {
-            Ok(Some(_)) => self.update_book(book.clone()),
-            _ => self.add_book(book.clone()),
-        }?;
+    #[tracing::instrument(skip(self), err(level = "warn"))]
+    pub fn has_chapter_content(&self, chapter_url: &str) -> Result<bool> {
+        let conn = self.conn()?;
+        let exists = conn
+            .prepare_cached("SELECT 1 FROM chapter_content WHERE chapter_id = ?1;")?
+            .exists([source::chapter_id(chapter_url)])?;
+        Ok(exists)
+    }
 
+    /// Urls of the book's chapters that have text stored
+    #[tracing::instrument(skip(self), err(level = "warn"))]
+    pub fn get_downloaded_chapters(&self, book_url: &str) -> Result<HashSet<String>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT c.chapter_url FROM chapters c
+            JOIN chapter_content cc ON cc.chapter_id = c.chapter_id
+            WHERE c.book_id = ?1;",
+        )?;
+        let urls = stmt
+            .query_map([source::book_id(book_url)], |row| {
+                row.get::<usize, String>(0)
+            })?
+            .collect::<rusqlite::Result<HashSet<String>>>()?;
+        Ok(urls)
+    }
+
+    /// Marks chapters as read or unread
+    #[tracing::instrument(skip_all, fields(count = chapter_urls.len(), read), err)]
+    pub fn set_read(&self, chapter_urls: &[String], read: bool) -> Result<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        {
+            let mut insert = tx.prepare(
+                "INSERT OR REPLACE INTO read_chapters (chapter_id, read) values (?1, ?2);",
+            )?;
+            let mut delete = tx.prepare("DELETE FROM read_chapters WHERE chapter_id = ?1;")?;
+            for url in chapter_urls {
+                let id = source::chapter_id(url);
+                match read {
+                    true => insert.execute((id, now))?,
+                    false => delete.execute([id])?,
+                };
+            }
+        }
+        tx.commit()?;
         Ok(())
     }
 
-    fn add_book(&mut self, book: Book) -> Result<(), Box<dyn std::error::Error>> {
-        let conn = rusqlite::Connection::open(self.storage_path.join(Self::STORAGE_FILE))?;
-        conn.execute(
-            "INSERT INTO books (source, name, book_url, image_url, in_library) values (?1, ?2, ?3 ,?4, ?5)",
-            [
-                book.source.clone(),
-                book.name.clone(),
-                book.url.clone(),
-                book.image.clone().unwrap_or("".into()),
-                if book.in_library {
-                    "1".into()
-                } else {
-                    "0".into()
-                },
-            ],
+    /// Urls of the book's chapters that are read
+    #[tracing::instrument(skip(self), err(level = "warn"))]
+    pub fn get_read_chapters(&self, book_url: &str) -> Result<HashSet<String>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT c.chapter_url FROM chapters c
+            JOIN read_chapters r ON r.chapter_id = c.chapter_id
+            WHERE c.book_id = ?1;",
         )?;
+        let urls = stmt
+            .query_map([source::book_id(book_url)], |row| {
+                row.get::<usize, String>(0)
+            })?
+            .collect::<rusqlite::Result<HashSet<String>>>()?;
+        Ok(urls)
+    }
 
-        _ = self.books.insert(book.url.clone(), book.clone());
+    /// Remembers the chapter and page the book was left at
+    #[tracing::instrument(skip(self), err)]
+    pub fn set_progress(&self, book_url: &str, progress: &Progress) -> Result<()> {
+        let conn = self.conn()?;
+        let updated = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        conn.execute(
+            "INSERT OR REPLACE INTO progress (book_id, chapter_url, chapter_id, page, updated) values (?1, ?2, ?3, ?4, ?5)",
+            (
+                source::book_id(book_url),
+                &progress.chapter_url,
+                source::chapter_id(&progress.chapter_url),
+                i64::try_from(progress.page).unwrap_or(i64::MAX),
+                updated,
+            ),
+        )?;
         Ok(())
     }
-    fn update_book(&mut self, book: Book) -> Result<(), Box<dyn std::error::Error>> {
-        let conn = rusqlite::Connection::open(self.storage_path.join(Self::STORAGE_FILE))?;
-        _ = conn.execute(
-            "UPDATE books SET source = ?1, name = ?2, book_url = ?3, image_url = ?4, in_library = ?5 WHERE book_url = ?3;",
-            [
-                book.source.clone(),
-                book.name.clone(),
-                book.url.clone(),
-                book.image.clone().unwrap_or("".into()),
-                if book.in_library {
-                    "1".into()
-                } else {
-                    "0".into()
-                },
-            ],
+
+    #[tracing::instrument(skip(self), err(level = "warn"))]
+    pub fn get_progress(&self, book_url: &str) -> Result<Option<Progress>> {
+        let conn = self.conn()?;
+        // The chapter's current url, the one last read from if it's gone
+        let mut stmt = conn.prepare_cached(
+            "SELECT COALESCE(c.chapter_url, p.chapter_url), p.page FROM progress p
+            LEFT JOIN chapters c ON c.book_id = p.book_id AND c.chapter_id = p.chapter_id
+            WHERE p.book_id = ?1;",
         )?;
+        let mut rows = stmt.query_map([source::book_id(book_url)], |row| {
+            Ok(Progress {
+                chapter_url: row.get(0)?,
+                page: row.get::<usize, i64>(1)?.max(0) as usize,
+            })
+        })?;
 
-        _ = self.books.insert(book.url.clone(), book.clone());
+        match rows.next() {
+            Some(progress) => Ok(Some(progress?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The books with a saved place, the last read first
+    #[tracing::instrument(skip(self), err)]
+    pub fn get_history(&self, limit: usize) -> Result<Vec<HistoryEntry>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT {}, COALESCE(l.chapter_url, p.chapter_url), p.page, COALESCE(p.updated, 0), l.name,
+                CASE WHEN l.id IS NOT NULL THEN (SELECT COUNT(*) FROM chapters c WHERE c.book_id = p.book_id AND c.id < l.id) END
+            FROM books JOIN progress p USING (book_id)
+            LEFT JOIN chapters l ON l.book_id = p.book_id AND l.chapter_id = p.chapter_id
+            ORDER BY p.updated DESC LIMIT ?1;",
+            Self::BOOK_COLUMNS
+        ))?;
+
+        let entries = stmt
+            .query_map([i64::try_from(limit).unwrap_or(i64::MAX)], |row| {
+                Ok(HistoryEntry {
+                    book: Self::book_from_row(row)?,
+                    progress: Progress {
+                        chapter_url: row.get(11)?,
+                        page: row.get::<usize, i64>(12)?.max(0) as usize,
+                    },
+                    updated: row.get(13)?,
+                    chapter_name: row.get(14)?,
+                    chapter_index: row
+                        .get::<usize, Option<i64>>(15)?
+                        .map(|i| i.max(0) as usize),
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<HistoryEntry>>>()?;
+        Ok(entries)
+    }
+
+    /// Stores a new annotation, returning it with its id set
+    #[tracing::instrument(skip_all, fields(book = %annotation.book_url), err)]
+    pub fn add_annotation(&self, annotation: &Annotation) -> Result<Annotation> {
+        let conn = self.conn()?;
+        conn.execute(
+            "INSERT INTO annotations (book_url, chapter_url, chapter_name, kind, paragraph, fingerprint, text, note, created, book_id, chapter_id, passage) values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            (
+                &annotation.book_url,
+                &annotation.chapter_url,
+                &annotation.chapter_name,
+                annotation.kind.as_str(),
+                i64::try_from(annotation.paragraph).unwrap_or(i64::MAX),
+                &annotation.fingerprint,
+                &annotation.text,
+                &annotation.note,
+                annotation.created,
+                source::book_id(&annotation.book_url),
+                source::chapter_id(&annotation.chapter_url),
+                &annotation.passage,
+            ),
+        )?;
+
+        Ok(Annotation {
+            id: conn.last_insert_rowid(),
+            ..annotation.clone()
+        })
+    }
+
+    /// Writes back the note and the anchor, which moves when a chapter changed
+    #[tracing::instrument(skip_all, fields(id = annotation.id), err)]
+    pub fn update_annotation(&self, annotation: &Annotation) -> Result<()> {
+        let conn = self.conn()?;
+        conn.execute(
+            "UPDATE annotations SET paragraph = ?2, note = ?3 WHERE id = ?1;",
+            (
+                annotation.id,
+                i64::try_from(annotation.paragraph).unwrap_or(i64::MAX),
+                &annotation.note,
+            ),
+        )?;
         Ok(())
     }
 
-    pub fn get_library_books(&self) -> Result<Vec<Book>, Box<dyn std::error::Error>> {
-        let conn = rusqlite::Connection::open(self.storage_path.join(Self::STORAGE_FILE))?;
-        let mut stmt = conn
-            .prepare("SELECT source, book_url, name, image_url, in_library FROM books WHERE in_library = 1")
-            .unwrap();
+    #[tracing::instrument(skip(self), err)]
+    pub fn delete_annotation(&self, id: i64) -> Result<()> {
+        let conn = self.conn()?;
+        conn.execute("DELETE FROM annotations WHERE id = ?1;", [id])?;
+        Ok(())
+    }
 
-        let book_iter = stmt
-            .query_map([], |row| {
-                Ok(Book::new(
-                    row.get(0)?,
-                    row.get(1)?,
-                    row.get(2)?,
-                    row.get(3)?,
-                    row.get(4)?,
-                ))
+    /// The book's annotations in chapter order, then by paragraph
+    #[tracing::instrument(skip(self), err(level = "warn"))]
+    pub fn get_annotations(&self, book_url: &str) -> Result<Vec<Annotation>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT a.id, a.book_url, COALESCE(c.chapter_url, a.chapter_url), a.chapter_name, a.kind, a.paragraph, a.fingerprint, a.text, a.note, a.created,
+                COALESCE(a.passage, '')
+            FROM annotations a
+            LEFT JOIN chapters c ON c.book_id = a.book_id AND c.chapter_id = a.chapter_id
+            WHERE a.book_id = ?1
+            ORDER BY c.id, a.chapter_id, a.paragraph, a.id;",
+        )?;
+
+        let annotations = stmt
+            .query_map([source::book_id(book_url)], |row| {
+                Ok(Annotation {
+                    id: row.get(0)?,
+                    book_url: row.get(1)?,
+                    chapter_url: row.get(2)?,
+                    chapter_name: row.get(3)?,
+                    kind: AnnotationKind::from_name(&row.get::<usize, String>(4)?),
+                    paragraph: row.get::<usize, i64>(5)?.max(0) as usize,
+                    fingerprint: row.get(6)?,
+                    text: row.get(7)?,
+                    note: row.get(8)?,
+                    created: row.get(9)?,
+                    passage: row.get(10)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<Annotation>>>()?;
+
+        Ok(annotations)
+    }
+
+    /// Maps a row selected with [`Self::BOOK_COLUMNS`] to a book
+    fn book_from_row(row: &rusqlite::Row) -> rusqlite::Result<Book> {
+        let mut book = Book::new(
+            row.get(0)?,
+            row.get(1)?,
+            row.get(2)?,
+            row.get(3)?,
+            row.get(4)?,
+        );
+        if book.image == Some("".into()) {
+            book.image = None;
+        }
+        book.tags = row
+            .get::<usize, Option<String>>(5)?
+            .map(|tags| {
+                tags.split(',')
+                    .filter(|t| !t.is_empty())
+                    .map(str::to_owned)
+                    .collect()
             })
-            .unwrap();
+            .unwrap_or_default();
+        book.description = row
+            .get::<usize, Option<String>>(6)?
+            .filter(|d| !d.is_empty());
+        book.partial = row.get::<usize, Option<bool>>(7)?.unwrap_or(false);
+        book.status = row
+            .get::<usize, Option<String>>(8)?
+            .filter(|s| !s.is_empty());
+        book.category = row
+            .get::<usize, Option<String>>(9)?
+            .filter(|c| !c.is_empty());
+        book.author = row
+            .get::<usize, Option<String>>(10)?
+            .filter(|a| !a.is_empty());
+        Ok(book)
+    }
 
-        let mut books = vec![];
-        for book in book_iter {
-            match book {
-                Ok(mut book) => {
-                    if book.image == Some("".into()) {
-                        book.image = None;
-                    }
-                    books.push(book);
-                }
-                Err(e) => {
-                    dbg!(e);
+    /// Parameters for the books table, in [`Self::BOOK_COLUMNS`] order with
+    /// name and url swapped, then the book id
+    fn book_params(book: &Book) -> [String; 12] {
+        let bit = |b: bool| if b { "1".to_string() } else { "0".to_string() };
+        [
+            book.source.clone(),
+            book.name.clone(),
+            book.url.clone(),
+            book.image.clone().unwrap_or("".into()),
+            bit(book.in_library),
+            book.tags.join(","),
+            book.description.clone().unwrap_or("".into()),
+            bit(book.partial),
+            book.status.clone().unwrap_or("".into()),
+            book.category.clone().unwrap_or("".into()),
+            book.author.clone().unwrap_or("".into()),
+            book.id(),
+        ]
+    }
+
+    /// Runs the one-time steps an older database is missing, counted in
+    /// `PRAGMA user_version`
+    fn migrate(conn: &mut rusqlite::Connection) -> Result<()> {
+        let version: i64 = conn.query_row("PRAGMA user_version;", (), |row| row.get(0))?;
+
+        if version < 1 {
+            // Rows duplicated before the unique indexes existed, books and
+            // covers keep the newest row and chapters their first place
+            tracing::info!("removing duplicate books, chapters and covers");
+            let tx = conn.transaction()?;
+            // The kept row is in the library if any copy was
+            tx.execute_batch(
+                "UPDATE books SET in_library = 1 WHERE book_url IN (SELECT book_url FROM books WHERE in_library = 1);
+                DELETE FROM books WHERE id NOT IN (SELECT MAX(id) FROM books GROUP BY book_url);
+                DELETE FROM chapters WHERE chapter_url IS NOT NULL AND id NOT IN
+                    (SELECT MIN(id) FROM chapters WHERE chapter_url IS NOT NULL GROUP BY book_url, chapter_url);
+                DELETE FROM thumbnails WHERE id NOT IN (SELECT MAX(id) FROM thumbnails GROUP BY book_url);
+                CREATE UNIQUE INDEX IF NOT EXISTS books_book_url ON books (book_url);
+                CREATE UNIQUE INDEX IF NOT EXISTS chapters_book_chapter ON chapters (book_url, chapter_url);
+                CREATE UNIQUE INDEX IF NOT EXISTS thumbnails_book_url ON thumbnails (book_url);
+                PRAGMA user_version = 1;",
+            )?;
+            tx.commit()?;
+        }
+
+        if version < 2 {
+            // Rows were keyed by the url as scraped, so every form of a url
+            // and every rename made another book
+            tracing::info!("keying books by source id");
+            let tx = conn.transaction()?;
+            Self::add_column_if_missing(&tx, "books", "book_id", "TEXT")?;
+            Self::add_column_if_missing(&tx, "annotations", "book_id", "TEXT")?;
+            tx.execute_batch(
+                "DROP INDEX IF EXISTS books_book_url;
+                ALTER TABLE chapters RENAME COLUMN book_url TO book_id;
+                ALTER TABLE progress RENAME COLUMN book_url TO book_id;
+                ALTER TABLE thumbnails RENAME COLUMN book_url TO book_id;",
+            )?;
+
+            let urls = tx
+                .prepare(
+                    "SELECT book_url FROM books UNION SELECT book_url FROM annotations
+                    UNION SELECT book_id FROM chapters UNION SELECT book_id FROM progress
+                    UNION SELECT book_id FROM thumbnails;",
+                )?
+                .query_map((), |row| row.get::<usize, Option<String>>(0))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            for url in urls.into_iter().flatten() {
+                let id = source::book_id(&url);
+                tx.execute(
+                    "UPDATE books SET book_id = ?1 WHERE book_url = ?2;",
+                    (&id, &url),
+                )?;
+                tx.execute(
+                    "UPDATE annotations SET book_id = ?1 WHERE book_url = ?2;",
+                    (&id, &url),
+                )?;
+                // Where two urls were the same book the one moved last stays
+                for table in ["chapters", "progress", "thumbnails"] {
+                    tx.execute(
+                        &format!("UPDATE OR REPLACE {table} SET book_id = ?1 WHERE book_id = ?2;"),
+                        (&id, &url),
+                    )?;
                 }
             }
+
+            // Merged books keep the newest row, in the library if any copy was
+            tx.execute_batch(
+                "UPDATE books SET in_library = 1 WHERE book_id IN (SELECT book_id FROM books WHERE in_library = 1);
+                DELETE FROM books WHERE id NOT IN (SELECT MAX(id) FROM books GROUP BY book_id);
+                CREATE UNIQUE INDEX IF NOT EXISTS books_book_id ON books (book_id);
+                DROP INDEX IF EXISTS thumbnails_book_url;
+                CREATE UNIQUE INDEX IF NOT EXISTS thumbnails_book_id ON thumbnails (book_id);
+                PRAGMA user_version = 2;",
+            )?;
+            tx.commit()?;
         }
 
-        Ok(books)
-    }
+        if version < 3 {
+            // Library books from before the date was kept sort as the oldest,
+            // and keep that when stored again
+            conn.execute_batch(
+                "UPDATE books SET added = 0 WHERE in_library = 1 AND added IS NULL;
+                PRAGMA user_version = 3;",
+            )?;
+        }
 
-    pub fn get_image_handle(&self, book: &Book) -> cosmic::widget::image::Handle {
-        if let Some(h) = self.book_covers.get(&book.url) {
-            cosmic::widget::image::Handle::from_memory(h.clone())
-        } else {
-            if let Ok(Some(bytes)) = self.get_image_as_bytes(book) {
-                return cosmic::widget::image::Handle::from_memory(bytes);
+        if version < 4 {
+            // Chapters were keyed by their url, which carries the chapter's
+            // title, so a renamed chapter lost its text, read mark, place
+            // and annotations
+            tracing::info!("keying chapters by source id");
+            let tx = conn.transaction()?;
+            for table in ["chapters", "progress", "annotations"] {
+                Self::add_column_if_missing(&tx, table, "chapter_id", "TEXT")?;
+            }
+            tx.execute_batch(
+                "ALTER TABLE chapter_content RENAME COLUMN chapter_url TO chapter_id;
+                ALTER TABLE read_chapters RENAME COLUMN chapter_url TO chapter_id;",
+            )?;
+
+            let urls = tx
+                .prepare(
+                    "SELECT chapter_url FROM chapters UNION SELECT chapter_url FROM progress
+                    UNION SELECT chapter_url FROM annotations UNION SELECT chapter_id FROM chapter_content
+                    UNION SELECT chapter_id FROM read_chapters;",
+                )?
+                .query_map((), |row| row.get::<usize, Option<String>>(0))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            for url in urls.into_iter().flatten() {
+                let id = source::chapter_id(&url);
+                for table in ["chapters", "progress", "annotations"] {
+                    tx.execute(
+                        &format!("UPDATE {table} SET chapter_id = ?1 WHERE chapter_url = ?2;"),
+                        (&id, &url),
+                    )?;
+                }
+                // Where two urls were the same chapter the one moved last stays
+                for table in ["chapter_content", "read_chapters"] {
+                    tx.execute(
+                        &format!(
+                            "UPDATE OR REPLACE {table} SET chapter_id = ?1 WHERE chapter_id = ?2;"
+                        ),
+                        (&id, &url),
+                    )?;
+                }
             }
-            cosmic::widget::image::Handle::from_path("res/covers/rr-image.png")
+
+            tx.execute_batch(
+                "DELETE FROM chapters WHERE chapter_id IS NOT NULL AND id NOT IN
+                    (SELECT MIN(id) FROM chapters WHERE chapter_id IS NOT NULL GROUP BY book_id, chapter_id);
+                DROP INDEX IF EXISTS chapters_book_chapter;
+                CREATE UNIQUE INDEX IF NOT EXISTS chapters_book_chapter_id ON chapters (book_id, chapter_id);
+                PRAGMA user_version = 4;",
+            )?;
+            tx.commit()?;
         }
+
+        Ok(())
     }
 
-    pub fn get_image_as_bytes(
-        &self,
-        book: &Book,
-    ) -> Result<Option<bytes::Bytes>, Box<dyn std::error::Error>> {
-        if let Some(h) = self.book_covers.get(&book.url) {
-            return Ok(Some(h.clone()));
-        };
+    fn add_column_if_missing(
+        conn: &rusqlite::Connection,
+        table: &str,
+        column: &str,
+        decl: &str,
+    ) -> rusqlite::Result<()> {
+        let exists = conn
+            .prepare(&format!(
+                "SELECT 1 FROM pragma_table_info('{table}') WHERE name = ?1;"
+            ))?
+            .exists([column])?;
+        if !exists {
+            conn.execute(
+                &format!("ALTER TABLE {table} ADD COLUMN {column} {decl};"),
+                (),
+            )?;
+        }
+        Ok(())
+    }
 
-        let conn = rusqlite::Connection::open(self.storage_path.join(Self::STORAGE_FILE))?;
-        let mut stmt = conn.prepare("SELECT image_data FROM thumbnails WHERE book_url = :url;")?;
+    /// Both sizes of a book's stored cover, processing covers stored before
+    /// they were kept at two sizes and dropping any that can't be read
+    #[tracing::instrument(skip(self), err(level = "warn"))]
+    pub fn get_cover(&self, url: &str) -> Result<Option<Cover>> {
+        let book_id = source::book_id(url);
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT image_data, detail_data FROM thumbnails WHERE book_id = :id;",
+        )?;
 
-        let mut image_iter = stmt.query_map(&[(":url", &book.url)], |row| {
-            Ok(row.get::<usize, Vec<u8>>(0)?)
+        let mut image_iter = stmt.query_map(&[(":id", &book_id)], |row| {
+            Ok((
+                row.get::<usize, Option<Vec<u8>>>(0)?,
+                row.get::<usize, Option<Vec<u8>>>(1)?,
+            ))
         })?;
 
-        return match image_iter.next() {
-            Some(img) => {
-                let bytes = bytes::Bytes::from(img?);
-                Ok(Some(bytes))
-            }
-            None => Ok(None),
+        let (thumbnail, detail) = match image_iter.next() {
+            Some(row) => row?,
+            None => return Ok(None),
         };
+        match (thumbnail, detail) {
+            (Some(thumbnail), Some(detail)) => Ok(Some(Cover {
+                thumbnail: bytes::Bytes::from(thumbnail),
+                detail: bytes::Bytes::from(detail),
+            })),
+            (Some(original), None) => match Cover::process(&original) {
+                Ok(cover) => {
+                    self.store_cover(&conn, &book_id, &cover)?;
+                    Ok(Some(cover))
+                }
+                Err(_) => {
+                    conn.execute("DELETE FROM thumbnails WHERE book_id = ?1;", [&book_id])?;
+                    Ok(None)
+                }
+            },
+            _ => Ok(None),
+        }
     }
 
-    fn get_image_from_storage(
-        &self,
-        book: &Book,
-    ) -> Result<Option<bytes::Bytes>, Box<dyn std::error::Error>> {
-        let conn = rusqlite::Connection::open(self.storage_path.join(Self::STORAGE_FILE))?;
-        let mut stmt = conn.prepare("SELECT image_data FROM thumbnails WHERE book_url = :url;")?;
-
-        let mut image_iter = stmt.query_map(&[(":url", &book.url)], |row| {
-            Ok(row.get::<usize, Vec<u8>>(0)?)
-        })?;
+    #[tracing::instrument(skip_all, fields(url = %book.url, len = cover.byte_size()), err)]
+    pub fn set_cover(&self, book: &Book, cover: &Cover) -> Result<()> {
+        let conn = self.conn()?;
+        self.store_cover(&conn, &book.id(), cover)
+    }
 
-        return match image_iter.next() {
-            Some(img) => {
-                let bytes = bytes::Bytes::from(img?);
-                Ok(Some(bytes))
-            }
-            None => Ok(None),
-        };
+    fn store_cover(&self, conn: &rusqlite::Connection, book_id: &str, cover: &Cover) -> Result<()> {
+        conn.execute(
+            "INSERT INTO thumbnails (book_id, image_data, detail_data) values (?1, ?2, ?3)
+            ON CONFLICT (book_id) DO UPDATE SET image_data = excluded.image_data, detail_data = excluded.detail_data;",
+            (book_id, &cover.thumbnail[..], &cover.detail[..]),
+        )?;
+        Ok(())
     }
+}
 
-    pub fn set_image_as_bytes(
-        &mut self,
-        book: &Book,
-        bytes: bytes::Bytes,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        _ = match self.get_image_from_storage(book) {
-            Ok(Some(_)) => {
-                let conn = rusqlite::Connection::open(self.storage_path.join(Self::STORAGE_FILE))?;
-                conn.execute(
-                    "UPDATE thumbnails SET image_data = ?2 WHERE book_url = ?1;",
-                    (book.url.clone(), bytes.to_vec()),
-                )
-            }
-            _ => {
-                let conn = rusqlite::Connection::open(self.storage_path.join(Self::STORAGE_FILE))?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merging_duplicate_books_keeps_them_in_the_library() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = "https://www.royalroad.com/fiction/1/a-book";
+        {
+            // Books as stored before duplicates were merged, only the older
+            // copy in the library
+            let conn =
+                rusqlite::Connection::open(dir.path().join(DataManager::STORAGE_FILE)).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE books (
+                    id INTEGER PRIMARY KEY,
+                    source TEXT,
+                    name TEXT,
+                    book_url TEXT,
+                    image_url TEXT,
+                    in_library BIT,
+                    tags TEXT,
+                    description TEXT,
+                    partial BIT);",
+            )
+            .unwrap();
+            for (name, in_library) in [("Old name", true), ("New name", false)] {
                 conn.execute(
-                    "INSERT INTO thumbnails (book_url, image_data) values (?1, ?2)",
-                    (book.url.clone(), bytes.to_vec()),
+                    "INSERT INTO books (source, name, book_url, in_library) values ('royalroad', ?1, ?2, ?3);",
+                    (name, url, in_library),
                 )
+                .unwrap();
             }
-        }?;
-
-        _ = self.book_covers.insert(book.url.clone(), bytes);
+        }
 
-        Ok(())
-    }
+        let data = DataManager::new();
+        assert!(data.init(dir.path().to_path_buf()).is_empty());
 
-    pub fn set_image_as_bytes_to_cache(&mut self, book: &Book, bytes: bytes::Bytes) {
-        _ = self.book_covers.insert(book.url.clone(), bytes);
+        let books = data.get_library_books().unwrap();
+        assert_eq!(books.len(), 1);
+        assert_eq!(books[0].name, "New name");
+        assert!(books[0].in_library);
     }
 
-    pub fn send_thumbnail_to_storage(&self, book: Book) -> Result<(), Box<dyn std::error::Error>> {
-        let bytes = match self.book_covers.get(&book.url) {
-            Some(b) => b,
-            None => {
-                dbg!(&book);
-                return Err("No book cover".into()).into();
-            }
+    #[test]
+    fn history_has_no_chapter_index_once_the_chapter_is_gone() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = DataManager::new();
+        assert!(data.init(dir.path().to_path_buf()).is_empty());
+
+        let url = "https://www.royalroad.com/fiction/1/a-book";
+        let chapter = |n: u32| {
+            Chapter::new(
+                Some(n),
+                Some(format!("Chapter {n}")),
+                Some(format!("{url}/chapter/{n}/part-{n}")),
+            )
         };
-
-        let conn = rusqlite::Connection::open(self.storage_path.join(Self::STORAGE_FILE))?;
-        let _ = conn.execute(
-            "INSERT INTO thumbnails (book_url, image_data) values (?1, ?2)",
-            (&book.url.clone(), &bytes.to_vec()),
-        )?;
-
-        Ok(())
+        data.set_book(&Book {
+            source: "royalroad".into(),
+            url: url.into(),
+            name: "A book".into(),
+            ..Default::default()
+        })
+        .unwrap();
+        data.set_chapters(url, &[chapter(1), chapter(2)]).unwrap();
+        data.set_progress(
+            url,
+            &Progress {
+                chapter_url: chapter(2).url.unwrap(),
+                page: 0,
+            },
+        )
+        .unwrap();
+
+        let history = data.get_history(10).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].chapter_index, Some(1));
+        assert_eq!(history[0].chapter_name.as_deref(), Some("Chapter 2"));
+
+        data.set_chapters(url, &[chapter(1)]).unwrap();
+        let history = data.get_history(10).unwrap();
+        assert_eq!(history[0].chapter_index, None);
+        assert_eq!(history[0].chapter_name, None);
     }
 }
@@ -0,0 +1,94 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::fl;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Everything that can go wrong in `core`, kept `Clone` so it can travel inside messages
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// The request failed or the server answered with an error status
+    Network(String),
+    /// A page was fetched but an expected element was missing
+    Parse { selector: String },
+    /// SQLite or the filesystem failed
+    Storage(String),
+    /// The requested book, chapter or page doesn't exist
+    NotFound(String),
+    /// The site answered 429 Too Many Requests
+    RateLimited,
+    /// No source handles the url
+    UnsupportedSource(String),
+    /// The text-to-speech engine is missing or failed
+    Speech(String),
+    /// A downloaded image couldn't be decoded or re-encoded
+    Image(String),
+}
+
+impl Error {
+    pub fn parse(selector: &str) -> Error {
+        Error::Parse {
+            selector: selector.to_owned(),
+        }
+    }
+
+    /// A readable message for the user in the current language
+    pub fn localized(&self) -> String {
+        match self {
+            Error::Network(reason) => fl!("error-network", reason = reason.clone()),
+            Error::Parse { selector } => fl!("error-parse", selector = selector.clone()),
+            Error::Storage(reason) => fl!("error-storage", reason = reason.clone()),
+            Error::NotFound(what) => fl!("error-not-found", what = what.clone()),
+            Error::RateLimited => fl!("error-rate-limited"),
+            Error::UnsupportedSource(url) => fl!("error-unsupported-source", url = url.clone()),
+            Error::Speech(reason) => fl!("error-speech", reason = reason.clone()),
+            Error::Image(reason) => fl!("error-image", reason = reason.clone()),
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Network(reason) => write!(f, "network error: {reason}"),
+            Error::Parse { selector } => write!(f, "failed to parse page at `{selector}`"),
+            Error::Storage(reason) => write!(f, "storage error: {reason}"),
+            Error::NotFound(what) => write!(f, "not found: {what}"),
+            Error::RateLimited => write!(f, "rate limited"),
+            Error::UnsupportedSource(url) => write!(f, "unsupported source: {url}"),
+            Error::Speech(reason) => write!(f, "speech error: {reason}"),
+            Error::Image(reason) => write!(f, "image error: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        match e.status() {
+            Some(reqwest::StatusCode::TOO_MANY_REQUESTS) => Error::RateLimited,
+            Some(reqwest::StatusCode::NOT_FOUND) => Error::NotFound(
+                e.url()
+                    .map(|u| u.to_string())
+                    .unwrap_or_else(|| e.to_string()),
+            ),
+            _ => Error::Network(e.to_string()),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(e: rusqlite::Error) -> Self {
+        match e {
+            rusqlite::Error::QueryReturnedNoRows => Error::NotFound(e.to_string()),
+            e => Error::Storage(e.to_string()),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Storage(e.to_string())
+    }
+}
@@ -0,0 +1,152 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use base64::Engine as _;
+
+use super::{chapter_title, paragraphs, ExportBook, Exporter};
+use crate::core::{file_name, Result};
+
+const STYLE: &str = "body { max-width: 40em; margin: 2em auto; padding: 0 1em; font-family: serif; line-height: 1.6; }
+img.cover { display: block; max-width: 100%; max-height: 30em; margin: 0 auto; }
+nav ol { padding-left: 1.5em; }
+section { margin-top: 3em; }";
+
+/// One HTML file with the cover embedded and a table of contents, readable
+/// offline in any browser
+#[derive(Debug, Default)]
+pub struct HtmlExporter;
+
+impl Exporter for HtmlExporter {
+    fn as_str(&self) -> &'static str {
+        "html"
+    }
+
+    fn label(&self) -> &'static str {
+        "HTML"
+    }
+
+    #[tracing::instrument(skip_all, fields(book = %book.book.url), err)]
+    fn export(&self, book: &ExportBook, dest: &Path) -> Result<PathBuf> {
+        book.check()?;
+        let name = escape(&book.book.name);
+
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{name}</title>\n<style>\n{STYLE}\n</style>\n</head>\n<body>\n<header>\n"
+        );
+        if let Some(uri) = book.cover.as_deref().and_then(data_uri) {
+            _ = writeln!(html, "<img class=\"cover\" alt=\"\" src=\"{uri}\">");
+        }
+        _ = writeln!(html, "<h1>{name}</h1>");
+        _ = writeln!(
+            html,
+            "<p><a href=\"{0}\">{0}</a></p>",
+            escape(&book.book.url)
+        );
+        if let Some(description) = &book.book.description {
+            for paragraph in paragraphs(description) {
+                _ = writeln!(html, "<p>{}</p>", escape(paragraph));
+            }
+        }
+        html.push_str("</header>\n<nav>\n<h2>Contents</h2>\n<ol>\n");
+
+        let titles = book
+            .chapters
+            .iter()
+            .enumerate()
+            .map(|(i, (chapter, _))| escape(&chapter_title(i, chapter)))
+            .collect::<Vec<String>>();
+        for (i, title) in titles.iter().enumerate() {
+            _ = writeln!(html, "<li><a href=\"#chapter-{}\">{title}</a></li>", i + 1);
+        }
+        html.push_str("</ol>\n</nav>\n");
+
+        for (i, (title, (_, content))) in titles.iter().zip(&book.chapters).enumerate() {
+            _ = writeln!(html, "<section id=\"chapter-{}\">\n<h2>{title}</h2>", i + 1);
+            for paragraph in paragraphs(content) {
+                _ = writeln!(html, "<p>{}</p>", escape(paragraph));
+            }
+            html.push_str("</section>\n");
+        }
+        html.push_str("</body>\n</html>\n");
+
+        std::fs::create_dir_all(dest)?;
+        let path = dest.join(format!("{}.html", file_name(&book.book.name, "book")));
+        std::fs::write(&path, html)?;
+        Ok(path)
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Image type from the file's magic bytes, `None` for anything a browser may not show
+fn mime(bytes: &[u8]) -> Option<&'static str> {
+    match bytes {
+        [0x89, b'P', b'N', b'G', ..] => Some("image/png"),
+        [0xFF, 0xD8, 0xFF, ..] => Some("image/jpeg"),
+        [b'G', b'I', b'F', b'8', ..] => Some("image/gif"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+        _ => None,
+    }
+}
+
+/// The cover embedded as a data URI, in standard padded base64
+fn data_uri(bytes: &[u8]) -> Option<String> {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(bytes);
+    Some(format!("data:{};base64,{encoded}", mime(bytes)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_markup_and_quotes() {
+        assert_eq!(
+            escape(r#"<b>Tom & "Jerry"</b> won't"#),
+            "&lt;b&gt;Tom &amp; &quot;Jerry&quot;&lt;/b&gt; won&#39;t"
+        );
+        assert_eq!(escape("&amp;"), "&amp;amp;");
+        assert_eq!(escape("plain — text"), "plain — text");
+    }
+
+    #[test]
+    fn data_uri_pads_the_last_group() {
+        assert_eq!(
+            data_uri(b"GIF8").as_deref(),
+            Some("data:image/gif;base64,R0lGOA==")
+        );
+        assert_eq!(
+            data_uri(b"GIF89").as_deref(),
+            Some("data:image/gif;base64,R0lGODk=")
+        );
+        assert_eq!(
+            data_uri(b"GIF89a").as_deref(),
+            Some("data:image/gif;base64,R0lGODlh")
+        );
+        assert_eq!(
+            data_uri(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]).as_deref(),
+            Some("data:image/png;base64,iVBORw0KGgo=")
+        );
+    }
+
+    #[test]
+    fn data_uri_skips_images_browsers_may_not_show() {
+        assert_eq!(data_uri(b"BM\0\0"), None);
+        assert_eq!(data_uri(&[]), None);
+    }
+}
@@ -0,0 +1,138 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use super::{chapter_title, paragraphs, ExportBook, Exporter};
+use crate::core::{file_name, Chapter, Result};
+
+/// Markdown in one file, or a file per chapter in a directory named after the book
+#[derive(Debug, Default)]
+pub struct MarkdownExporter {
+    pub split: bool,
+}
+
+impl Exporter for MarkdownExporter {
+    fn as_str(&self) -> &'static str {
+        match self.split {
+            false => "markdown",
+            true => "markdown-chapters",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self.split {
+            false => "Markdown",
+            true => "Markdown, file per chapter",
+        }
+    }
+
+    #[tracing::instrument(skip_all, fields(book = %book.book.url, split = self.split), err)]
+    fn export(&self, book: &ExportBook, dest: &Path) -> Result<PathBuf> {
+        book.check()?;
+        let name = file_name(&book.book.name, "book");
+
+        let mut front = format!("# {}\n\n<{}>\n", escape(&book.book.name), book.book.url);
+        if let Some(description) = &book.book.description {
+            for paragraph in paragraphs(description) {
+                _ = write!(front, "\n{}\n", escape(paragraph));
+            }
+        }
+
+        if !self.split {
+            let mut text = front;
+            for (i, (chapter, content)) in book.chapters.iter().enumerate() {
+                text.push('\n');
+                text.push_str(&chapter_text(i, chapter, content, "##"));
+            }
+            std::fs::create_dir_all(dest)?;
+            let path = dest.join(format!("{name}.md"));
+            std::fs::write(&path, text)?;
+            return Ok(path);
+        }
+
+        // The index links the chapter files, numbered so they sort in reading order
+        let dir = dest.join(&name);
+        std::fs::create_dir_all(&dir)?;
+        let mut index = front;
+        index.push('\n');
+        for (i, (chapter, content)) in book.chapters.iter().enumerate() {
+            let title = chapter_title(i, chapter);
+            let file = format!("{:03} {}.md", i + 1, file_name(&title, "chapter"));
+            std::fs::write(dir.join(&file), chapter_text(i, chapter, content, "#"))?;
+            _ = writeln!(index, "{}. [{}](<{file}>)", i + 1, escape(&title));
+        }
+        std::fs::write(dir.join("index.md"), index)?;
+        Ok(dir)
+    }
+}
+
+fn chapter_text(index: usize, chapter: &Chapter, content: &str, heading: &str) -> String {
+    let mut text = format!("{heading} {}\n", escape(&chapter_title(index, chapter)));
+    for paragraph in paragraphs(content) {
+        _ = write!(text, "\n{}\n", escape(paragraph));
+    }
+    text
+}
+
+/// Backslashes the characters that would otherwise turn prose into markup
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    // Lines starting like a list item stay prose
+    let list_marker = escaped
+        .find(|c: char| !c.is_ascii_digit())
+        .filter(|i| *i > 0 && escaped[*i..].starts_with(". "));
+    match (escaped.chars().next(), list_marker) {
+        (Some('-' | '+'), _) if escaped[1..].starts_with(' ') => format!("\\{escaped}"),
+        (_, Some(i)) => format!("{}\\{}", &escaped[..i], &escaped[i..]),
+        _ => escaped,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_inline_markup() {
+        assert_eq!(
+            escape(r"*Really* [not] a_link <b> `code` #1 | \"),
+            r"\*Really\* \[not\] a\_link \<b\> \`code\` \#1 \| \\"
+        );
+    }
+
+    #[test]
+    fn escapes_list_markers_at_the_start() {
+        assert_eq!(escape("- said no one"), r"\- said no one");
+        assert_eq!(escape("+ one more"), r"\+ one more");
+        assert_eq!(escape("* star"), r"\* star");
+        assert_eq!(escape("1. first"), r"1\. first");
+        assert_eq!(escape("1984. A year"), r"1984\. A year");
+    }
+
+    #[test]
+    fn leaves_prose_that_only_looks_close_to_a_list() {
+        for text in [
+            "-dash",
+            "--",
+            "-",
+            "1.5 litres",
+            "1.",
+            "No. 5",
+            "a - b",
+            "It was 1. Then 2. ",
+        ] {
+            assert_eq!(escape(text), text);
+        }
+    }
+}
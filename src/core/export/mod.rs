@@ -0,0 +1,78 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Writes a book's stored chapters to files for reading elsewhere, each
+//! format is an [`Exporter`]
+
+pub mod html;
+pub mod markdown;
+pub mod text;
+
+use std::path::{Path, PathBuf};
+
+use once_cell::sync::Lazy;
+
+use super::{Book, Chapter, Error, Result};
+
+/// A book with the chapters that have text stored, in reading order
+#[derive(Debug, Clone, Default)]
+pub struct ExportBook {
+    pub book: Book,
+    pub cover: Option<bytes::Bytes>,
+    /// Chapters with their text, paragraphs separated by newlines
+    pub chapters: Vec<(Chapter, String)>,
+}
+
+pub trait Exporter: Send + Sync {
+    /// Name used on the command line and in the settings
+    fn as_str(&self) -> &'static str;
+    fn label(&self) -> &'static str;
+    /// Writes the book into `dest`, returning the file or directory created
+    fn export(&self, book: &ExportBook, dest: &Path) -> Result<PathBuf>;
+}
+
+/// Every export format, in the order they are offered
+pub fn all() -> Vec<Box<dyn Exporter>> {
+    vec![
+        Box::new(text::TextExporter),
+        Box::new(markdown::MarkdownExporter { split: false }),
+        Box::new(markdown::MarkdownExporter { split: true }),
+        Box::new(html::HtmlExporter),
+    ]
+}
+
+/// Labels of [`all`] in the same order, for pickers
+pub fn labels() -> &'static [&'static str] {
+    static LABELS: Lazy<Vec<&'static str>> =
+        Lazy::new(|| all().iter().map(|e| e.label()).collect());
+    &LABELS
+}
+
+pub fn for_name(name: &str) -> Result<Box<dyn Exporter>> {
+    all()
+        .into_iter()
+        .find(|e| e.as_str().eq_ignore_ascii_case(name))
+        .ok_or_else(|| Error::NotFound(format!("export format {name}")))
+}
+
+impl ExportBook {
+    /// Writes nothing for a book without stored chapters
+    fn check(&self) -> Result<()> {
+        match self.chapters.is_empty() {
+            true => Err(Error::NotFound("stored chapters to export".into())),
+            false => Ok(()),
+        }
+    }
+}
+
+/// The chapter's name, or its position for chapters without one
+fn chapter_title(index: usize, chapter: &Chapter) -> String {
+    chapter
+        .name
+        .clone()
+        .filter(|n| !n.trim().is_empty())
+        .unwrap_or_else(|| format!("Chapter {}", index + 1))
+}
+
+fn paragraphs(content: &str) -> impl Iterator<Item = &str> {
+    content.lines().map(str::trim).filter(|p| !p.is_empty())
+}
@@ -0,0 +1,44 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use super::{chapter_title, paragraphs, ExportBook, Exporter};
+use crate::core::{file_name, Result};
+
+/// The whole book in one `.txt` file
+#[derive(Debug, Default)]
+pub struct TextExporter;
+
+impl Exporter for TextExporter {
+    fn as_str(&self) -> &'static str {
+        "txt"
+    }
+
+    fn label(&self) -> &'static str {
+        "Plain text"
+    }
+
+    #[tracing::instrument(skip_all, fields(book = %book.book.url), err)]
+    fn export(&self, book: &ExportBook, dest: &Path) -> Result<PathBuf> {
+        book.check()?;
+
+        let mut text = format!("{}\n{}\n", book.book.name, book.book.url);
+        if let Some(description) = &book.book.description {
+            _ = write!(text, "\n{description}\n");
+        }
+
+        for (i, (chapter, content)) in book.chapters.iter().enumerate() {
+            let title = chapter_title(i, chapter);
+            _ = write!(text, "\n\n{title}\n{}\n", "=".repeat(title.chars().count()));
+            for paragraph in paragraphs(content) {
+                _ = write!(text, "\n{paragraph}\n");
+            }
+        }
+
+        std::fs::create_dir_all(dest)?;
+        let path = dest.join(format!("{}.txt", file_name(&book.book.name, "book")));
+        std::fs::write(&path, text)?;
+        Ok(path)
+    }
+}
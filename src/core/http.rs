@@ -0,0 +1,553 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use once_cell::sync::Lazy;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::core::Result;
+
+/// The shared response cache, every page a source fetches goes through it
+pub static CACHE: Lazy<HttpCache> = Lazy::new(HttpCache::default);
+
+tokio::task_local! {
+    /// Set while [`revalidate`] runs
+    static REVALIDATE: ();
+}
+
+/// Runs `f` with every fetch in it asking the server whether the page
+/// changed, even while the stored copy is fresh. For refreshes the user
+/// asked for, which would otherwise get the same page back until the TTL ends
+pub async fn revalidate<F: Future>(f: F) -> F::Output {
+    REVALIDATE.scope((), f).await
+}
+
+/// What a response holds, each kind is served from the cache for its own TTL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resource {
+    /// Book and chapter pages, which change as authors post and edit
+    Page,
+    /// Covers, which hardly ever change once uploaded
+    Image,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CacheConfig {
+    /// How long a stored page is served without asking the server
+    pub page_ttl: Duration,
+    /// How long a stored image is served without asking the server
+    pub image_ttl: Duration,
+    /// Upper bound for the size of the cache directory, least recently used
+    /// entries go first
+    pub max_bytes: u64,
+    /// Requests allowed on the network at once, shared by every source so
+    /// background downloads can't flood a site
+    pub concurrency: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            page_ttl: Duration::from_secs(60 * 60),
+            image_ttl: Duration::from_secs(7 * 24 * 60 * 60),
+            max_bytes: 100 * 1024 * 1024,
+            concurrency: 2,
+        }
+    }
+}
+
+impl CacheConfig {
+    pub fn ttl(&self, resource: Resource) -> Duration {
+        match resource {
+            Resource::Page => self.page_ttl,
+            Resource::Image => self.image_ttl,
+        }
+    }
+}
+
+/// On-disk HTTP cache that revalidates with ETag / Last-Modified and falls
+/// back to stale entries when the network is unavailable
+pub struct HttpCache {
+    client: reqwest::Client,
+    dir: RwLock<Option<PathBuf>>,
+    config: RwLock<CacheConfig>,
+    /// Permits for [`CacheConfig::concurrency`], replaced when it changes
+    limit: RwLock<Arc<Semaphore>>,
+    /// Bytes stored since the size limit was last enforced
+    written: AtomicU64,
+}
+
+impl Default for HttpCache {
+    fn default() -> Self {
+        let config = CacheConfig::default();
+        HttpCache {
+            client: reqwest::Client::default(),
+            dir: RwLock::default(),
+            limit: RwLock::new(Arc::new(Semaphore::new(config.concurrency))),
+            config: RwLock::new(config),
+            written: AtomicU64::new(0),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Entry {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    fetched: u64,
+}
+
+impl HttpCache {
+    const BODY_EXT: &'static str = "body";
+    const META_EXT: &'static str = "meta";
+    /// Times the size limit is enforced while filling the cache up once,
+    /// scanning the directory after every write would cost more than the fetch
+    const CHECKS_PER_LIMIT: u64 = 32;
+
+    /// Sets the directory entries are stored in, until then every fetch goes to the network
+    pub fn init(&self, dir: PathBuf) -> Result<()> {
+        std::fs::create_dir_all(&dir)?;
+        *self.dir.write().unwrap() = Some(dir);
+        Ok(())
+    }
+
+    pub fn config(&self) -> CacheConfig {
+        *self.config.read().unwrap()
+    }
+
+    pub fn set_config(&self, config: CacheConfig) {
+        let old = self.config();
+        // Requests holding permits of the old limit finish under it
+        if config.concurrency != old.concurrency {
+            *self.limit.write().unwrap() = Arc::new(Semaphore::new(config.concurrency.max(1)));
+        }
+        *self.config.write().unwrap() = config;
+        // Called from the UI thread, which mustn't wait on the scan
+        if let Some(dir) = self.dir().filter(|_| config.max_bytes < old.max_bytes) {
+            std::thread::spawn(move || Self::shrink(&dir, config.max_bytes));
+        }
+    }
+
+    /// Removes every stored response, leaving `data.db` untouched
+    pub fn clear(&self) -> Result<()> {
+        let Some(dir) = self.dir() else {
+            return Ok(());
+        };
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir)?;
+        }
+        std::fs::create_dir_all(&dir)?;
+        Ok(())
+    }
+
+    /// Total size of the stored responses in bytes
+    pub fn size(&self) -> u64 {
+        let Some(dir) = self.dir() else {
+            return 0;
+        };
+        Self::entries(&dir).iter().map(|(_, len, _)| len).sum()
+    }
+
+    /// The page, from the cache while it is fresh unless inside [`revalidate`]
+    pub async fn get_text(&self, url: &str) -> Result<String> {
+        let bytes = self.get(url, Resource::Page).await?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// The image, from the cache while it is fresh unless inside [`revalidate`]
+    pub async fn get_image(&self, url: &str) -> Result<bytes::Bytes> {
+        self.get(url, Resource::Image).await
+    }
+
+    async fn get(&self, url: &str, resource: Resource) -> Result<bytes::Bytes> {
+        let revalidate = REVALIDATE.try_with(|_| ()).is_ok();
+        self.fetch(url, resource, revalidate).await
+    }
+
+    #[tracing::instrument(skip(self), err(level = "warn"))]
+    async fn fetch(&self, url: &str, resource: Resource, revalidate: bool) -> Result<bytes::Bytes> {
+        let Some(dir) = self.dir() else {
+            let _permit = self.permit().await;
+            return Ok(self.client.get(url).send().await?.bytes().await?);
+        };
+
+        let key = Self::key(url);
+        let body_path = dir.join(&key).with_extension(Self::BODY_EXT);
+        let meta_path = dir.join(&key).with_extension(Self::META_EXT);
+
+        let cached = match (
+            tokio::fs::read_to_string(&meta_path).await,
+            tokio::fs::read(&body_path).await,
+        ) {
+            (Ok(meta), Ok(body)) => Some((Entry::parse(&meta), bytes::Bytes::from(body))),
+            _ => None,
+        };
+
+        if let Some((entry, body)) = cached.as_ref().filter(|_| !revalidate) {
+            if Self::now().saturating_sub(entry.fetched) < self.config().ttl(resource).as_secs() {
+                tracing::debug!("fresh cache hit");
+                Self::touch(&body_path);
+                return Ok(body.clone());
+            }
+        }
+
+        let mut request = self.client.get(url);
+        if let Some((entry, _)) = &cached {
+            if let Some(etag) = &entry.etag {
+                request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &entry.last_modified {
+                request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let _permit = self.permit().await;
+        let response = match request.send().await.and_then(|r| r.error_for_status()) {
+            Ok(response) => response,
+            // Offline or the server is failing, a stale page beats no page
+            Err(e) => {
+                return match cached {
+                    Some((_, body)) => {
+                        tracing::info!("serving stale page: {e}");
+                        Self::touch(&body_path);
+                        Ok(body)
+                    }
+                    None => Err(e.into()),
+                }
+            }
+        };
+
+        if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+            if let Some((mut entry, body)) = cached {
+                tracing::debug!("revalidated");
+                entry.fetched = Self::now();
+                tokio::fs::write(&meta_path, entry.to_string()).await?;
+                Self::touch(&body_path);
+                return Ok(body);
+            }
+        }
+
+        let header = |name: reqwest::header::HeaderName| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_owned)
+        };
+        let entry = Entry {
+            etag: header(reqwest::header::ETAG),
+            last_modified: header(reqwest::header::LAST_MODIFIED),
+            fetched: Self::now(),
+        };
+        let body = response.bytes().await?;
+
+        tokio::fs::write(&body_path, &body).await?;
+        tokio::fs::write(&meta_path, entry.to_string()).await?;
+        self.stored(&dir, body.len() as u64);
+
+        Ok(body)
+    }
+
+    /// Waits for a turn on the network, held until the body has been read
+    async fn permit(&self) -> Option<OwnedSemaphorePermit> {
+        let limit = self.limit.read().unwrap().clone();
+        limit.acquire_owned().await.ok()
+    }
+
+    fn dir(&self) -> Option<PathBuf> {
+        self.dir.read().unwrap().clone()
+    }
+
+    /// Marks the entry as just used, eviction goes by modification time
+    fn touch(body_path: &Path) {
+        let touched = std::fs::File::options()
+            .append(true)
+            .open(body_path)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+        if let Err(e) = touched {
+            tracing::debug!("failed to touch cache entry: {e}");
+        }
+    }
+
+    /// Counts the bytes just stored and, once enough piled up since the last
+    /// time, evicts entries over the limit on a blocking thread
+    fn stored(&self, dir: &Path, len: u64) {
+        let max_bytes = self.config().max_bytes;
+        let threshold = max_bytes / Self::CHECKS_PER_LIMIT;
+        // Only the fetch that takes the count back to zero shrinks the cache
+        if self.written.fetch_add(len, Ordering::Relaxed) + len < threshold
+            || self.written.swap(0, Ordering::Relaxed) < threshold
+        {
+            return;
+        }
+        let dir = dir.to_owned();
+        tokio::task::spawn_blocking(move || Self::shrink(&dir, max_bytes));
+    }
+
+    fn shrink(dir: &Path, max_bytes: u64) {
+        if let Err(e) = Self::enforce_size_limit(dir, max_bytes) {
+            tracing::warn!("failed to shrink network cache: {e}");
+        }
+    }
+
+    /// Stored bodies as (path, length, last used)
+    fn entries(dir: &Path) -> Vec<(PathBuf, u64, SystemTime)> {
+        let Ok(read_dir) = std::fs::read_dir(dir) else {
+            return vec![];
+        };
+
+        read_dir
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == Self::BODY_EXT))
+            .filter_map(|p| {
+                let metadata = std::fs::metadata(&p).ok()?;
+                Some((p, metadata.len(), metadata.modified().ok()?))
+            })
+            .collect()
+    }
+
+    fn enforce_size_limit(dir: &Path, max_bytes: u64) -> Result<()> {
+        let mut entries = Self::entries(dir);
+        let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        if total <= max_bytes {
+            return Ok(());
+        }
+
+        // Least recently used first
+        entries.sort_by_key(|(_, _, used)| *used);
+        for (path, len, _) in entries {
+            if total <= max_bytes {
+                break;
+            }
+            std::fs::remove_file(&path)?;
+            _ = std::fs::remove_file(path.with_extension(Self::META_EXT));
+            total -= len;
+        }
+        Ok(())
+    }
+
+    /// File name of the url's entry. FNV-1a, unlike the std hashers its
+    /// output never changes, so entries outlive toolchain upgrades
+    fn key(url: &str) -> String {
+        let hash = url.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+        });
+        format!("{hash:016x}")
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    }
+}
+
+impl Entry {
+    fn parse(meta: &str) -> Entry {
+        let mut entry = Entry::default();
+        for line in meta.lines() {
+            match line.split_once(": ") {
+                Some(("etag", v)) => entry.etag = Some(v.to_owned()),
+                Some(("last-modified", v)) => entry.last_modified = Some(v.to_owned()),
+                Some(("fetched", v)) => entry.fetched = v.parse().unwrap_or(0),
+                _ => (),
+            }
+        }
+        entry
+    }
+}
+
+impl std::fmt::Display for Entry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(etag) = &self.etag {
+            writeln!(f, "etag: {etag}")?;
+        }
+        if let Some(last_modified) = &self.last_modified {
+            writeln!(f, "last-modified: {last_modified}")?;
+        }
+        writeln!(f, "fetched: {}", self.fetched)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_is_stable() {
+        // FNV-1a test vectors, a key that changes orphans every stored entry
+        assert_eq!(HttpCache::key(""), "cbf29ce484222325");
+        assert_eq!(HttpCache::key("a"), "af63dc4c8601ec8c");
+        assert_eq!(HttpCache::key("foobar"), "85944171f73967e8");
+    }
+
+    const FIRST: &str =
+        "HTTP/1.1 200 OK\r\netag: \"v1\"\r\ncontent-length: 5\r\nconnection: close\r\n\r\nfirst";
+    const SECOND: &str = "HTTP/1.1 200 OK\r\ncontent-length: 6\r\nconnection: close\r\n\r\nsecond";
+    const NOT_MODIFIED: &str = "HTTP/1.1 304 Not Modified\r\nconnection: close\r\n\r\n";
+
+    fn cache(dir: &Path, page_ttl: Duration) -> HttpCache {
+        let cache = HttpCache {
+            client: reqwest::Client::builder().no_proxy().build().unwrap(),
+            ..HttpCache::default()
+        };
+        cache.init(dir.to_owned()).unwrap();
+        cache.set_config(CacheConfig {
+            page_ttl,
+            ..CacheConfig::default()
+        });
+        cache
+    }
+
+    /// Answers each request with the next response, repeating the last one,
+    /// and keeps the request heads it got
+    async fn serve(responses: Vec<&'static str>) -> (String, Arc<std::sync::Mutex<Vec<String>>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/page", listener.local_addr().unwrap());
+        let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let received = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut head = Vec::new();
+                let mut buf = [0; 1024];
+                while !head.ends_with(b"\r\n\r\n") {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    head.extend_from_slice(&buf[..n]);
+                }
+                let i = {
+                    let mut received = received.lock().unwrap();
+                    received.push(String::from_utf8_lossy(&head).to_lowercase());
+                    received.len() - 1
+                };
+                let response = responses[i.min(responses.len() - 1)];
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    fn meta_path(dir: &Path, url: &str) -> PathBuf {
+        dir.join(HttpCache::key(url))
+            .with_extension(HttpCache::META_EXT)
+    }
+
+    #[test]
+    fn entry_survives_a_round_trip() {
+        let entry = Entry {
+            etag: Some("W/\"abc: def\"".into()),
+            last_modified: Some("Wed, 21 Oct 2015 07:28:00 GMT".into()),
+            fetched: 1_700_000_000,
+        };
+        assert_eq!(Entry::parse(&entry.to_string()), entry);
+
+        let bare = Entry {
+            fetched: 42,
+            ..Entry::default()
+        };
+        assert_eq!(Entry::parse(&bare.to_string()), bare);
+    }
+
+    #[tokio::test]
+    async fn fresh_entry_is_served_without_asking_the_server() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache(dir.path(), Duration::from_secs(60));
+        let (url, requests) = serve(vec![FIRST, SECOND]).await;
+
+        assert_eq!(cache.get_text(&url).await.unwrap(), "first");
+        assert_eq!(cache.get_text(&url).await.unwrap(), "first");
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn expired_entry_is_fetched_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache(dir.path(), Duration::ZERO);
+        let (url, requests) = serve(vec![FIRST, SECOND]).await;
+
+        assert_eq!(cache.get_text(&url).await.unwrap(), "first");
+        assert_eq!(cache.get_text(&url).await.unwrap(), "second");
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn not_modified_refreshes_the_stored_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache(dir.path(), Duration::from_secs(60));
+        let (url, requests) = serve(vec![FIRST, NOT_MODIFIED]).await;
+
+        assert_eq!(cache.get_text(&url).await.unwrap(), "first");
+        // Expire it
+        let meta_path = meta_path(dir.path(), &url);
+        let mut entry = Entry::parse(&std::fs::read_to_string(&meta_path).unwrap());
+        entry.fetched = 0;
+        std::fs::write(&meta_path, entry.to_string()).unwrap();
+
+        assert_eq!(cache.get_text(&url).await.unwrap(), "first");
+        assert!(requests.lock().unwrap()[1].contains("if-none-match: \"v1\""));
+        let entry = Entry::parse(&std::fs::read_to_string(&meta_path).unwrap());
+        assert!(entry.fetched > 0);
+        // Fresh again
+        assert_eq!(cache.get_text(&url).await.unwrap(), "first");
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn stale_entry_is_served_when_the_network_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache(dir.path(), Duration::from_secs(60));
+        // Nothing listens on the port once the listener is dropped
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let url = format!("http://{addr}/page");
+
+        assert!(cache.get_text(&url).await.is_err());
+
+        let body_path = dir
+            .path()
+            .join(HttpCache::key(&url))
+            .with_extension(HttpCache::BODY_EXT);
+        std::fs::write(body_path, "stale").unwrap();
+        std::fs::write(meta_path(dir.path(), &url), Entry::default().to_string()).unwrap();
+        assert_eq!(cache.get_text(&url).await.unwrap(), "stale");
+    }
+
+    #[test]
+    fn least_recently_used_entries_are_evicted_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = SystemTime::now();
+        for (key, age) in [("old", 30), ("used", 10), ("new", 20)] {
+            let body_path = dir.path().join(key).with_extension(HttpCache::BODY_EXT);
+            std::fs::write(&body_path, [0; 10]).unwrap();
+            std::fs::write(body_path.with_extension(HttpCache::META_EXT), "").unwrap();
+            std::fs::File::options()
+                .append(true)
+                .open(&body_path)
+                .unwrap()
+                .set_modified(now - Duration::from_secs(age))
+                .unwrap();
+        }
+
+        HttpCache::enforce_size_limit(dir.path(), 15).unwrap();
+
+        let exists = |key: &str, ext: &str| dir.path().join(key).with_extension(ext).exists();
+        assert!(!exists("old", HttpCache::BODY_EXT));
+        assert!(!exists("old", HttpCache::META_EXT));
+        assert!(!exists("new", HttpCache::BODY_EXT));
+        assert!(exists("used", HttpCache::BODY_EXT));
+        assert!(exists("used", HttpCache::META_EXT));
+
+        HttpCache::enforce_size_limit(dir.path(), 15).unwrap();
+        assert!(exists("used", HttpCache::BODY_EXT));
+    }
+}
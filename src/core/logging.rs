@@ -0,0 +1,105 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use once_cell::sync::OnceCell;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::{fmt, prelude::*, reload, EnvFilter, Registry};
+
+use crate::core::Result;
+
+/// Levels offered in the settings, `RUST_LOG` can still set any filter
+pub const LEVELS: [&str; 5] = ["error", "warn", "info", "debug", "trace"];
+pub const DEFAULT_LEVEL: &str = "info";
+
+const FILE_PREFIX: &str = "web-reader";
+const FILE_SUFFIX: &str = "log";
+const MAX_FILES: usize = 7;
+
+static FILTER: OnceCell<reload::Handle<EnvFilter, Registry>> = OnceCell::new();
+static LOG_DIR: OnceCell<PathBuf> = OnceCell::new();
+
+/// Logs to stderr and a daily rotated file in `dir`, the guard flushes the
+/// file writer and has to live as long as the app
+pub fn init(dir: PathBuf) -> Result<WorkerGuard> {
+    std::fs::create_dir_all(&dir)?;
+
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(FILE_PREFIX)
+        .filename_suffix(FILE_SUFFIX)
+        .max_log_files(MAX_FILES)
+        .build(&dir)
+        .map_err(|e| crate::core::Error::Storage(e.to_string()))?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(filter(DEFAULT_LEVEL)));
+    let (filter, handle) = reload::Layer::new(filter);
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_writer(std::io::stderr))
+        .with(fmt::layer().with_writer(writer).with_ansi(false))
+        .init();
+
+    _ = FILTER.set(handle);
+    _ = LOG_DIR.set(dir);
+    Ok(guard)
+}
+
+/// Changes the level of the app's own logs at runtime
+pub fn set_level(level: &str) {
+    let Some(handle) = FILTER.get() else {
+        return;
+    };
+    if let Err(e) = handle.modify(|f| *f = EnvFilter::new(filter(level))) {
+        tracing::warn!("failed to change log level: {e}");
+    }
+}
+
+/// Concatenates the rotated log files, oldest first, into one file in `dest`
+pub fn export(dest: &Path) -> Result<PathBuf> {
+    let dir = LOG_DIR
+        .get()
+        .ok_or(crate::core::Error::NotFound("log directory".into()))?;
+
+    let mut files = std::fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with(FILE_PREFIX))
+        })
+        .collect::<Vec<PathBuf>>();
+    // Rotated names end in the date, so they sort chronologically
+    files.sort();
+
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let path = dest.join(format!("{FILE_PREFIX}-logs-{seconds}.{FILE_SUFFIX}"));
+
+    let mut out = std::fs::File::create(&path)?;
+    writeln!(
+        out,
+        "{} {} ({})",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        env!("VERGEN_GIT_SHA")
+    )?;
+    for file in files {
+        writeln!(out, "==> {} <==", file.display())?;
+        out.write_all(&std::fs::read(&file)?)?;
+    }
+
+    Ok(path)
+}
+
+/// Other crates stay at warn so the logs are about this app
+fn filter(level: &str) -> String {
+    format!("warn,{}={level}", env!("CARGO_CRATE_NAME"))
+}
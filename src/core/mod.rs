@@ -1,19 +1,41 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
+pub mod annotation;
+pub mod audio;
 pub mod book;
 pub mod chapter;
+pub mod cover;
 pub mod data;
+pub mod error;
+pub mod export;
+pub mod http;
 pub mod localization;
+pub mod logging;
+pub mod pool;
+pub mod search;
 pub mod source;
+pub mod tts;
 pub mod widget;
 
-#[derive(Debug, Clone, PartialEq)]
+pub use annotation::{Annotation, AnnotationKind};
+pub use error::{Error, Result};
+
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct Book {
     pub source: String,
     pub url: String,
     pub image: Option<String>,
     pub name: String,
+    pub author: Option<String>,
     pub in_library: bool,
+    pub tags: Vec<String>,
+    pub description: Option<String>,
+    /// Set for books built from a search listing, which still need a full scrape
+    pub partial: bool,
+    /// Publication status as the source labels it, lowercased, e.g. `completed`
+    pub status: Option<String>,
+    /// Library category the book is filed under, `None` for uncategorised
+    pub category: Option<String>,
 }
 
 #[derive(Clone, Debug, Default, PartialEq)]
@@ -21,4 +43,58 @@ pub struct Chapter {
     pub number: Option<u32>,
     pub name: Option<String>,
     pub url: Option<String>,
+    /// When the chapter came out, as the source writes it
+    pub released: Option<String>,
+}
+
+/// Where a book was left off
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Progress {
+    pub chapter_url: String,
+    /// Page within the chapter, 0 when reading in scroll mode
+    pub page: usize,
+}
+
+/// A book read lately and where it was left, see
+/// [`data::DataManager::get_history`]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HistoryEntry {
+    pub book: Book,
+    pub progress: Progress,
+    pub chapter_name: Option<String>,
+    /// Position of the chapter in the book's list, `None` if it is gone
+    pub chapter_index: Option<usize>,
+    /// Unix time it was last read
+    pub updated: i64,
+}
+
+/// What the library sorts and filters a book by, see
+/// [`data::DataManager::get_library_stats`]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LibraryStats {
+    /// Unix time the book joined the library, 0 for books added before it was kept
+    pub added: i64,
+    /// Unix time the book was last read, if ever
+    pub last_read: Option<i64>,
+    /// Release date of the newest chapter, as the source writes it
+    pub last_updated: Option<String>,
+    pub chapters: usize,
+    pub unread: usize,
+    pub downloaded: usize,
+}
+
+/// A book name without the characters file systems object to, `fallback`
+/// if nothing is left
+pub(crate) fn file_name(name: &str, fallback: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect::<String>();
+    match name.trim() {
+        "" => fallback.to_string(),
+        name => name.to_string(),
+    }
 }
@@ -0,0 +1,176 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! A few long-lived SQLite connections shared by the UI and background
+//! tasks. The database runs in WAL mode so reads carry on while a download
+//! writes.
+
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use rusqlite::Connection;
+
+use super::Result;
+
+/// Connections kept open once returned, more can be open while busy
+const MAX_IDLE: usize = 4;
+/// Connections open at once, [`Pool::get`] waits for one to come back past it
+const MAX_OPEN: usize = 8;
+/// Prepared statements each connection keeps for `prepare_cached`
+const STATEMENT_CACHE: usize = 64;
+/// How long a writer waits for another before giving up with SQLITE_BUSY
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+pub struct Pool {
+    path: PathBuf,
+    state: Mutex<State>,
+    /// Signalled when a connection comes back or is closed
+    returned: Condvar,
+}
+
+#[derive(Debug)]
+struct State {
+    idle: Vec<Connection>,
+    /// Idle and on loan
+    open: usize,
+}
+
+/// A connection on loan, it goes back to the pool when dropped
+pub struct PooledConnection {
+    pool: Arc<Pool>,
+    conn: Option<Connection>,
+}
+
+impl Pool {
+    /// Opens the database, switching it to WAL mode
+    pub fn open(path: &Path) -> Result<Pool> {
+        let conn = Self::connect(path)?;
+        // Stored in the file, so only needs setting once
+        let mode: String = conn.query_row("PRAGMA journal_mode = WAL;", (), |row| row.get(0))?;
+        if !mode.eq_ignore_ascii_case("wal") {
+            tracing::warn!("database stayed in {mode} journal mode");
+        }
+
+        Ok(Pool {
+            path: path.to_path_buf(),
+            state: Mutex::new(State {
+                idle: vec![conn],
+                open: 1,
+            }),
+            returned: Condvar::new(),
+        })
+    }
+
+    /// The database file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// An idle connection, or a new one while fewer than [`MAX_OPEN`] are
+    /// open. Otherwise blocks until one is returned, so a thread must not ask
+    /// for a second connection while holding one
+    pub fn get(self: &Arc<Self>) -> Result<PooledConnection> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(conn) = state.idle.pop() {
+                return Ok(self.loan(conn));
+            }
+            if state.open < MAX_OPEN {
+                break;
+            }
+            state = self.returned.wait(state).unwrap();
+        }
+        state.open += 1;
+        drop(state);
+
+        match Self::connect(&self.path) {
+            Ok(conn) => Ok(self.loan(conn)),
+            Err(e) => {
+                self.state.lock().unwrap().open -= 1;
+                self.returned.notify_one();
+                Err(e)
+            }
+        }
+    }
+
+    fn loan(self: &Arc<Self>, conn: Connection) -> PooledConnection {
+        PooledConnection {
+            pool: Arc::clone(self),
+            conn: Some(conn),
+        }
+    }
+
+    fn connect(path: &Path) -> Result<Connection> {
+        let conn = Connection::open(path)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        // WAL keeps the database consistent on a crash at this level, only
+        // the last commits can be lost on power failure
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        conn.set_prepared_statement_cache_capacity(STATEMENT_CACHE);
+        Ok(conn)
+    }
+}
+
+impl Deref for PooledConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledConnection {
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.conn.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledConnection {
+    fn drop(&mut self) {
+        let Some(conn) = self.conn.take() else {
+            return;
+        };
+        let mut state = self.pool.state.lock().unwrap();
+        if state.idle.len() < MAX_IDLE {
+            state.idle.push(conn);
+        } else {
+            state.open -= 1;
+            drop(state);
+            // Closed outside the lock
+            drop(conn);
+        }
+        self.pool.returned.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_waits_for_a_connection_once_all_are_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = Arc::new(Pool::open(&dir.path().join("data.db")).unwrap());
+        let mut loaned: Vec<_> = (0..MAX_OPEN).map(|_| pool.get().unwrap()).collect();
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let waiting = Arc::clone(&pool);
+        let waiter = std::thread::spawn(move || {
+            let conn = waiting.get().unwrap();
+            tx.send(()).unwrap();
+            drop(conn);
+        });
+        assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+
+        loaned.pop();
+        rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        waiter.join().unwrap();
+
+        drop(loaned);
+        let state = pool.state.lock().unwrap();
+        assert_eq!(state.idle.len(), MAX_IDLE);
+        assert_eq!(state.open, MAX_IDLE);
+    }
+}
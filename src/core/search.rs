@@ -0,0 +1,317 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Library search queries such as `author:foo tag:litrpg -tag:harem dragon`.
+//! Words without a field are fuzzy matched against the name, author, tags
+//! and source and looked up in the description. Field words have to appear
+//! in that field, and a leading `-` turns any word into an exclusion.
+
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+
+use super::Book;
+
+/// Score of free text found in the description alone, below any decent
+/// match on the name
+const DESCRIPTION_SCORE: i64 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    /// A word without a field, only kept when negated
+    Any,
+    Author,
+    Tag,
+    Source,
+    Status,
+    Category,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Field> {
+        match name.to_lowercase().as_str() {
+            "author" => Some(Field::Author),
+            "tag" => Some(Field::Tag),
+            "source" => Some(Field::Source),
+            "status" => Some(Field::Status),
+            "category" => Some(Field::Category),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Term {
+    field: Field,
+    /// Lowercased
+    value: String,
+    negated: bool,
+}
+
+impl Term {
+    fn matches(&self, book: &Book) -> bool {
+        let has = |text: &str| text.to_lowercase().contains(&self.value);
+        let found = match self.field {
+            Field::Any => {
+                has(&book.name)
+                    || book.author.as_deref().is_some_and(has)
+                    || book.tags.iter().any(|t| has(t))
+                    || book.description.as_deref().is_some_and(has)
+            }
+            Field::Author => book.author.as_deref().is_some_and(has),
+            Field::Tag => book.tags.iter().any(|t| has(t)),
+            Field::Source => has(&book.source),
+            Field::Status => book.status.as_deref().is_some_and(has),
+            Field::Category => book.category.as_deref().is_some_and(has),
+        };
+        found != self.negated
+    }
+}
+
+/// How well a book matched, see [`Query::score`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Match {
+    pub score: i64,
+    /// Char positions in the name the free text matched, for highlighting
+    pub name_indices: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Query {
+    /// The words without a field, joined by spaces
+    text: String,
+    terms: Vec<Term>,
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Query {
+        let mut words = vec![];
+        let mut terms = vec![];
+        for word in input.split_whitespace() {
+            let (negated, word) = match word.strip_prefix('-') {
+                Some(rest) if !rest.is_empty() => (true, rest),
+                _ => (false, word),
+            };
+            // Unknown fields are searched for as they are typed
+            let term = word.split_once(':').and_then(|(field, value)| {
+                Some((Field::parse(field)?, value)).filter(|(_, value)| !value.is_empty())
+            });
+            match (term, negated) {
+                (Some((field, value)), _) => terms.push(Term {
+                    field,
+                    value: value.to_lowercase(),
+                    negated,
+                }),
+                (None, true) => terms.push(Term {
+                    field: Field::Any,
+                    value: word.to_lowercase(),
+                    negated,
+                }),
+                (None, false) => words.push(word),
+            }
+        }
+        Query {
+            text: words.join(" "),
+            terms,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty() && self.terms.is_empty()
+    }
+
+    /// Whether there is free text to rank the matches by
+    pub fn is_ranked(&self) -> bool {
+        !self.text.is_empty()
+    }
+
+    /// Whether this query only finds books `from` found as well, so only
+    /// those need scoring again. Words that grow or get added narrow the
+    /// search, a growing exclusion lets more books through
+    pub fn narrows(&self, from: &Query) -> bool {
+        self.text.starts_with(&from.text)
+            && self.terms.len() >= from.terms.len()
+            && self.terms.iter().all(|t| !t.negated)
+            && from.terms.iter().zip(&self.terms).all(|(old, new)| {
+                old.field == new.field && !old.negated && new.value.starts_with(&old.value)
+            })
+    }
+
+    /// `None` if the book doesn't match, else its score, 0 without free text
+    pub fn score(&self, book: &Book, matcher: &SkimMatcherV2) -> Option<Match> {
+        if !self.terms.iter().all(|t| t.matches(book)) {
+            return None;
+        }
+        if self.text.is_empty() {
+            return Some(Match::default());
+        }
+
+        // The name counts most, a match only in the description least
+        let name = matcher.fuzzy_indices(&book.name, &self.text);
+        let fuzzy = |text: &str| matcher.fuzzy_match(text, &self.text);
+        let author = book.author.as_deref().and_then(fuzzy).map(|s| s * 2);
+        let tags = book
+            .tags
+            .iter()
+            .filter_map(|t| fuzzy(t))
+            .max()
+            .map(|s| s * 2);
+        let source = fuzzy(&book.source);
+        let text = self.text.to_lowercase();
+        let description = book
+            .description
+            .as_deref()
+            .filter(|d| {
+                let d = d.to_lowercase();
+                text.split(' ').all(|word| d.contains(word))
+            })
+            .map(|_| DESCRIPTION_SCORE);
+
+        let score = [
+            name.as_ref().map(|(s, _)| s * 3),
+            author,
+            tags,
+            source,
+            description,
+        ]
+        .into_iter()
+        .flatten()
+        .max()?;
+        Some(Match {
+            score,
+            name_indices: name.map(|(_, indices)| indices).unwrap_or_default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn term(field: Field, value: &str, negated: bool) -> Term {
+        Term {
+            field,
+            value: value.into(),
+            negated,
+        }
+    }
+
+    fn book(name: &str, author: &str, tags: &[&str], description: &str) -> Book {
+        Book {
+            name: name.into(),
+            source: "https://www.royalroad.com".into(),
+            author: Some(author.into()),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            description: Some(description.into()),
+            ..Default::default()
+        }
+    }
+
+    fn names(query: &str, books: &[Book]) -> Vec<String> {
+        let matcher = SkimMatcherV2::default();
+        let query = Query::parse(query);
+        books
+            .iter()
+            .filter(|b| query.score(b, &matcher).is_some())
+            .map(|b| b.name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn parses_fields_and_free_text() {
+        let query = Query::parse("  author:Foo dragon TAG:LitRPG  Rider ");
+        assert_eq!(query.text, "dragon Rider");
+        assert_eq!(
+            query.terms,
+            vec![
+                term(Field::Author, "foo", false),
+                term(Field::Tag, "litrpg", false)
+            ]
+        );
+        assert!(Query::parse(" ").is_empty());
+    }
+
+    #[test]
+    fn dash_negates_fields_and_words() {
+        let query = Query::parse("-tag:harem -Slow");
+        assert_eq!(query.text, "");
+        assert_eq!(
+            query.terms,
+            vec![
+                term(Field::Tag, "harem", true),
+                term(Field::Any, "slow", true)
+            ]
+        );
+        // A dash on its own is just text
+        assert_eq!(Query::parse("- a -").text, "- a -");
+    }
+
+    #[test]
+    fn unknown_fields_and_empty_values_are_text() {
+        let query = Query::parse("genre:fantasy tag: re:zero");
+        assert_eq!(query.text, "genre:fantasy tag: re:zero");
+        assert!(query.terms.is_empty());
+
+        assert_eq!(
+            Query::parse("-tag:").terms,
+            vec![term(Field::Any, "tag:", true)]
+        );
+    }
+
+    #[test]
+    fn typing_on_narrows() {
+        let narrows = |from: &str, to: &str| Query::parse(to).narrows(&Query::parse(from));
+        assert!(narrows("", "dra"));
+        assert!(narrows("dra", "drag"));
+        assert!(narrows("dragon", "dragon rider"));
+        assert!(narrows("tag:lit", "tag:litrpg"));
+        assert!(narrows("tag:litrpg", "tag:litrpg author:a"));
+
+        assert!(!narrows("drag", "dra"));
+        assert!(!narrows("tag:litrpg", "tag:lit"));
+        assert!(!narrows("tag:lit", "author:lit"));
+        assert!(!narrows("tag:litrpg author:a", "tag:litrpg"));
+    }
+
+    #[test]
+    fn exclusions_never_narrow() {
+        let narrows = |from: &str, to: &str| Query::parse(to).narrows(&Query::parse(from));
+        // -tag:h hides every tag starting with h, -tag:harem fewer
+        assert!(!narrows("-tag:h", "-tag:harem"));
+        assert!(!narrows("dragon", "dragon -slow"));
+        assert!(!narrows("-slow", "-slow dragon"));
+    }
+
+    #[test]
+    fn terms_filter_the_books() {
+        let books = [
+            book("Dragon Rider", "Ann", &["Fantasy", "Harem"], ""),
+            book("Mage School", "Bob", &["LitRPG"], "no dragons here"),
+            book("Sky Ships", "Ann", &["LitRPG", "Fantasy"], ""),
+        ];
+        assert_eq!(names("author:ann", &books), ["Dragon Rider", "Sky Ships"]);
+        assert_eq!(names("author:ann -tag:harem", &books), ["Sky Ships"]);
+        assert_eq!(names("tag:litrpg", &books), ["Mage School", "Sky Ships"]);
+        assert_eq!(names("-dragon", &books), ["Sky Ships"]);
+        assert!(names("status:completed", &books).is_empty());
+    }
+
+    #[test]
+    fn name_matches_rank_above_the_description() {
+        let matcher = SkimMatcherV2::default();
+        let in_name = book("Dragon Rider", "Ann", &[], "");
+        let in_description = book("Other", "Bob", &[], "A story with a Dragon in it");
+        let query = Query::parse("dragon");
+
+        let name = query.score(&in_name, &matcher).unwrap();
+        let description = query.score(&in_description, &matcher).unwrap();
+        assert!(name.score > description.score);
+        assert_eq!(name.name_indices, vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(description.score, DESCRIPTION_SCORE);
+        assert!(description.name_indices.is_empty());
+
+        assert_eq!(Query::parse("zzz").score(&in_name, &matcher), None);
+        assert_eq!(
+            Query::parse("author:ann").score(&in_name, &matcher),
+            Some(Match::default())
+        );
+    }
+}
@@ -1,22 +1,95 @@
 pub mod royalroad;
 use super::Book;
 use super::Chapter;
+use super::{Error, Result};
 use async_trait::async_trait;
+use once_cell::sync::Lazy;
 
 #[async_trait]
 pub trait Source: Send + Sync {
     fn as_str(&self) -> String;
-    async fn search(&self, term: String) -> Result<Vec<String>, Box<dyn std::error::Error>>;
-
-    async fn scrape_book(&self, url: String) -> Result<Book, Box<dyn std::error::Error>>;
-    async fn scrape_chapter(
-        &self,
-        url: String,
-    ) -> Result<(Chapter, Option<String>), Box<dyn std::error::Error>>;
-    async fn download_chapter(
-        &self,
-        chapter: &Chapter,
-    ) -> Result<String, Box<dyn std::error::Error>>;
+    /// Stable id of the book at the url, the same for every form of the url
+    /// and across renames. `None` if the url isn't a book of this source
+    fn book_id(&self, url: &url::Url) -> Option<String>;
+    /// Stable id of the chapter at the url, like [`Source::book_id`]
+    fn chapter_id(&self, url: &url::Url) -> Option<String>;
+    /// Returns partial books built from the search page alone, see [`Book::partial`]
+    async fn search(&self, term: String) -> Result<Vec<Book>>;
+
+    async fn scrape_book(&self, url: String) -> Result<Book>;
+    /// Lists the chapters of a book in reading order
+    async fn scrape_chapter_list(&self, book_url: String) -> Result<Vec<Chapter>>;
+    async fn scrape_chapter(&self, url: String) -> Result<(Chapter, Option<String>)>;
+    async fn download_chapter(&self, chapter: &Chapter) -> Result<String>;
+}
+
+/// Every source the app knows about
+pub fn all() -> Vec<Box<dyn Source>> {
+    vec![Box::new(RoyalRoadSource::new())]
+}
+
+/// Every source by the host it serves, for lookups by url
+static SOURCES: Lazy<Vec<(String, Box<dyn Source>)>> = Lazy::new(|| {
+    all()
+        .into_iter()
+        .filter_map(|source| {
+            let url = url::Url::parse(&source.as_str()).ok()?;
+            Some((host(&url)?.to_owned(), source))
+        })
+        .collect()
+});
+
+/// Finds the source that handles the url, matching on the host so `http`
+/// and a missing `www` still find it
+pub fn for_url(url: &str) -> Result<&'static dyn Source> {
+    url::Url::parse(url)
+        .ok()
+        .and_then(|parsed| for_parsed(&parsed))
+        .ok_or(Error::UnsupportedSource(url.to_owned()))
+}
+
+fn for_parsed(url: &url::Url) -> Option<&'static dyn Source> {
+    let wanted = host(url)?;
+    SOURCES
+        .iter()
+        .find(|(host, _)| host == wanted)
+        .map(|(_, source)| source.as_ref())
+}
+
+/// The key books are stored and compared under. Urls no source has an id
+/// for fall back to their host and path, without `www` or a trailing slash
+pub fn book_id(url: &str) -> String {
+    canonical(url, |source, parsed| source.book_id(parsed))
+}
+
+/// The key chapter text, read marks, places and annotations are stored
+/// under, falling back like [`book_id`]
+pub fn chapter_id(url: &str) -> String {
+    canonical(url, |source, parsed| source.chapter_id(parsed))
+}
+
+fn canonical(url: &str, id: impl Fn(&dyn Source, &url::Url) -> Option<String>) -> String {
+    let url = url.trim();
+    let Ok(parsed) = url::Url::parse(url) else {
+        return url.to_owned();
+    };
+    if let Some(id) = for_parsed(&parsed).and_then(|source| id(source, &parsed)) {
+        return id;
+    }
+    match host(&parsed) {
+        Some(host) => format!("{host}{}", parsed.path().trim_end_matches('/')),
+        None => url.to_owned(),
+    }
+}
+
+/// The url's host without `www`
+fn host(url: &url::Url) -> Option<&str> {
+    url.host_str().map(|host| host.trim_start_matches("www."))
+}
+
+/// Parses a CSS selector, failures carry the selector so broken parsers are easy to spot
+pub(crate) fn selector(selector: &str) -> Result<scraper::Selector> {
+    scraper::Selector::parse(selector).map_err(|_| Error::parse(selector))
 }
 
 use core::fmt::Debug;
@@ -2,9 +2,12 @@ use super::RoyalRoadSource;
 use crate::core::book::*;
 use crate::core::chapter::*;
 use crate::core::source::*;
+use crate::core::{Error, Result};
 use async_trait::async_trait;
 
 const HOST: &str = "https://www.royalroad.com";
+/// Fiction page labels that are a publication status rather than a type
+const STATUSES: [&str; 5] = ["ongoing", "completed", "hiatus", "stub", "dropped"];
 
 #[async_trait]
 impl Source for RoyalRoadSource {
@@ -12,93 +15,183 @@ impl Source for RoyalRoadSource {
         HOST.into()
     }
 
-    async fn search(&self, term: String) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    /// The fiction number, the slug after it follows the title
+    fn book_id(&self, url: &url::Url) -> Option<String> {
+        let mut segments = url.path_segments()?;
+        if segments.next()? != "fiction" {
+            return None;
+        }
+        let number = segments.next()?.parse::<u64>().ok()?;
+        Some(format!("royalroad:{number}"))
+    }
+
+    /// The chapter number, both slugs around it follow renames
+    fn chapter_id(&self, url: &url::Url) -> Option<String> {
+        let mut segments = url.path_segments()?;
+        if segments.next()? != "fiction" {
+            return None;
+        }
+        segments.find(|s| *s == "chapter")?;
+        let number = segments.next()?.parse::<u64>().ok()?;
+        Some(format!("royalroad:chapter:{number}"))
+    }
+
+    #[tracing::instrument(skip(self), err)]
+    async fn search(&self, term: String) -> Result<Vec<Book>> {
         let search_ext = "/fictions/search?title=";
         let term = term.replace(" ", "+");
         let url = HOST.to_string() + search_ext + term.as_str();
 
         let document = RoyalRoadSource::get_document_from_url(url.clone()).await?;
-        let binding = &scraper::Selector::parse(".fiction-list-item").unwrap();
-        let search_entries = document.select(binding).into_iter();
+        let search_entries = document.select(&selector(".fiction-list-item")?);
+
+        let title_selector = selector("h2.fiction-title a")?;
+        let cover_selector = selector("figure img")?;
+        let tag_selector = selector(".tags a.fiction-tag")?;
+        let description_selector = selector("div[id^=description]")?;
 
-        let mut results: Vec<String> = vec![];
+        let mut results: Vec<Book> = vec![];
         for e in search_entries {
-            let url = e
-                .select(&scraper::Selector::parse("h2").unwrap())
-                .next()
-                .unwrap()
-                .select(&scraper::Selector::parse("a").unwrap())
+            let Some(title) = e.select(&title_selector).next() else {
+                continue;
+            };
+            let Some(url) = title.value().attr("href").map(|s| HOST.to_owned() + s) else {
+                continue;
+            };
+            let name = title.text().collect::<String>().trim().to_string();
+
+            let img = e
+                .select(&cover_selector)
                 .next()
-                .and_then(|a| a.value().attr("href"))
-                .map(|s| HOST.to_owned() + s);
+                .and_then(|img| img.value().attr("src"))
+                .and_then(RoyalRoadSource::cover_url);
 
-            let url = match url {
-                Some(u) => u,
-                None => continue,
-            };
+            let mut book = Book::new(self.as_str(), url, name, img, false);
+            book.tags = e
+                .select(&tag_selector)
+                .map(|a| a.text().collect::<String>().trim().to_string())
+                .collect();
+            book.description = e
+                .select(&description_selector)
+                .next()
+                .map(|d| d.text().collect::<String>().trim().to_string());
+            book.partial = true;
 
-            results.push(url);
+            results.push(book);
         }
         Ok(results)
     }
 
-    async fn scrape_book(&self, url: String) -> Result<Book, Box<dyn std::error::Error>> {
+    #[tracing::instrument(skip(self), err)]
+    async fn scrape_book(&self, url: String) -> Result<Book> {
         let document = RoyalRoadSource::get_document_from_url(url.clone()).await?;
 
         let name = document
-            .select(&scraper::Selector::parse("h1.font-white").unwrap())
+            .select(&selector("h1.font-white")?)
             .next()
             .map(|span| span.text().collect::<String>())
-            .ok_or("Failed to retrieve name")?;
+            .ok_or(Error::parse("h1.font-white"))?;
 
-        let mut img = document
-            .select(&scraper::Selector::parse(".thumbnail").unwrap())
+        let img = document
+            .select(&selector(".thumbnail")?)
             .next()
             .and_then(|img| img.value().attr("src"))
-            .map(str::to_owned);
+            .and_then(RoyalRoadSource::cover_url);
 
-        if img == Some("/dist/img/nocover-new-min.png".to_owned()) {
-            img = None;
-        }
+        // A renamed story keeps its number, the canonical link has the current slug
+        let url = document
+            .select(&selector("link[rel=canonical]")?)
+            .next()
+            .and_then(|link| link.value().attr("href"))
+            .filter(|href| book_id(href) == book_id(&url))
+            .map(str::to_owned)
+            .unwrap_or(url);
+
+        let mut book = Book::new(self.as_str(), url, name, img, false);
+        book.author = document
+            .select(&selector(".fic-title h4 a[href^='/profile/']")?)
+            .next()
+            .map(|a| a.text().collect::<String>().trim().to_string())
+            .filter(|a| !a.is_empty());
+        book.tags = document
+            .select(&selector(".tags a.fiction-tag")?)
+            .map(|a| a.text().collect::<String>().trim().to_string())
+            .collect();
+        book.description = document
+            .select(&selector(".description")?)
+            .next()
+            .map(|d| d.text().collect::<String>().trim().to_string());
+        book.status = document
+            .select(&selector(".fiction-info span.label")?)
+            .map(|span| span.text().collect::<String>().trim().to_lowercase())
+            .find(|label| STATUSES.contains(&label.as_str()));
 
-        let book = Book::new(self.as_str(), url, name, img, false);
         Ok(book)
     }
 
-    async fn scrape_chapter(
-        &self,
-        url: String,
-    ) -> Result<(Chapter, Option<String>), Box<dyn std::error::Error>> {
+    #[tracing::instrument(skip(self), err)]
+    async fn scrape_chapter_list(&self, book_url: String) -> Result<Vec<Chapter>> {
+        let document = RoyalRoadSource::get_document_from_url(book_url).await?;
+        let link_selector = selector("td a")?;
+        let time_selector = selector("time")?;
+
+        let chapters = document
+            .select(&selector("tr.chapter-row")?)
+            .filter_map(|row| {
+                let a = row.select(&link_selector).next()?;
+                let url = HOST.to_owned() + a.value().attr("href")?;
+                let name = a.text().collect::<String>().trim().to_string();
+                let released = row
+                    .select(&time_selector)
+                    .next()
+                    .and_then(|time| time.value().attr("datetime"))
+                    .map(str::to_owned);
+                Some((name, url, released))
+            })
+            .enumerate()
+            .map(|(i, (name, url, released))| Chapter {
+                released,
+                ..Chapter::new(Some(i as u32 + 1), Some(name), Some(url))
+            })
+            .collect();
+
+        Ok(chapters)
+    }
+
+    #[tracing::instrument(skip(self), err)]
+    async fn scrape_chapter(&self, url: String) -> Result<(Chapter, Option<String>)> {
         let document = RoyalRoadSource::get_document_from_url(url.clone()).await?;
         let name = document
-            .select(&scraper::Selector::parse(".break-word").unwrap())
+            .select(&selector(".break-word")?)
             .next()
             .map(|h1| h1.text().collect::<String>());
 
+        // The last chapter has no next button
         let next_chapter = document
-            .select(&scraper::Selector::parse("i.far.fa-chevron-double-right.ml-3").unwrap())
+            .select(&selector("i.far.fa-chevron-double-right.ml-3")?)
             .next()
-            .unwrap()
-            .parent()
-            .and_then(|a| scraper::ElementRef::wrap(a).unwrap().value().attr("href"))
-            .map(str::to_string);
+            .and_then(|i| i.parent())
+            .and_then(scraper::ElementRef::wrap)
+            .and_then(|a| a.value().attr("href"))
+            .map(|href| match href.starts_with('/') {
+                true => HOST.to_owned() + href,
+                false => href.to_owned(),
+            });
 
         Ok((Chapter::new(None, name, Some(url)), next_chapter))
     }
 
-    async fn download_chapter(
-        &self,
-        chapter: &Chapter,
-    ) -> Result<String, Box<dyn std::error::Error>> {
+    #[tracing::instrument(skip_all, fields(url = ?chapter.url), err)]
+    async fn download_chapter(&self, chapter: &Chapter) -> Result<String> {
         let Some(url) = &chapter.url else {
-            return Err("No url available".into());
+            return Err(Error::NotFound("chapter url".into()));
         };
         let document = RoyalRoadSource::get_document_from_url(url.clone()).await?;
         let content = document
-            .select(&scraper::Selector::parse(".chapter-content")?)
+            .select(&selector(".chapter-content")?)
             .next()
-            .unwrap()
-            .select(&scraper::Selector::parse("p")?)
+            .ok_or(Error::parse(".chapter-content"))?
+            .select(&selector("p")?)
             .map(|span| span.text().collect::<String>())
             .fold("".to_string(), |acc, c| acc + "\n" + &c);
 
@@ -111,11 +204,16 @@ impl RoyalRoadSource {
         RoyalRoadSource
     }
 
-    async fn get_document_from_url(
-        url: String,
-    ) -> Result<scraper::Html, Box<dyn std::error::Error>> {
-        let response = reqwest::get(url).await;
-        let html_content = response?.text().await?;
+    /// Filters out the placeholder cover RoyalRoad serves for books without one
+    fn cover_url(src: &str) -> Option<String> {
+        if src.contains("nocover") {
+            return None;
+        }
+        Some(src.to_owned())
+    }
+
+    async fn get_document_from_url(url: String) -> Result<scraper::Html> {
+        let html_content = crate::core::http::CACHE.get_text(&url).await?;
         Ok(scraper::Html::parse_document(&html_content))
     }
 
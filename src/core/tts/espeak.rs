@@ -0,0 +1,88 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::path::Path;
+
+use async_trait::async_trait;
+use tokio::process::Command;
+
+use super::{output, run, Cancel, SpeechBackend, Voice};
+use crate::core::Result;
+
+const PROGRAM: &str = "espeak-ng";
+/// espeak-ng's own defaults, words per minute and 0-99 pitch
+const DEFAULT_WPM: f32 = 175.0;
+const DEFAULT_PITCH: i16 = 50;
+
+/// Speaks with a local espeak-ng
+#[derive(Debug, Default)]
+pub struct EspeakBackend;
+
+impl EspeakBackend {
+    /// Rate and pitch in espeak-ng's units
+    fn args(voice: &Voice) -> Vec<String> {
+        let rate = f32::from(voice.rate.clamp(-100, 100)) / 100.0;
+        let wpm = (DEFAULT_WPM * (1.0 + rate)).clamp(80.0, 450.0) as u32;
+        let pitch = (DEFAULT_PITCH + voice.pitch.clamp(-100, 100) / 2).clamp(0, 99);
+
+        let mut args = vec![
+            "-s".to_string(),
+            wpm.to_string(),
+            "-p".to_string(),
+            pitch.to_string(),
+        ];
+        if let Some(name) = &voice.name {
+            args.extend(["-v".to_string(), name.clone()]);
+        }
+        args
+    }
+}
+
+#[async_trait]
+impl SpeechBackend for EspeakBackend {
+    fn as_str(&self) -> &'static str {
+        "espeak-ng"
+    }
+
+    #[tracing::instrument(skip(self), err)]
+    async fn voices(&self) -> Result<Vec<String>> {
+        let mut command = Command::new(PROGRAM);
+        command.arg("--voices");
+
+        // Columns are Pty Language Age/Gender VoiceName File Other, under a header;
+        // the language code is what `-v` takes
+        Ok(output(command)
+            .await?
+            .lines()
+            .skip(1)
+            .filter_map(|line| line.split_whitespace().nth(1))
+            .map(String::from)
+            .collect())
+    }
+
+    #[tracing::instrument(skip_all, fields(len = text.len()), err)]
+    async fn speak(&self, text: &str, voice: &Voice, cancel: &Cancel) -> Result<()> {
+        let mut command = Command::new(PROGRAM);
+        command.args(Self::args(voice)).arg("--").arg(text);
+
+        run(command, cancel).await
+    }
+
+    #[tracing::instrument(skip(self, text, voice), fields(len = text.len()), err)]
+    async fn synthesize(&self, text: &str, voice: &Voice, path: &Path) -> Result<()> {
+        // Whole chapters can be longer than a command line allows
+        let text_path = path.with_extension("txt");
+        tokio::fs::write(&text_path, text).await?;
+
+        let mut command = Command::new(PROGRAM);
+        command
+            .args(Self::args(voice))
+            .arg("-w")
+            .arg(path)
+            .arg("-f")
+            .arg(&text_path);
+        let res = run(command, &Cancel::default()).await;
+
+        _ = tokio::fs::remove_file(&text_path).await;
+        res
+    }
+}
@@ -0,0 +1,41 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+
+use super::{Cancel, SpeechBackend, Voice};
+use crate::core::Result;
+
+/// Says nothing and remembers what it was asked to, for tests
+#[derive(Debug, Default)]
+pub struct FakeBackend {
+    /// Texts spoken to the end
+    pub spoken: Mutex<Vec<String>>,
+    /// Texts cut off before they started
+    pub cancelled: Mutex<Vec<String>>,
+    pub stops: Mutex<usize>,
+}
+
+#[async_trait]
+impl SpeechBackend for FakeBackend {
+    fn as_str(&self) -> &'static str {
+        "fake"
+    }
+
+    async fn voices(&self) -> Result<Vec<String>> {
+        Ok(vec!["fake-voice".into()])
+    }
+
+    async fn speak(&self, text: &str, _voice: &Voice, cancel: &Cancel) -> Result<()> {
+        match cancel.is_cancelled() {
+            true => self.cancelled.lock().unwrap().push(text.into()),
+            false => self.spoken.lock().unwrap().push(text.into()),
+        }
+        Ok(())
+    }
+
+    fn stop(&self) {
+        *self.stops.lock().unwrap() += 1;
+    }
+}
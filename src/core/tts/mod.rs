@@ -0,0 +1,165 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+pub mod espeak;
+#[cfg(test)]
+pub mod fake;
+pub mod speech_dispatcher;
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::process::Command;
+use tokio::sync::Notify;
+
+use super::{Error, Result};
+pub use espeak::EspeakBackend;
+pub use speech_dispatcher::SpeechDispatcherBackend;
+
+/// How the text is spoken, rate and pitch run from -100 to 100 around the
+/// engine's default
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Voice {
+    pub rate: i16,
+    pub pitch: i16,
+    /// Engine voice name, `None` uses the engine default
+    pub name: Option<String>,
+}
+
+/// Cuts off speech, also speech that only starts after [`Cancel::cancel`]
+/// was called. Clones share the flag
+#[derive(Debug, Clone, Default)]
+pub struct Cancel(Arc<(AtomicBool, Notify)>);
+
+impl Cancel {
+    pub fn cancel(&self) {
+        self.0 .0.store(true, Ordering::SeqCst);
+        self.0 .1.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0 .0.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once cancelled
+    pub async fn cancelled(&self) {
+        let notified = self.0 .1.notified();
+        tokio::pin!(notified);
+        // Registered before checking the flag so a cancel in between still wakes it
+        notified.as_mut().enable();
+        if self.is_cancelled() {
+            return;
+        }
+        notified.await;
+    }
+}
+
+/// A local text-to-speech engine
+#[async_trait]
+pub trait SpeechBackend: Send + Sync {
+    fn as_str(&self) -> &'static str;
+    /// Voice names the engine offers
+    async fn voices(&self) -> Result<Vec<String>>;
+    /// Speaks the text, returning once it has been said or `cancel` fires
+    async fn speak(&self, text: &str, voice: &Voice, cancel: &Cancel) -> Result<()>;
+    /// Silences the engine beyond what cancelling [`Self::speak`] does. Called
+    /// from the UI thread, so it must not wait
+    fn stop(&self) {}
+    /// Renders the text to a WAV file instead of the speakers
+    async fn synthesize(&self, _text: &str, _voice: &Voice, _path: &Path) -> Result<()> {
+        Err(Error::Speech(format!(
+            "{} can't write audio files",
+            self.as_str()
+        )))
+    }
+}
+
+/// The engines the app can drive, the first is the default
+#[derive(Clone)]
+pub struct Backends {
+    backends: Vec<Arc<dyn SpeechBackend>>,
+    names: Vec<&'static str>,
+}
+
+impl Default for Backends {
+    fn default() -> Self {
+        Self::new(vec![
+            Arc::new(SpeechDispatcherBackend),
+            Arc::new(EspeakBackend),
+        ])
+    }
+}
+
+impl Backends {
+    /// Panics without any engine
+    pub fn new(backends: Vec<Arc<dyn SpeechBackend>>) -> Self {
+        assert!(!backends.is_empty(), "no speech engines");
+        let names = backends.iter().map(|backend| backend.as_str()).collect();
+        Self { backends, names }
+    }
+
+    /// Engine names for pickers
+    pub fn names(&self) -> &[&'static str] {
+        &self.names
+    }
+
+    /// The first engine that can write audio files, preferring the named one
+    pub fn for_synthesis(&self, name: &str) -> Arc<dyn SpeechBackend> {
+        // speech-dispatcher only talks to the sound server
+        match name {
+            "speech-dispatcher" => self.for_name("espeak-ng"),
+            name => self.for_name(name),
+        }
+    }
+
+    /// The engine with the name, unknown names get the first one
+    pub fn for_name(&self, name: &str) -> Arc<dyn SpeechBackend> {
+        self.backends
+            .iter()
+            .find(|backend| backend.as_str() == name)
+            .unwrap_or(&self.backends[0])
+            .clone()
+    }
+}
+
+/// Runs an engine's command until it exits or `cancel` fires
+pub(crate) async fn run(mut command: Command, cancel: &Cancel) -> Result<()> {
+    if cancel.is_cancelled() {
+        return Ok(());
+    }
+    let program = command.as_std().get_program().to_string_lossy().to_string();
+    let mut child = command
+        .stdout(std::process::Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| Error::Speech(format!("{program}: {e}")))?;
+
+    tokio::select! {
+        status = child.wait() => match status {
+            Ok(status) if status.success() => Ok(()),
+            Ok(status) => Err(Error::Speech(format!("{program} exited with {status}"))),
+            Err(e) => Err(Error::Speech(format!("{program}: {e}"))),
+        },
+        _ = cancel.cancelled() => {
+            _ = child.kill().await;
+            Ok(())
+        }
+    }
+}
+
+/// Runs an engine's command and returns what it printed
+pub(crate) async fn output(mut command: Command) -> Result<String> {
+    let program = command.as_std().get_program().to_string_lossy().to_string();
+    let output = command
+        .output()
+        .await
+        .map_err(|e| Error::Speech(format!("{program}: {e}")))?;
+    if !output.status.success() {
+        return Err(Error::Speech(format!(
+            "{program} exited with {}",
+            output.status
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
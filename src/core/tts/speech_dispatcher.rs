@@ -0,0 +1,61 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use async_trait::async_trait;
+use tokio::process::Command;
+
+use super::{output, run, Cancel, SpeechBackend, Voice};
+use crate::core::Result;
+
+const PROGRAM: &str = "spd-say";
+
+/// Speaks through the speech-dispatcher daemon with its `spd-say` client
+#[derive(Debug, Default)]
+pub struct SpeechDispatcherBackend;
+
+#[async_trait]
+impl SpeechBackend for SpeechDispatcherBackend {
+    fn as_str(&self) -> &'static str {
+        "speech-dispatcher"
+    }
+
+    #[tracing::instrument(skip(self), err)]
+    async fn voices(&self) -> Result<Vec<String>> {
+        let mut command = Command::new(PROGRAM);
+        command.arg("--list-synthesis-voices");
+
+        // The first line is the NAME LANGUAGE VARIANT header
+        Ok(output(command)
+            .await?
+            .lines()
+            .skip(1)
+            .filter_map(|line| line.split_whitespace().next())
+            .map(String::from)
+            .collect())
+    }
+
+    #[tracing::instrument(skip_all, fields(len = text.len()), err)]
+    async fn speak(&self, text: &str, voice: &Voice, cancel: &Cancel) -> Result<()> {
+        let mut command = Command::new(PROGRAM);
+        command
+            .arg("--wait")
+            .args(["--rate", &voice.rate.clamp(-100, 100).to_string()])
+            .args(["--pitch", &voice.pitch.clamp(-100, 100).to_string()]);
+        if let Some(name) = &voice.name {
+            command.args(["--synthesis-voice", name]);
+        }
+        command.arg("--").arg(text);
+
+        run(command, cancel).await
+    }
+
+    fn stop(&self) {
+        // Killing the client leaves the daemon talking. Stopping runs on the
+        // UI thread, so the cancel is reaped on a thread of its own
+        match std::process::Command::new(PROGRAM).arg("--cancel").spawn() {
+            Ok(mut child) => {
+                std::thread::spawn(move || child.wait());
+            }
+            Err(e) => tracing::warn!("failed to cancel speech: {e}"),
+        }
+    }
+}
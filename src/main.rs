@@ -1,16 +1,39 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
 use app::App;
+use cosmic::Application;
 /// The `app` module is used by convention to indicate the main component of our application.
 mod app;
+mod cli;
+mod config;
 mod core;
 
 /// The `cosmic::app::run()` function is the starting point of your application.
 /// It takes two arguments:
 /// - `settings` is a structure that contains everything relevant with your app's configuration, such as antialiasing, themes, icons, etc...
-/// - `()` is the flags that your app needs to use before it starts.
-///  If your app does not need any flags, you can pass in `()`.
+/// - `flags` is the data that your app needs to use before it starts, here the loaded config.
 fn main() -> cosmic::iced::Result {
+    // Keep the guard alive so buffered log lines reach the file on exit
+    let _log_guard = match dirs::data_local_dir() {
+        Some(dir) => core::logging::init(dir.join(App::APP_ID).join("logs"))
+            .map_err(|e| eprintln!("failed to set up logging: {e}"))
+            .ok(),
+        None => None,
+    };
+
+    let (config_handler, config) = config::Config::load(App::APP_ID);
+
+    // Subcommands run headless and exit
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let data_dir = dirs::data_local_dir().map(|dir| dir.join(App::APP_ID));
+    if let Some(code) = cli::run(&args, &config, data_dir) {
+        std::process::exit(code);
+    }
+    let flags = app::Flags {
+        config_handler,
+        config,
+    };
+
     let settings = cosmic::app::Settings::default();
-    cosmic::app::run::<App>(settings, ())
+    cosmic::app::run::<App>(settings, flags)
 }